@@ -1,6 +1,7 @@
 use std::{fmt::Debug, sync::Arc};
 
 use crate::{
+    extract::AddState,
     middleware::{Next, SharedMiddleware},
     Endpoint, Middleware, Request, Response,
 };
@@ -10,6 +11,8 @@ use crate::{
 pub struct App<E: Endpoint> {
     endpoint: E,
     middlewares: Vec<SharedMiddleware>,
+    // The number of states at the end of `middlewares`, which run before any other middleware.
+    states: usize,
 }
 
 impl<E: Endpoint> Debug for App<E> {
@@ -27,12 +30,14 @@ impl<E: Endpoint> App<E> {
         Self {
             endpoint,
             middlewares: Vec::new(),
+            states: 0,
         }
     }
 
     /// Add a shared middleware to this app.
     pub fn add_middleware(&mut self, middleware: SharedMiddleware) {
-        self.middlewares.push(middleware);
+        let index = self.middlewares.len() - self.states;
+        self.middlewares.insert(index, middleware);
     }
 
     /// Add a middleware to this app.
@@ -41,6 +46,15 @@ impl<E: Endpoint> App<E> {
         self
     }
 
+    /// Share a state with all requests, which can be extracted by `extract::State`.
+    /// The state is cloned into request extensions before any middleware runs,
+    /// whether the middleware is added before or after the state.
+    pub fn state<T: Clone + Send + Sync + 'static>(mut self, state: T) -> Self {
+        self.middlewares.push(Arc::new(AddState(state)));
+        self.states += 1;
+        self
+    }
+
     /// Run the app with a provided request.
     pub async fn run(&self, mut request: Request) -> crate::Result<Response> {
         Next::new(&self.middlewares, &self.endpoint)
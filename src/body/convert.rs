@@ -78,7 +78,9 @@ impl From<Pin<Box<dyn AsyncBufRead + Send + Sync + 'static>>> for Body {
             inner: BodyInner::Reader {
                 reader,
                 length: None,
+                replay: None,
             },
+            trailers: None,
         }
     }
 }
@@ -1,4 +1,4 @@
-use super::{BodyFrozen, BoxStdError};
+use super::{BodyFrozen, BodyLengthMismatch, BodyTooLarge, BoxStdError};
 use std::error::Error as StdError;
 use std::fmt::Display;
 use std::io;
@@ -14,6 +14,12 @@ pub enum Error {
     Utf8(Utf8Error),
     /// The body has been consumed and can not provide data anymore.It is distinguished from a normal empty body.
     BodyFrozen,
+    /// The body exceeds the size limit set by [`Body::limit`](super::Body::limit).
+    TooLarge,
+    /// The length of the data does not match the declared length of the body.
+    LengthMismatch,
+    /// The data is corrupt for its content coding.
+    Decode(io::Error),
     #[cfg(feature = "json")]
     /// Fail to serialize/deserialize object to JSON.
     JsonError(serde_json::Error),
@@ -28,7 +34,7 @@ pub enum Error {
 }
 
 macro_rules! impl_body_error {
-    ($(($field:tt,$ty:ty $(,$feature:tt)?)),*) => {
+    ($(($field:tt,$ty:ty $(,$feature:tt)?)),*;$($wrapper:tt),*) => {
         $(
             $(#[cfg(feature = $feature)])*
             impl From<$ty> for Error {
@@ -45,7 +51,10 @@ macro_rules! impl_body_error {
                         $(#[cfg(feature = $feature)])*
                         Self::$field(error) => error.fmt(f),
                     )*
+                    $(Self::$wrapper(error) => error.fmt(f),)*
                     Self::BodyFrozen => BodyFrozen::new().fmt(f),
+                    Self::TooLarge => BodyTooLarge::new().fmt(f),
+                    Self::LengthMismatch => BodyLengthMismatch::new().fmt(f),
                 }
             }
         }
@@ -57,7 +66,8 @@ macro_rules! impl_body_error {
                         $(#[cfg(feature = $feature)])*
                         Self::$field(error) => error.source(),
                     )*
-                    Error::BodyFrozen => None,
+                    $(Self::$wrapper(error) => error.source(),)*
+                    Error::BodyFrozen | Error::TooLarge | Error::LengthMismatch => None,
                 }
             }
         }
@@ -66,14 +76,58 @@ macro_rules! impl_body_error {
 }
 
 impl_body_error![
-    (Io, io::Error),
     (Utf8, Utf8Error),
-    (Other, BoxStdError),
     (JsonError, serde_json::Error, "json"),
     (SerializeForm, serde_urlencoded::ser::Error, "form"),
-    (DeserializeForm, serde_urlencoded::de::Error, "form")
+    (DeserializeForm, serde_urlencoded::de::Error, "form");
+    Io,
+    Decode,
+    Other
 ];
 
+// Errors of `Body` travel through readers and streams wrapped in `io::Error` or a boxed error,
+// so that they are unwrapped here to keep the original variant.
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        if error.get_ref().is_some_and(|inner| inner.is::<Self>()) {
+            *error.into_inner().unwrap().downcast::<Self>().unwrap()
+        } else {
+            Self::Io(error)
+        }
+    }
+}
+
+impl From<BoxStdError> for Error {
+    fn from(error: BoxStdError) -> Self {
+        match error.downcast::<Self>() {
+            Ok(error) => *error,
+            Err(error) => match error.downcast::<io::Error>() {
+                Ok(error) => (*error).into(),
+                Err(error) => Self::Other(error),
+            },
+        }
+    }
+}
+
+impl From<BodyTooLarge> for Error {
+    fn from(_error: BodyTooLarge) -> Self {
+        Self::TooLarge
+    }
+}
+
+impl Error {
+    // Convert into `crate::Error`, an exceeded size limit is reported as `413 Payload Too Large`
+    // and malformed data as `400 Bad Request`.
+    pub(crate) fn into_http_error(self) -> crate::Error {
+        let status = match self {
+            Self::TooLarge => http::StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Utf8(_) | Self::Decode(_) | Self::LengthMismatch => http::StatusCode::BAD_REQUEST,
+            _ => http::StatusCode::SERVICE_UNAVAILABLE,
+        };
+        crate::Error::new(self, status)
+    }
+}
+
 impl From<BodyFrozen> for Error {
     fn from(_error: BodyFrozen) -> Self {
         Self::BodyFrozen
@@ -1,16 +1,20 @@
 mod convert;
 mod error_type;
+#[cfg(feature = "fs")]
+mod spool;
+mod tee;
 mod utils;
 pub use error_type::Error;
 use futures_lite::{ready, Stream, StreamExt};
 
-use self::utils::IntoAsyncRead;
+use self::utils::{Exact, IntoAsyncRead, Limited};
 use bytestr::ByteStr;
 
 use bytes::Bytes;
-use futures_lite::{AsyncBufRead, AsyncBufReadExt};
+use futures_lite::{AsyncBufRead, AsyncReadExt};
 
 use std::fmt::Debug;
+use std::future::Future;
 use std::mem::{replace, swap, take};
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -22,12 +26,26 @@ type BoxStream = Pin<Box<dyn Stream<Item = Result<Bytes, BoxStdError>> + Send +
 // A boxed bufreader object.
 type BoxBufReader = Pin<Box<dyn AsyncBufRead + Send + Sync + 'static>>;
 
+// A function creating the body again, reading from the beginning.
+type Replay = std::sync::Arc<dyn Fn() -> Body + Send + Sync + 'static>;
+
+// A boxed future producing trailers.
+type BoxTrailers = Pin<
+    Box<dyn Future<Output = Result<Option<http::HeaderMap>, BoxStdError>> + Send + Sync + 'static>,
+>;
+
 #[cfg(feature = "http_body")]
 pub use http_body::Body as HttpBody;
 
 /// Flexible HTTP body.
 pub struct Body {
     inner: BodyInner,
+    trailers: Option<Trailers>,
+}
+
+enum Trailers {
+    Ready(http::HeaderMap),
+    Future(BoxTrailers),
 }
 
 impl Debug for Body {
@@ -41,11 +59,19 @@ impl_error!(
     "Body was frozen,it may have been consumed by `take()`"
 );
 
+impl_error!(BodyTooLarge, "Body exceeds the size limit");
+
+impl_error!(
+    BodyLengthMismatch,
+    "Body length does not match the declared length"
+);
+
 enum BodyInner {
     Once(Bytes),
     Reader {
         reader: BoxBufReader,
         length: Option<usize>,
+        replay: Option<Replay>,
     },
     Stream(BoxStream),
     Freeze,
@@ -62,6 +88,7 @@ impl Body {
     pub const fn empty() -> Self {
         Self {
             inner: BodyInner::Once(Bytes::new()),
+            trailers: None,
         }
     }
 
@@ -69,29 +96,44 @@ impl Body {
     pub const fn frozen() -> Self {
         Self {
             inner: BodyInner::Freeze,
+            trailers: None,
         }
     }
 
     /// Create a body from a object implement `AsyncBufRead`.
     /// This method allows you to create a object implement `AsyncBufRead`, which is useful for reading data
     /// from a file or any other source that implements the `AsyncBufRead` trait.
-    /// #Example
+    ///
+    /// If `length` is given, it is enforced rather than taken as a hint: reading fails with
+    /// [`Error::LengthMismatch`] if the data is longer or shorter. Pass `None` if the length is only an estimate.
+    ///
+    /// # Example
     /// ```rust
     /// use async_std::fs::File;
     /// use async_std::io::BufReader;
-    /// use http_util::Body;
-    /// let file = BufReader::new(File::open("index.html").await?);
-    /// Body::from_reader(file,file.metadata().await?.len())
+    /// use http_kit::Body;
+    /// # async fn example() -> std::io::Result<Body>{
+    /// let file = File::open("index.html").await?;
+    /// let len = file.metadata().await?.len() as usize;
+    /// Ok(Body::from_reader(BufReader::new(file),len))
+    /// # }
     /// ```
     pub fn from_reader(
         reader: impl AsyncBufRead + Send + Sync + 'static,
         length: impl Into<Option<usize>>,
     ) -> Self {
+        let length = length.into();
+        let reader: BoxBufReader = match length {
+            Some(length) => Box::pin(Exact::new(Box::pin(reader), length)),
+            None => Box::pin(reader),
+        };
         Self {
             inner: BodyInner::Reader {
-                reader: Box::pin(reader),
-                length: length.into(),
+                reader,
+                length,
+                replay: None,
             },
+            trailers: None,
         }
     }
 
@@ -106,12 +148,14 @@ impl Body {
             inner: BodyInner::Stream(Box::pin(
                 stream.map(|result| result.map(|data| data.into()).map_err(|error| error.into())),
             )),
+            trailers: None,
         }
     }
     /// Create a body from a chunk of bytes.
     pub fn from_bytes(data: impl Into<Bytes>) -> Self {
         Self {
             inner: BodyInner::Once(data.into()),
+            trailers: None,
         }
     }
 
@@ -138,25 +182,72 @@ impl Body {
         Ok(Self::from_bytes(serde_urlencoded::to_string(value)?))
     }
 
-    /// Try to get the length of the body.This method is primarily used in optimizations, but it is only an estimation,having no warranty of any kind.
+    /// Try to get the length of the body.
+    ///
+    /// The length is exact for bytes, and declared for readers, where reading fails if the data does not match.
+    /// The length of a stream is unknown unless it is declared by [`Body::with_length`].
     pub const fn len(&self) -> Option<usize> {
-        if let BodyInner::Once(bytes) = &self.inner {
-            Some(bytes.len())
-        } else {
-            None
+        match &self.inner {
+            BodyInner::Once(bytes) => Some(bytes.len()),
+            BodyInner::Reader { length, .. } => *length,
+            BodyInner::Stream(_) | BodyInner::Freeze => None,
+        }
+    }
+
+    /// Return the bounds on the remaining length of the body in bytes.
+    ///
+    /// The bounds are exact if the length is known, otherwise a stream reports its own `size_hint` as an estimate.
+    ///
+    /// # Example
+    /// ```rust
+    /// use futures_lite::stream;
+    /// use http_kit::Body;
+    ///
+    /// assert_eq!(Body::from("Hello").size_hint(), (5, Some(5)));
+    /// let body = Body::from_stream(stream::empty::<Result<&[u8], std::io::Error>>());
+    /// assert_eq!(body.len(), None);
+    /// assert_eq!(body.size_hint(), (0, Some(0)));
+    /// ```
+    pub fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            BodyInner::Once(bytes) => (bytes.len(), Some(bytes.len())),
+            BodyInner::Reader {
+                length: Some(length),
+                ..
+            } => (*length, Some(*length)),
+            BodyInner::Reader { length: None, .. } | BodyInner::Freeze => (0, None),
+            BodyInner::Stream(stream) => stream.size_hint(),
         }
     }
 
     /// Returns true if `Body` has a length of zero bytes.
     pub const fn is_empty(&self) -> Option<bool> {
-        if let Some(len) = self.len() {
-            if len == 0 {
-                Some(true)
-            } else {
-                Some(false)
-            }
-        } else {
-            None
+        match self.len() {
+            Some(len) => Some(len == 0),
+            None => None,
+        }
+    }
+
+    /// Declare the length of the body, reading fails with [`Error::LengthMismatch`] if the data is longer or shorter.
+    ///
+    /// # Example
+    /// ```rust
+    /// # async_std::task::block_on(async {
+    /// use futures_lite::stream;
+    /// use http_kit::{Body, BodyError};
+    ///
+    /// let body = Body::from_stream(stream::iter([Ok::<_, std::io::Error>("Hello")]));
+    /// assert_eq!(body.len(), None);
+    /// let body = body.with_length(11);
+    /// assert_eq!(body.len(), Some(11));
+    /// assert!(matches!(body.into_bytes().await, Err(BodyError::LengthMismatch)));
+    /// # })
+    /// ```
+    pub fn with_length(self, length: usize) -> Self {
+        match &self.inner {
+            BodyInner::Once(bytes) if bytes.len() == length => self,
+            BodyInner::Freeze => self,
+            _ => self.map_reader(|reader| Exact::new(reader, length), Some(length)),
         }
     }
 
@@ -164,16 +255,12 @@ impl Body {
     pub async fn into_bytes(self) -> Result<Bytes, Error> {
         match self.inner {
             BodyInner::Once(bytes) => Ok(bytes),
-            BodyInner::Reader { mut reader, length } => {
+            BodyInner::Reader {
+                mut reader, length, ..
+            } => {
                 let mut vec = Vec::with_capacity(length.unwrap_or_default());
-                loop {
-                    let data = reader.fill_buf().await?;
-                    if data.is_empty() {
-                        break;
-                    } else {
-                        vec.extend_from_slice(data);
-                    }
-                }
+                // `fill_buf` polls the reader again after it is ready, which is not reliable at EOF for some readers.
+                reader.read_to_end(&mut vec).await?;
                 Ok(vec.into())
             }
 
@@ -211,9 +298,227 @@ impl Body {
         IntoAsyncRead::new(self)
     }
 
+    /// Transform the data of the body through a reader adapter, keeping the trailers.
+    pub(crate) fn map_reader<R>(
+        mut self,
+        f: impl FnOnce(IntoAsyncRead) -> R,
+        length: Option<usize>,
+    ) -> Self
+    where
+        R: AsyncBufRead + Send + Sync + 'static,
+    {
+        let trailers = self.trailers.take();
+        Self {
+            inner: BodyInner::Reader {
+                reader: Box::pin(f(IntoAsyncRead::new(self))),
+                length,
+                replay: None,
+            },
+            trailers,
+        }
+    }
+
+    /// Limit the size of the body, reading more than `max_bytes` fails with [`Error::TooLarge`].
+    /// A body declaring a larger length fails before any data is read.
+    ///
+    /// # Example
+    /// ```rust
+    /// # async_std::task::block_on(async {
+    /// use http_kit::{Body, BodyError};
+    ///
+    /// let body = Body::from("Hello,world").limit(5);
+    /// assert!(matches!(body.into_bytes().await, Err(BodyError::TooLarge)));
+    /// # })
+    /// ```
+    pub fn limit(self, max_bytes: usize) -> Self {
+        match &self.inner {
+            BodyInner::Once(bytes) if bytes.len() <= max_bytes => return self,
+            BodyInner::Freeze => return self,
+            _ => {}
+        }
+        let length = self.len();
+        let exceeded = length.is_some_and(|length| length > max_bytes);
+        self.map_reader(|reader| Limited::new(reader, max_bytes, exceeded), length)
+    }
+
+    /// Split the body into two halves streaming the same data and trailers.
+    ///
+    /// Data read by one half is buffered for the other one, up to 64 KiB.
+    /// Once the limit is reached, the faster half waits until the slower half catches up or is dropped,
+    /// so that both halves should be read concurrently.
+    ///
+    /// # Example
+    /// ```rust
+    /// # async_std::task::block_on(async {
+    /// use http_kit::Body;
+    ///
+    /// let (left, right) = Body::from("Hello,world").tee();
+    /// let (left, right) = futures_lite::future::zip(left.into_bytes(), right.into_bytes()).await;
+    /// assert_eq!(left.unwrap(), "Hello,world");
+    /// assert_eq!(right.unwrap(), "Hello,world");
+    /// # })
+    /// ```
+    pub fn tee(self) -> (Self, Self) {
+        tee::tee(self)
+    }
+
+    /// Read the body into memory, so that it can be cloned by [`Body::try_clone`].
+    /// Fail with [`Error::TooLarge`] if the body is larger than `max_bytes`.
+    pub async fn into_replayable(self, max_bytes: usize) -> Result<Self, Error> {
+        let mut body = self.limit(max_bytes);
+        body.as_bytes().await?;
+        body.trailers = body.trailers().await?.map(Trailers::Ready);
+        Ok(body)
+    }
+
+    /// Read the body, keeping the first `memory_threshold` bytes in memory and spilling the rest to a temporary file.
+    ///
+    /// The returned body has a known length and reads the data back from memory and the file,
+    /// a body not exceeding the threshold is kept in memory entirely.
+    /// It can be re-read by [`Body::try_clone`], the file is only readable by the current user
+    /// and is removed once the body and all its clones are dropped.
+    /// On Unix, it is unlinked right after its creation, so that it is never left behind.
+    ///
+    /// # Example
+    /// ```rust
+    /// # async_std::task::block_on(async {
+    /// use http_kit::Body;
+    ///
+    /// let body = Body::from(vec![b'a'; 4096]).spool(1024).await.unwrap();
+    /// assert_eq!(body.len(), Some(4096));
+    /// let clone = body.try_clone().unwrap();
+    /// assert_eq!(body.into_bytes().await.unwrap(), vec![b'a'; 4096]);
+    /// assert_eq!(clone.into_bytes().await.unwrap(), vec![b'a'; 4096]);
+    /// # })
+    /// ```
+    #[cfg(feature = "fs")]
+    pub async fn spool(self, memory_threshold: usize) -> Result<Self, Error> {
+        spool::spool(self, memory_threshold).await
+    }
+
+    /// Try to clone the body, which is only possible if the data and the trailers are in memory,
+    /// or if the body is returned by [`Body::spool`], whose clones read the data from the beginning.
+    ///
+    /// # Example
+    /// ```rust
+    /// # async_std::task::block_on(async {
+    /// use futures_lite::stream;
+    /// use http_kit::Body;
+    ///
+    /// let body = Body::from_stream(stream::iter([Ok::<_, std::io::Error>("Hello,world")]));
+    /// assert!(body.try_clone().is_none());
+    /// let body = body.into_replayable(1024).await.unwrap();
+    /// assert_eq!(body.try_clone().unwrap().into_bytes().await.unwrap(), "Hello,world");
+    /// # })
+    /// ```
+    pub fn try_clone(&self) -> Option<Self> {
+        let trailers = match &self.trailers {
+            None => None,
+            Some(Trailers::Ready(trailers)) => Some(Trailers::Ready(trailers.clone())),
+            Some(Trailers::Future(_)) => return None,
+        };
+        let inner = match &self.inner {
+            BodyInner::Once(bytes) => BodyInner::Once(bytes.clone()),
+            BodyInner::Freeze => BodyInner::Freeze,
+            BodyInner::Reader {
+                replay: Some(replay),
+                ..
+            } => replay().inner,
+            BodyInner::Reader { .. } | BodyInner::Stream(_) => return None,
+        };
+        Some(Self { inner, trailers })
+    }
+
+    // Return the data if it is held in memory.
+    pub(crate) fn buffered(&self) -> Option<&Bytes> {
+        match &self.inner {
+            BodyInner::Once(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Decode the body encoded with `encoding`, the data is decompressed while being read.
+    ///
+    /// # Example
+    /// ```rust
+    /// # #[cfg(feature = "gzip")]
+    /// # async_std::task::block_on(async {
+    /// use http_kit::{compression::Encoding, Body};
+    ///
+    /// let encoded = Body::from("Hello,world").encode(Encoding::Gzip);
+    /// let decoded = encoded.decode(Encoding::Gzip);
+    /// assert_eq!(decoded.into_string().await.unwrap(), "Hello,world");
+    /// # })
+    /// ```
+    #[cfg(any(
+        feature = "gzip",
+        feature = "deflate",
+        feature = "brotli",
+        feature = "zstd"
+    ))]
+    pub fn decode(self, encoding: crate::compression::Encoding) -> Self {
+        self.map_reader(|reader| encoding.decode(reader), None)
+    }
+
+    /// Encode the body with `encoding`, the data is compressed while being read.
+    #[cfg(any(
+        feature = "gzip",
+        feature = "deflate",
+        feature = "brotli",
+        feature = "zstd"
+    ))]
+    pub fn encode(self, encoding: crate::compression::Encoding) -> Self {
+        self.map_reader(|reader| encoding.encode(reader), None)
+    }
+
+    /// Guess the MIME of the body by its first bytes, return `None` if it is unknown.
+    ///
+    /// Up to 512 bytes are peeked and put back in front of the body, so that no data is consumed.
+    ///
+    /// # Example
+    /// ```rust
+    /// # async_std::task::block_on(async {
+    /// use futures_lite::io::Cursor;
+    /// use http_kit::Body;
+    ///
+    /// let mut body = Body::from_reader(Cursor::new(b"\x89PNG\r\n\x1a\n...".to_vec()), 11);
+    /// assert_eq!(body.sniff_mime().await.unwrap(), Some("image/png"));
+    /// assert_eq!(body.into_bytes().await.unwrap(), &b"\x89PNG\r\n\x1a\n..."[..]);
+    /// # })
+    /// ```
+    pub async fn sniff_mime(&mut self) -> Result<Option<&'static str>, Error> {
+        if let BodyInner::Once(bytes) = &self.inner {
+            return Ok(crate::mime_guess::sniff(bytes));
+        }
+        let mut body = self.take()?;
+        let trailers = body.trailers.take();
+        let length = body.len();
+        // A spooled body can still be cloned once the head is read.
+        let replay = match &body.inner {
+            BodyInner::Reader { replay, .. } => replay.clone(),
+            _ => None,
+        };
+        let mut reader = IntoAsyncRead::new(body);
+        let mut head = Vec::with_capacity(crate::mime_guess::SNIFF_LEN);
+        (&mut reader)
+            .take(crate::mime_guess::SNIFF_LEN as u64)
+            .read_to_end(&mut head)
+            .await?;
+        let mime = crate::mime_guess::sniff(&head);
+        self.inner = BodyInner::Reader {
+            reader: Box::pin(futures_lite::io::Cursor::new(head).chain(reader)),
+            length,
+            replay,
+        };
+        self.trailers = trailers;
+        Ok(mime)
+    }
+
     /// Prepare a chunk of bytes in the inner representation, then return a reference to the bytes.
     pub async fn as_bytes(&mut self) -> Result<&[u8], Error> {
-        self.inner = BodyInner::Once(self.take()?.into_bytes().await?);
+        let mut body = self.take()?;
+        self.trailers = body.trailers.take();
+        self.inner = BodyInner::Once(body.into_bytes().await?);
         match self.inner {
             BodyInner::Once(ref bytes) => Ok(bytes),
             _ => unreachable!(),
@@ -250,6 +555,118 @@ impl Body {
         Ok(serde_urlencoded::from_bytes(self.as_bytes().await?)?)
     }
 
+    /// Attach trailers to the body, which are sent after the data.
+    /// Fields not allowed in trailers, such as `Content-Length`, `Host` or `Authorization`, are dropped by the HTTP/1.1 codec.
+    ///
+    /// # Example
+    /// ```rust
+    /// use http_kit::{header, Body};
+    /// # async_std::task::block_on(async {
+    /// let mut trailers = http::HeaderMap::new();
+    /// trailers.insert(header::ETAG, "\"abc\"".parse().unwrap());
+    /// let mut body = Body::from("Hello,world").with_trailers(trailers);
+    /// assert_eq!(body.as_str().await.unwrap(), "Hello,world");
+    /// assert_eq!(body.trailers().await.unwrap().unwrap()[header::ETAG], "\"abc\"");
+    /// # })
+    /// ```
+    pub fn with_trailers(mut self, trailers: http::HeaderMap) -> Self {
+        self.trailers = Some(Trailers::Ready(trailers));
+        self
+    }
+
+    /// Attach a future producing trailers to the body, which is only polled once the data is exhausted.
+    /// This allows a streaming producer to compute trailers, such as a checksum, after sending the data.
+    pub fn with_trailers_from<F, E>(mut self, trailers: F) -> Self
+    where
+        F: Future<Output = Result<Option<http::HeaderMap>, E>> + Send + Sync + 'static,
+        E: Into<BoxStdError>,
+    {
+        self.trailers = Some(Trailers::Future(Box::pin(async move {
+            trailers.await.map_err(Into::into)
+        })));
+        self
+    }
+
+    /// Return `true` if trailers are attached to the body.
+    pub const fn has_trailers(&self) -> bool {
+        self.trailers.is_some()
+    }
+
+    /// Poll the trailers of the body, which should be called after the data is exhausted.
+    /// Return `None` if the body has no trailers, or they have already been taken.
+    pub fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Error>> {
+        let result = match &mut self.trailers {
+            None => return Poll::Ready(Ok(None)),
+            Some(Trailers::Ready(trailers)) => Ok(Some(take(trailers))),
+            Some(Trailers::Future(trailers)) => ready!(trailers.as_mut().poll(cx)),
+        };
+        self.trailers = None;
+        Poll::Ready(result.map_err(Error::from))
+    }
+
+    /// Wait for the trailers of the body, which should be called after the data is exhausted.
+    /// Return `None` if the body has no trailers, or they have already been taken.
+    pub async fn trailers(&mut self) -> Result<Option<http::HeaderMap>, Error> {
+        futures_lite::future::poll_fn(|cx| Pin::new(&mut *self).poll_trailers(cx)).await
+    }
+
+    // Set or validate the framing headers of a message carrying the body.
+    // `method` is given for requests, whose empty body is not declared unless the method expects a body.
+    pub(crate) fn finalize_headers(
+        &mut self,
+        headers: &mut http::HeaderMap,
+        version: http::Version,
+        method: Option<&http::Method>,
+    ) -> Result<(), Error> {
+        use http::{header, HeaderValue, Method, Version};
+
+        let chunked = HeaderValue::from_static("chunked");
+        // `Transfer-Encoding` overrides `Content-Length`.
+        if headers.contains_key(header::TRANSFER_ENCODING) {
+            headers.remove(header::CONTENT_LENGTH);
+            return Ok(());
+        }
+        if self.has_trailers() && version == Version::HTTP_11 {
+            headers.remove(header::CONTENT_LENGTH);
+            headers.insert(header::TRANSFER_ENCODING, chunked);
+            return Ok(());
+        }
+
+        let mut values = headers.get_all(header::CONTENT_LENGTH).iter();
+        let declared = match (values.next(), values.next()) {
+            (None, _) => None,
+            (Some(value), None) => Some(
+                value
+                    .to_str()
+                    .ok()
+                    .and_then(|value| value.trim().parse::<usize>().ok())
+                    .ok_or(Error::LengthMismatch)?,
+            ),
+            (Some(_), Some(_)) => return Err(Error::LengthMismatch),
+        };
+        let omit_empty = method
+            .is_some_and(|method| !matches!(*method, Method::POST | Method::PUT | Method::PATCH));
+
+        match (self.len(), declared) {
+            (Some(length), Some(declared)) if length != declared => {
+                return Err(Error::LengthMismatch)
+            }
+            (Some(0), None) if omit_empty => {}
+            (Some(length), _) => {
+                headers.insert(header::CONTENT_LENGTH, HeaderValue::from(length));
+            }
+            (None, Some(declared)) => *self = take(self).with_length(declared),
+            (None, None) if version == Version::HTTP_11 => {
+                headers.insert(header::TRANSFER_ENCODING, chunked);
+            }
+            (None, None) => {}
+        }
+        Ok(())
+    }
+
     /// Replace the value of the body and return the old body.
     pub fn replace(&mut self, body: Body) -> Body {
         replace(self, body)
@@ -303,7 +720,7 @@ impl Stream for Body {
                     Poll::Ready(Some(Ok(take(bytes))))
                 }
             }
-            BodyInner::Reader { reader, length } => {
+            BodyInner::Reader { reader, length, .. } => {
                 let data = ready!(reader.as_mut().poll_fill_buf(cx))?;
                 if data.is_empty() {
                     return Poll::Ready(None);
@@ -311,7 +728,8 @@ impl Stream for Body {
                 let data = Bytes::copy_from_slice(data);
                 reader.as_mut().consume(data.len());
                 if let Some(length) = length {
-                    *length -= data.len();
+                    // The declared length is enforced by the reader, this is only defensive.
+                    *length = length.saturating_sub(data.len());
                 }
                 Poll::Ready(Some(Ok(data)))
             }
@@ -321,12 +739,7 @@ impl Stream for Body {
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        match &self.inner {
-            BodyInner::Once(bytes) => (bytes.len(), Some(bytes.len())),
-            BodyInner::Reader { length, .. } => (0, *length),
-            BodyInner::Stream(body) => body.size_hint(),
-            BodyInner::Freeze => (0, None),
-        }
+        Body::size_hint(self)
     }
 }
 
@@ -345,8 +758,122 @@ impl HttpBody for Body {
 
     fn poll_trailers(
         self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
+        cx: &mut Context<'_>,
     ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
-        Poll::Ready(Ok(None))
+        Body::poll_trailers(self, cx).map_err(Into::into)
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        let (lower, upper) = Body::size_hint(self);
+        let mut hint = http_body::SizeHint::new();
+        hint.set_lower(lower as u64);
+        if let Some(upper) = upper {
+            hint.set_upper(upper as u64);
+        }
+        hint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, stream};
+
+    use super::{Body, Error as BodyError};
+
+    #[test]
+    fn size_hint() {
+        assert_eq!(Body::from("Hello").size_hint(), (5, Some(5)));
+        assert_eq!(Body::frozen().size_hint(), (0, None));
+
+        let reader = futures_lite::io::Cursor::new("Hello");
+        assert_eq!(Body::from_reader(reader, 5).size_hint(), (5, Some(5)));
+        let reader = futures_lite::io::Cursor::new("Hello");
+        assert_eq!(Body::from_reader(reader, None).size_hint(), (0, None));
+
+        let body = Body::from_stream(stream::empty::<Result<&[u8], std::io::Error>>());
+        assert_eq!(body.len(), None);
+        assert_eq!(body.size_hint(), (0, Some(0)));
+        assert_eq!(body.with_length(5).size_hint(), (5, Some(5)));
+    }
+
+    #[test]
+    fn size_hint_is_remaining_length() {
+        use futures_lite::StreamExt;
+
+        let reader = futures_lite::io::Cursor::new("Hello,world");
+        let mut body = Body::from_reader(futures_lite::io::BufReader::with_capacity(5, reader), 11);
+        let data = block_on(body.next()).unwrap().unwrap();
+        assert_eq!(data, "Hello");
+        assert_eq!(body.size_hint(), (6, Some(6)));
+        assert_eq!(body.len(), Some(6));
+    }
+
+    #[test]
+    fn reader_length_is_enforced() {
+        for length in [4, 6] {
+            let reader = futures_lite::io::Cursor::new("Hello");
+            let body = Body::from_reader(reader, length);
+            assert!(matches!(
+                block_on(body.into_bytes()),
+                Err(BodyError::LengthMismatch)
+            ));
+        }
+        let reader = futures_lite::io::Cursor::new("Hello");
+        assert_eq!(
+            block_on(Body::from_reader(reader, 5).into_bytes()).unwrap(),
+            "Hello"
+        );
+        assert_eq!(
+            BodyError::LengthMismatch.into_http_error().status(),
+            http::StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn len_is_const() {
+        const fn is_empty(body: &Body) -> Option<bool> {
+            body.is_empty()
+        }
+        assert_eq!(is_empty(&Body::empty()), Some(true));
+        assert_eq!(is_empty(&Body::frozen()), None);
+    }
+
+    #[test]
+    fn limit_known_length() {
+        assert_eq!(
+            block_on(Body::from("Hello").limit(5).into_bytes()).unwrap(),
+            "Hello"
+        );
+        assert!(matches!(
+            block_on(Body::from("Hello,world").limit(5).into_bytes()),
+            Err(BodyError::TooLarge)
+        ));
+
+        // A declared length over the limit fails before the reader is polled.
+        let reader = futures_lite::io::BufReader::new(futures_lite::io::repeat(0));
+        let body = Body::from_reader(reader, 1024).limit(16);
+        assert_eq!(body.len(), Some(1024));
+        assert!(matches!(
+            block_on(body.into_bytes()),
+            Err(BodyError::TooLarge)
+        ));
+    }
+
+    #[test]
+    fn limit_streaming() {
+        use futures_lite::StreamExt;
+
+        let chunks = || stream::iter([Ok::<_, std::io::Error>("Hello"), Ok(",world")]);
+        let mut body = Body::from_stream(chunks()).limit(8);
+        assert_eq!(block_on(body.next()).unwrap().unwrap(), "Hello");
+        let error = block_on(body.next()).unwrap().unwrap_err();
+        assert!(matches!(BodyError::from(error), BodyError::TooLarge));
+
+        let body = Body::from_stream(chunks()).limit(11);
+        assert_eq!(block_on(body.into_bytes()).unwrap(), "Hello,world");
+        assert_eq!(
+            BodyError::TooLarge.into_http_error().status(),
+            http::StatusCode::PAYLOAD_TOO_LARGE
+        );
     }
 }
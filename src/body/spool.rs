@@ -0,0 +1,256 @@
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::mem::take;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use async_fs::File;
+use blocking::{unblock, Task};
+use bytes::Bytes;
+use futures_lite::io::Cursor;
+use futures_lite::{ready, AsyncBufRead, AsyncRead, AsyncWriteExt, StreamExt};
+
+use super::{Body, BodyInner, Error, Trailers};
+
+const BUFFER_SIZE: usize = 8 * 1024;
+
+// Create a temporary file only accessible by the current user, which is removed once its last handle is closed.
+// It is unlinked right after its creation on Unix, and deleted on close on Windows.
+fn create_temp_file() -> io::Result<fs::File> {
+    loop {
+        let name = format!("http-kit-{:016x}", rand::random::<u64>());
+        let path = std::env::temp_dir().join(name);
+        let mut options = fs::OpenOptions::new();
+        options.read(true).write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+            const FILE_FLAG_DELETE_ON_CLOSE: u32 = 0x0400_0000;
+            options.custom_flags(FILE_FLAG_DELETE_ON_CLOSE);
+        }
+        match options.open(&path) {
+            Ok(file) => {
+                #[cfg(unix)]
+                fs::remove_file(&path)?;
+                return Ok(file);
+            }
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+// Return the temporary file for reading, with a handle for writing.
+async fn temp_file() -> io::Result<(Arc<fs::File>, File)> {
+    let file = unblock(create_temp_file).await?;
+    let writer = File::from(file.try_clone()?);
+    Ok((Arc::new(file), writer))
+}
+
+#[cfg(unix)]
+fn read_at(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+}
+
+// Read the data kept in memory, then the file.
+// The file is read with positional reads, so that clones sharing it do not share an offset.
+struct SpoolReader {
+    head: Cursor<Bytes>,
+    file: Arc<fs::File>,
+    offset: u64,
+    buffer: Vec<u8>,
+    consumed: usize,
+    reading: Option<Task<(Vec<u8>, io::Result<usize>)>>,
+}
+
+impl SpoolReader {
+    fn head_is_done(&self) -> bool {
+        self.head.position() >= self.head.get_ref().len() as u64
+    }
+}
+
+impl AsyncBufRead for SpoolReader {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        if !this.head_is_done() {
+            return Pin::new(&mut this.head).poll_fill_buf(cx);
+        }
+        if this.consumed == this.buffer.len() {
+            let task = this.reading.get_or_insert_with(|| {
+                let file = this.file.clone();
+                let offset = this.offset;
+                let mut buffer = take(&mut this.buffer);
+                this.consumed = 0;
+                unblock(move || {
+                    buffer.resize(BUFFER_SIZE, 0);
+                    let result = read_at(&file, &mut buffer, offset);
+                    (buffer, result)
+                })
+            });
+            let (mut buffer, result) = ready!(Pin::new(task).poll(cx));
+            this.reading = None;
+            let len = *result.as_ref().unwrap_or(&0);
+            buffer.truncate(len);
+            this.buffer = buffer;
+            this.offset += len as u64;
+            result?;
+        }
+        Poll::Ready(Ok(&this.buffer[this.consumed..]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        if !this.head_is_done() {
+            Pin::new(&mut this.head).consume(amt);
+        } else {
+            this.consumed += amt;
+        }
+    }
+}
+
+impl AsyncRead for SpoolReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let data = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.consume(len);
+        Poll::Ready(Ok(len))
+    }
+}
+
+// Create a body reading `head` then `file`, which can be created again by `Body::try_clone`.
+fn spooled(head: Bytes, file: Arc<fs::File>, length: usize) -> Body {
+    let reader = SpoolReader {
+        head: Cursor::new(head.clone()),
+        file: file.clone(),
+        offset: 0,
+        buffer: Vec::new(),
+        consumed: 0,
+        reading: None,
+    };
+    let mut body = Body::from_reader(reader, length);
+    if let BodyInner::Reader { replay, .. } = &mut body.inner {
+        *replay = Some(Arc::new(move || {
+            spooled(head.clone(), file.clone(), length)
+        }));
+    }
+    body
+}
+
+pub(super) async fn spool(mut body: Body, memory_threshold: usize) -> Result<Body, Error> {
+    let mut head = Vec::new();
+    let mut file: Option<(Arc<fs::File>, File)> = None;
+    let mut length = 0;
+    while let Some(data) = body.try_next().await? {
+        length += data.len();
+        if let Some((_, writer)) = &mut file {
+            writer.write_all(&data).await?;
+            continue;
+        }
+        let split = data.len().min(memory_threshold - head.len());
+        head.extend_from_slice(&data[..split]);
+        if split < data.len() {
+            let (temp, mut writer) = temp_file().await?;
+            writer.write_all(&data[split..]).await?;
+            file = Some((temp, writer));
+        }
+    }
+    let trailers = body.trailers().await?.map(Trailers::Ready);
+
+    let mut body = match file {
+        Some((temp, mut writer)) => {
+            writer.flush().await?;
+            spooled(head.into(), temp, length)
+        }
+        None => Body::from_bytes(head),
+    };
+    body.trailers = trailers;
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::{block_on, zip};
+
+    use super::temp_file;
+    use crate::Body;
+
+    #[test]
+    fn spooled_body_is_replayable() {
+        block_on(async {
+            let data: Vec<u8> = (0..10_000u32).map(|index| index as u8).collect();
+            let body = Body::from(data.clone()).spool(1024).await.unwrap();
+            assert_eq!(body.len(), Some(data.len()));
+            let clone = body.try_clone().unwrap();
+            assert_eq!(body.into_bytes().await.unwrap(), data);
+            assert_eq!(clone.try_clone().unwrap().into_bytes().await.unwrap(), data);
+            assert_eq!(clone.into_bytes().await.unwrap(), data);
+        });
+    }
+
+    #[test]
+    fn sniffed_body_is_replayable() {
+        block_on(async {
+            let mut data = b"%PDF-".to_vec();
+            data.resize(4096, b'a');
+            let mut body = Body::from(data.clone()).spool(1024).await.unwrap();
+            assert_eq!(body.sniff_mime().await.unwrap(), Some("application/pdf"));
+            let clone = body.try_clone().unwrap();
+            assert_eq!(body.into_bytes().await.unwrap(), data);
+            assert_eq!(clone.into_bytes().await.unwrap(), data);
+        });
+    }
+
+    #[test]
+    fn small_body_is_kept_in_memory() {
+        block_on(async {
+            let body = Body::from("Hello,world").spool(1024).await.unwrap();
+            assert_eq!(
+                body.try_clone().unwrap().into_bytes().await.unwrap(),
+                "Hello,world"
+            );
+        });
+    }
+
+    #[test]
+    fn temp_file_is_private_and_unlinked() {
+        block_on(async {
+            let (file, _writer) = temp_file().await.unwrap();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::{MetadataExt, PermissionsExt};
+                let metadata = file.metadata().unwrap();
+                assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+                assert_eq!(metadata.nlink(), 0);
+            }
+        });
+    }
+
+    #[test]
+    fn clones_read_independently() {
+        block_on(async {
+            let data: Vec<u8> = (0..100_000u32).map(|index| index as u8).collect();
+            let body = Body::from(data.clone()).spool(16).await.unwrap();
+            let clone = body.try_clone().unwrap();
+            let (left, right) = zip(body.into_bytes(), clone.into_bytes()).await;
+            assert_eq!(left.unwrap(), data);
+            assert_eq!(right.unwrap(), data);
+        });
+    }
+}
@@ -0,0 +1,421 @@
+use std::collections::VecDeque;
+use std::error::Error as StdError;
+use std::fmt::{Debug, Display};
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+use bytes::Bytes;
+use futures_lite::{ready, Stream};
+use http::HeaderMap;
+
+use super::{Body, BodyInner, BoxStdError, Error};
+
+// The maximum size of data buffered for the slower half.
+const MAX_BUFFERED: usize = 64 * 1024;
+
+// An error of the original body, shared by both halves.
+#[derive(Clone)]
+struct SharedError(Arc<Error>);
+
+impl Debug for SharedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Display for SharedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl StdError for SharedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.source()
+    }
+}
+
+impl SharedError {
+    fn new(error: impl Into<Error>) -> Self {
+        Self(Arc::new(error.into()))
+    }
+
+    // Return an error of the same kind for a half, so that a half fails as the original body does.
+    fn boxed(&self) -> BoxStdError {
+        let shared = || self.clone();
+        let error = match &*self.0 {
+            Error::Io(error) => Error::Io(io::Error::new(error.kind(), shared())),
+            Error::Decode(error) => Error::Decode(io::Error::new(error.kind(), shared())),
+            Error::Utf8(error) => Error::Utf8(*error),
+            Error::BodyFrozen => Error::BodyFrozen,
+            Error::TooLarge => Error::TooLarge,
+            Error::LengthMismatch => Error::LengthMismatch,
+            _ => Error::Other(Box::new(shared())),
+        };
+        Box::new(error)
+    }
+}
+
+// Wakers of both halves, the original body is polled with a waker waking them all,
+// so that no half misses the progress made by the other one.
+#[derive(Default)]
+struct Wakers([Mutex<Option<Waker>>; 2]);
+
+impl Wakers {
+    fn register(&self, index: usize, waker: &Waker) {
+        *self.0[index].lock().unwrap() = Some(waker.clone());
+    }
+
+    fn wake_one(&self, index: usize) {
+        if let Some(waker) = self.0[index].lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Wake for Wakers {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wake_one(0);
+        self.wake_one(1);
+    }
+}
+
+#[derive(Default)]
+struct Half {
+    queue: VecDeque<Bytes>,
+    buffered: usize,
+    dropped: bool,
+    finished: bool,
+}
+
+struct Shared {
+    body: Body,
+    halves: [Half; 2],
+    end: Option<Result<(), SharedError>>,
+    trailers: Option<Result<Option<HeaderMap>, SharedError>>,
+    wakers: Arc<Wakers>,
+    waker: Waker,
+}
+
+impl Shared {
+    fn poll_data(
+        &mut self,
+        index: usize,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, BoxStdError>>> {
+        let other = 1 - index;
+        if let Some(data) = self.halves[index].queue.pop_front() {
+            self.halves[index].buffered -= data.len();
+            self.wakers.wake_one(other);
+            return Poll::Ready(Some(Ok(data)));
+        }
+
+        if let Some(end) = &self.end {
+            if self.halves[index].finished {
+                return Poll::Ready(None);
+            }
+            self.halves[index].finished = true;
+            return Poll::Ready(end.as_ref().err().map(|error| Err(error.boxed())));
+        }
+
+        self.wakers.register(index, cx.waker());
+        // Wait for the other half to catch up.
+        if !self.halves[other].dropped && self.halves[other].buffered >= MAX_BUFFERED {
+            return Poll::Pending;
+        }
+
+        let waker = self.waker.clone();
+        let result = ready!(Pin::new(&mut self.body).poll_next(&mut Context::from_waker(&waker)));
+        let result = match result {
+            Some(Ok(data)) => {
+                let half = &mut self.halves[other];
+                if !half.dropped {
+                    half.buffered += data.len();
+                    half.queue.push_back(data.clone());
+                }
+                Some(Ok(data))
+            }
+            Some(Err(error)) => {
+                let error = SharedError::new(error);
+                self.end = Some(Err(error.clone()));
+                self.halves[index].finished = true;
+                Some(Err(error.boxed()))
+            }
+            None => {
+                self.end = Some(Ok(()));
+                self.halves[index].finished = true;
+                None
+            }
+        };
+        self.wakers.wake_one(other);
+        Poll::Ready(result)
+    }
+
+    fn poll_trailers(
+        &mut self,
+        index: usize,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, BoxStdError>> {
+        if self.trailers.is_none() {
+            self.wakers.register(index, cx.waker());
+            let waker = self.waker.clone();
+            let result =
+                ready!(Pin::new(&mut self.body).poll_trailers(&mut Context::from_waker(&waker)));
+            self.trailers = Some(result.map_err(SharedError::new));
+            self.wakers.wake_one(1 - index);
+        }
+        match self.trailers.as_ref().unwrap() {
+            Ok(trailers) => Poll::Ready(Ok(trailers.clone())),
+            Err(error) => Poll::Ready(Err(error.boxed())),
+        }
+    }
+}
+
+struct TeeHalf {
+    shared: Arc<Mutex<Shared>>,
+    index: usize,
+}
+
+impl Stream for TeeHalf {
+    type Item = Result<Bytes, BoxStdError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.shared.lock().unwrap().poll_data(self.index, cx)
+    }
+}
+
+impl Drop for TeeHalf {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        let half = &mut shared.halves[self.index];
+        half.dropped = true;
+        half.queue.clear();
+        half.buffered = 0;
+        shared.wakers.wake_one(1 - self.index);
+    }
+}
+
+pub(super) fn tee(body: Body) -> (Body, Body) {
+    let has_trailers = body.has_trailers();
+    let length = body.len();
+    let wakers = Arc::new(Wakers::default());
+    let shared = Arc::new(Mutex::new(Shared {
+        body,
+        halves: Default::default(),
+        end: None,
+        trailers: None,
+        waker: Waker::from(wakers.clone()),
+        wakers,
+    }));
+
+    let half = |index| {
+        let mut body = Body {
+            inner: BodyInner::Stream(Box::pin(TeeHalf {
+                shared: shared.clone(),
+                index,
+            })),
+            trailers: None,
+        };
+        if let Some(length) = length {
+            body = body.with_length(length);
+        }
+        if has_trailers {
+            let shared = shared.clone();
+            body.with_trailers_from(futures_lite::future::poll_fn(move |cx| {
+                shared.lock().unwrap().poll_trailers(index, cx)
+            }))
+        } else {
+            body
+        }
+    };
+    (half(0), half(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    use bytes::Bytes;
+    use futures_lite::{future, stream, Stream, StreamExt};
+    use http::{HeaderMap, HeaderValue};
+
+    use super::MAX_BUFFERED;
+    use crate::{Body, BodyError};
+
+    const CHUNK: usize = 8 * 1024;
+
+    // A body of `chunks` chunks, each filled with its index.
+    fn chunked(chunks: usize) -> Body {
+        Body::from_stream(stream::iter(
+            (0..chunks).map(|index| Ok::<_, std::io::Error>(vec![index as u8; CHUNK])),
+        ))
+    }
+
+    fn expected(chunks: usize) -> Vec<u8> {
+        (0..chunks)
+            .flat_map(|index| vec![index as u8; CHUNK])
+            .collect()
+    }
+
+    #[derive(Default)]
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn halves_read_the_same_bytes() {
+        let (left, right) = chunked(4).tee();
+        // Both halves fit in the buffer, so that they can be read one after another.
+        assert_eq!(future::block_on(left.into_bytes()).unwrap(), expected(4));
+        assert_eq!(future::block_on(right.into_bytes()).unwrap(), expected(4));
+    }
+
+    #[test]
+    fn larger_than_buffer() {
+        let chunks = 4 * MAX_BUFFERED / CHUNK;
+        let (left, right) = chunked(chunks).tee();
+        let (left, right) = future::block_on(future::zip(left.into_bytes(), right.into_bytes()));
+        assert_eq!(left.unwrap(), expected(chunks));
+        assert_eq!(right.unwrap(), expected(chunks));
+    }
+
+    #[test]
+    fn slow_half_applies_backpressure() {
+        let chunks = 4 * MAX_BUFFERED / CHUNK;
+        let (mut left, mut right) = chunked(chunks).tee();
+        let flag = Arc::new(Flag::default());
+        let waker = Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut read = 0;
+        while let Poll::Ready(Some(data)) = std::pin::Pin::new(&mut left).poll_next(&mut cx) {
+            read += data.unwrap().len();
+        }
+        // The fast half stops once the slow half has a full buffer.
+        assert_eq!(read, MAX_BUFFERED);
+        assert!(!flag.0.load(Ordering::SeqCst));
+
+        let data = future::block_on(right.next()).unwrap().unwrap();
+        assert_eq!(data, Bytes::from(vec![0; CHUNK]));
+        assert!(flag.0.load(Ordering::SeqCst));
+        let Poll::Ready(Some(data)) = std::pin::Pin::new(&mut left).poll_next(&mut cx) else {
+            panic!("the fast half is not resumed");
+        };
+        assert_eq!(data.unwrap().len(), CHUNK);
+    }
+
+    #[test]
+    fn dropped_half_does_not_block() {
+        let chunks = 4 * MAX_BUFFERED / CHUNK;
+        let (left, right) = chunked(chunks).tee();
+        drop(right);
+        assert_eq!(
+            future::block_on(left.into_bytes()).unwrap(),
+            expected(chunks)
+        );
+
+        // A half dropped while the other one waits for it releases the waiting half.
+        let (mut left, right) = chunked(chunks).tee();
+        let waker = Waker::from(Arc::new(Flag::default()));
+        let mut cx = Context::from_waker(&waker);
+        while std::pin::Pin::new(&mut left).poll_next(&mut cx).is_ready() {}
+        drop(right);
+        let rest = future::block_on(left.into_bytes()).unwrap();
+        assert_eq!(rest.len(), (chunks * CHUNK) - MAX_BUFFERED);
+    }
+
+    #[test]
+    fn error_reaches_both_halves() {
+        let body = Body::from_stream(stream::iter([
+            Ok(Bytes::from("Hello")),
+            Err(std::io::Error::other("broken")),
+        ]));
+        let (left, right) = body.tee();
+        for half in [left, right] {
+            let error = future::block_on(half.into_bytes()).unwrap_err();
+            let BodyError::Io(error) = error else {
+                panic!("the error kind is lost");
+            };
+            assert_eq!(error.kind(), std::io::ErrorKind::Other);
+            assert_eq!(error.to_string(), "broken");
+        }
+    }
+
+    #[test]
+    fn error_kind_is_kept() {
+        let (left, right) = chunked(2).limit(CHUNK).tee();
+        for half in [left, right] {
+            let error = future::block_on(half.into_bytes()).unwrap_err();
+            assert!(matches!(error, BodyError::TooLarge));
+        }
+
+        let (left, right) = chunked(2).with_length(CHUNK).tee();
+        for half in [left, right] {
+            let error = future::block_on(half.into_bytes()).unwrap_err();
+            assert!(matches!(error, BodyError::LengthMismatch));
+        }
+
+        let body = Body::from_stream(stream::iter([Err::<Bytes, _>(BodyError::Decode(
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "corrupt"),
+        ))]));
+        let (left, right) = body.tee();
+        for half in [left, right] {
+            let error = future::block_on(half.into_bytes()).unwrap_err();
+            let BodyError::Decode(error) = error else {
+                panic!("the error kind is lost");
+            };
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        }
+
+        // A replayable body built from a half fails as the original body does.
+        let (left, _right) = chunked(2).limit(CHUNK).tee();
+        let error = future::block_on(left.into_replayable(4 * CHUNK)).unwrap_err();
+        assert!(matches!(error, BodyError::TooLarge));
+    }
+
+    #[test]
+    fn trailers_reach_both_halves() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("checksum", HeaderValue::from_static("abc"));
+        let (mut left, mut right) = chunked(1).with_trailers(trailers.clone()).tee();
+        for half in [&mut left, &mut right] {
+            future::block_on(half.as_bytes()).unwrap();
+            assert_eq!(
+                future::block_on(half.trailers()).unwrap(),
+                Some(trailers.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn replayable_streaming_body() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("checksum", HeaderValue::from_static("abc"));
+        let body = chunked(2).with_trailers(trailers.clone());
+        assert!(body.try_clone().is_none());
+
+        let body = future::block_on(body.into_replayable(2 * CHUNK)).unwrap();
+        let mut clone = body.try_clone().unwrap();
+        assert_eq!(future::block_on(body.into_bytes()).unwrap(), expected(2));
+        assert_eq!(
+            future::block_on(clone.as_bytes()).unwrap(),
+            &expected(2)[..]
+        );
+        assert_eq!(future::block_on(clone.trailers()).unwrap(), Some(trailers));
+
+        let error = future::block_on(chunked(2).into_replayable(CHUNK)).unwrap_err();
+        assert!(matches!(error, BodyError::TooLarge));
+    }
+}
@@ -8,7 +8,7 @@ use std::{io, pin::Pin};
 
 use futures_lite::{AsyncBufRead, AsyncRead};
 
-use super::{Body, BodyInner, BoxBufReader, BoxStream};
+use super::{Body, BodyInner, BoxBufReader, BoxStream, Error};
 
 pub(crate) enum IntoAsyncRead {
     Once(Reader<Bytes>),
@@ -52,7 +52,7 @@ fn poll_data(
 
     if let Some(data) = ready!(stream.as_mut().poll_next(cx))
         .transpose()
-        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?
+        .map_err(io::Error::other)?
     {
         if data.is_empty() {
             return poll_data(optional_stream, buf, cx);
@@ -79,10 +79,7 @@ impl AsyncRead for IntoAsyncRead {
                 ready!(poll_data(stream, buf, cx))?;
                 Poll::Ready(buf.read(read_buf))
             }
-            Self::Freeze => Poll::Ready(Err(io::Error::new(
-                io::ErrorKind::Other,
-                super::Error::BodyFrozen,
-            ))),
+            Self::Freeze => Poll::Ready(Err(io::Error::other(super::Error::BodyFrozen))),
         }
     }
 }
@@ -96,10 +93,7 @@ impl AsyncBufRead for IntoAsyncRead {
                 ready!(poll_data(stream, buf, cx))?;
                 Poll::Ready(buf.fill_buf())
             }
-            Self::Freeze => Poll::Ready(Err(io::Error::new(
-                io::ErrorKind::Other,
-                super::Error::BodyFrozen,
-            ))),
+            Self::Freeze => Poll::Ready(Err(io::Error::other(super::Error::BodyFrozen))),
         }
     }
 
@@ -112,4 +106,102 @@ impl AsyncBufRead for IntoAsyncRead {
         }
     }
 }
+
+// A reader failing if the data is longer or shorter than `remaining` bytes.
+pub(crate) struct Exact<R> {
+    reader: R,
+    remaining: usize,
+}
+
+impl<R> Exact<R> {
+    pub fn new(reader: R, length: usize) -> Self {
+        Self {
+            reader,
+            remaining: length,
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for Exact<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let data = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.consume(len);
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncBufRead for Exact<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        let data = ready!(Pin::new(&mut this.reader).poll_fill_buf(cx))?;
+        if data.len() > this.remaining || (data.is_empty() && this.remaining > 0) {
+            return Poll::Ready(Err(io::Error::other(Error::LengthMismatch)));
+        }
+        Poll::Ready(Ok(data))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.remaining -= amt;
+        Pin::new(&mut this.reader).consume(amt);
+    }
+}
+
+// A reader failing once more than `remaining` bytes are read.
+pub(crate) struct Limited<R> {
+    reader: R,
+    remaining: usize,
+    exceeded: bool,
+}
+
+impl<R> Limited<R> {
+    pub fn new(reader: R, remaining: usize, exceeded: bool) -> Self {
+        Self {
+            reader,
+            remaining,
+            exceeded,
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for Limited<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let data = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.consume(len);
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncBufRead for Limited<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        if !this.exceeded {
+            let data = ready!(Pin::new(&mut this.reader).poll_fill_buf(cx))?;
+            this.exceeded = data.len() > this.remaining;
+        }
+        if this.exceeded {
+            return Poll::Ready(Err(io::Error::other(Error::TooLarge)));
+        }
+        Pin::new(&mut this.reader).poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.remaining -= amt;
+        Pin::new(&mut this.reader).consume(amt);
+    }
+}
+
 // TODO: test them.
@@ -0,0 +1,732 @@
+//! An HTTP/1.1 client sharing the middleware pipeline of [`App`].
+//!
+//! A [`Client`] runs outgoing requests through its middlewares, then hands them to a terminal endpoint.
+//! By default the endpoint is a [`Connector`], which sends requests over TCP or a Unix socket and pools connections.
+//!
+//! # Example
+//! ```rust
+//! # use std::io::{BufRead, BufReader, Write};
+//! # let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+//! # let addr = listener.local_addr()?;
+//! # std::thread::spawn(move || -> std::io::Result<()> {
+//! #     let (mut stream, _) = listener.accept()?;
+//! #     let mut reader = BufReader::new(stream.try_clone()?);
+//! #     let mut line = String::new();
+//! #     while reader.read_line(&mut line)? > 2 {
+//! #         line.clear();
+//! #     }
+//! #     stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 11\r\n\r\nHello,world")
+//! # });
+//! # async_std::task::block_on(async {
+//! use http_kit::{client::Client, Request};
+//!
+//! let client = Client::new();
+//! let mut response = client.send(Request::get(format!("http://{addr}/"))).await?;
+//! assert_eq!(response.into_string().await?, "Hello,world");
+//! # http_kit::Result::Ok(())
+//! # }).unwrap();
+//! # std::io::Result::Ok(())
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use async_io::Timer;
+use async_net::TcpStream;
+use async_trait::async_trait;
+use futures_lite::{
+    future,
+    io::{BufReader, BufWriter},
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite,
+};
+use http::{Method, StatusCode};
+
+use crate::{
+    h1::{self, BodyDecoder, Parser},
+    middleware::SharedMiddleware,
+    App, Body, Endpoint, Middleware, Request, Response, Result,
+};
+
+/// An HTTP client running requests through middlewares before sending them with the endpoint `E`.
+pub struct Client<E: Endpoint = Connector> {
+    app: App<E>,
+}
+
+impl<E: Endpoint> Debug for Client<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client").field("app", &self.app).finish()
+    }
+}
+
+impl Client {
+    /// Create a client sending requests with a default [`Connector`].
+    pub fn new() -> Self {
+        Self::with_endpoint(Connector::new())
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Endpoint> Client<E> {
+    /// Create a client whose requests are finally handled by `endpoint`, which is useful for testing.
+    pub const fn with_endpoint(endpoint: E) -> Self {
+        Self {
+            app: App::new(endpoint),
+        }
+    }
+
+    /// Add a shared middleware to this client.
+    pub fn add_middleware(&mut self, middleware: SharedMiddleware) {
+        self.app.add_middleware(middleware);
+    }
+
+    /// Add a middleware to this client.
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.add_middleware(Arc::new(middleware));
+        self
+    }
+
+    /// Send a request and return the response.
+    pub async fn send(&self, request: Request) -> Result<Response> {
+        self.app.run(request).await
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(async_net::unix::UnixStream),
+}
+
+macro_rules! dispatch_stream {
+    ($self:expr, $stream:ident => $expr:expr) => {
+        match $self.get_mut() {
+            Stream::Tcp($stream) => $expr,
+            #[cfg(unix)]
+            Stream::Unix($stream) => $expr,
+        }
+    };
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        dispatch_stream!(self, stream => Pin::new(stream).poll_read(cx, buf))
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        dispatch_stream!(self, stream => Pin::new(stream).poll_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        dispatch_stream!(self, stream => Pin::new(stream).poll_flush(cx))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        dispatch_stream!(self, stream => Pin::new(stream).poll_close(cx))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Target {
+    Tcp {
+        host: String,
+        port: u16,
+    },
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+type Connection = BufReader<Stream>;
+
+struct Idle {
+    connection: Connection,
+    since: Instant,
+}
+
+// The settings are kept with the idle connections, so that they can be changed while the pool is shared.
+struct Pool(Mutex<PoolState>);
+
+struct PoolState {
+    idle: HashMap<Target, Vec<Idle>>,
+    max_idle_per_host: usize,
+    idle_timeout: Duration,
+}
+
+impl Pool {
+    fn state(&self) -> std::sync::MutexGuard<'_, PoolState> {
+        self.0.lock().unwrap()
+    }
+
+    fn take(&self, target: &Target) -> Option<Connection> {
+        let mut state = self.state();
+        let idle_timeout = state.idle_timeout;
+        let connections = state.idle.get_mut(target)?;
+        while let Some(connection) = connections.pop() {
+            if connection.since.elapsed() < idle_timeout {
+                return Some(connection.connection);
+            }
+        }
+        None
+    }
+
+    fn put(&self, target: Target, connection: Connection) {
+        let mut state = self.state();
+        let (idle_timeout, max_idle_per_host) = (state.idle_timeout, state.max_idle_per_host);
+        let connections = state.idle.entry(target).or_default();
+        connections.retain(|connection| connection.since.elapsed() < idle_timeout);
+        if connections.len() < max_idle_per_host {
+            connections.push(Idle {
+                connection,
+                since: Instant::now(),
+            });
+        }
+    }
+}
+
+/// An endpoint sending requests to remote servers over HTTP/1.1, reusing idle connections.
+///
+/// Only the `http` scheme is supported. Requests are sent over TCP to the authority of the URI,
+/// or to a fixed Unix socket if the connector is created by [`Connector::unix`].
+///
+/// A request failing on a pooled connection before any byte of the response is received is sent again
+/// on a new connection, if its method is idempotent and it has no body.
+/// Requests failing to connect or to receive the response head in time fail with `504 Gateway Timeout`,
+/// other failures fail with `502 Bad Gateway`.
+pub struct Connector {
+    pool: Arc<Pool>,
+    #[cfg(unix)]
+    unix: Option<PathBuf>,
+    parser: Parser,
+    connect_timeout: Duration,
+    response_timeout: Duration,
+}
+
+impl Debug for Connector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.pool.state();
+        let mut debug = f.debug_struct("Connector");
+        #[cfg(unix)]
+        debug.field("unix", &self.unix);
+        debug
+            .field("max_idle_per_host", &state.max_idle_per_host)
+            .field("idle_timeout", &state.idle_timeout)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("response_timeout", &self.response_timeout)
+            .finish()
+    }
+}
+
+impl Default for Connector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl_error!(UnsupportedScheme, "Only `http` scheme is supported");
+impl_error!(MissingHost, "Request URI has no host");
+
+impl Connector {
+    /// Create a connector sending requests over TCP.
+    pub fn new() -> Self {
+        Self {
+            pool: Arc::new(Pool(Mutex::new(PoolState {
+                idle: HashMap::new(),
+                max_idle_per_host: 16,
+                idle_timeout: Duration::from_secs(60),
+            }))),
+            #[cfg(unix)]
+            unix: None,
+            parser: Parser::new(),
+            connect_timeout: Duration::from_secs(10),
+            response_timeout: Duration::from_secs(60),
+        }
+    }
+
+    /// Create a connector sending all requests to the Unix socket at `path`, regardless of the host of the URI.
+    #[cfg(unix)]
+    pub fn unix(path: impl Into<PathBuf>) -> Self {
+        Self {
+            unix: Some(path.into()),
+            ..Self::new()
+        }
+    }
+
+    /// Set the maximum number of idle connections kept for each host, default to 16.
+    pub fn max_idle_per_host(self, max: usize) -> Self {
+        self.pool.state().max_idle_per_host = max;
+        self
+    }
+
+    /// Set how long an idle connection is kept in the pool, default to 60 seconds.
+    pub fn idle_timeout(self, timeout: Duration) -> Self {
+        self.pool.state().idle_timeout = timeout;
+        self
+    }
+
+    /// Set how long connecting to a server may take, default to 10 seconds.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Set how long sending a request and receiving the head of its response may take, default to 60 seconds.
+    /// The response body is not limited.
+    pub fn response_timeout(mut self, timeout: Duration) -> Self {
+        self.response_timeout = timeout;
+        self
+    }
+
+    /// Set the parser reading responses.
+    pub fn parser(mut self, parser: Parser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    fn target(&self, request: &Request) -> Result<Target> {
+        #[cfg(unix)]
+        if let Some(path) = &self.unix {
+            return Ok(Target::Unix(path.clone()));
+        }
+
+        let uri = request.uri();
+        if uri.scheme_str().is_some_and(|scheme| scheme != "http") {
+            return Err(crate::Error::new(
+                UnsupportedScheme::new(),
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+        let host = uri
+            .host()
+            .ok_or_else(|| crate::Error::new(MissingHost::new(), StatusCode::BAD_REQUEST))?;
+        Ok(Target::Tcp {
+            host: host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_owned(),
+            port: uri.port_u16().unwrap_or(80),
+        })
+    }
+
+    async fn connect(&self, target: &Target) -> io::Result<Connection> {
+        let stream = timeout(self.connect_timeout, async {
+            Ok(match target {
+                Target::Tcp { host, port } => {
+                    let stream = TcpStream::connect((host.as_str(), *port)).await?;
+                    stream.set_nodelay(true)?;
+                    Stream::Tcp(stream)
+                }
+                #[cfg(unix)]
+                Target::Unix(path) => {
+                    Stream::Unix(async_net::unix::UnixStream::connect(path).await?)
+                }
+            })
+        })
+        .await?;
+        Ok(BufReader::new(stream))
+    }
+
+    async fn connect_and_roundtrip(
+        &self,
+        target: &Target,
+        request: &mut Request,
+        method: &Method,
+    ) -> std::result::Result<(Connection, Response, h1::Framing), RoundtripError> {
+        let connection = self
+            .connect(target)
+            .await
+            .map_err(RoundtripError::after_response)?;
+        self.roundtrip(connection, request, method).await
+    }
+
+    async fn roundtrip(
+        &self,
+        mut connection: Connection,
+        request: &mut Request,
+        method: &Method,
+    ) -> std::result::Result<(Connection, Response, h1::Framing), RoundtripError> {
+        let roundtrip = async {
+            let mut writer = BufWriter::new(connection.get_ref().clone());
+            h1::write_request(&mut writer, request)
+                .await
+                .map_err(RoundtripError::before_response)?;
+            match connection.fill_buf().await {
+                Ok([]) => {
+                    return Err(RoundtripError::before_response(
+                        io::ErrorKind::UnexpectedEof.into(),
+                    ))
+                }
+                Err(error) => return Err(RoundtripError::before_response(error)),
+                Ok(_) => {}
+            }
+            loop {
+                let (response, framing) = self
+                    .parser
+                    .read_response_head(&mut connection, method)
+                    .await
+                    .map_err(RoundtripError::after_response)?;
+                // Interim responses are skipped.
+                if !response.status().is_informational()
+                    || response.status() == StatusCode::SWITCHING_PROTOCOLS
+                {
+                    return Ok((response, framing));
+                }
+            }
+        };
+        let (response, framing) = future::or(roundtrip, async {
+            Timer::after(self.response_timeout).await;
+            Err(RoundtripError::after_response(
+                io::ErrorKind::TimedOut.into(),
+            ))
+        })
+        .await?;
+        Ok((connection, response, framing))
+    }
+}
+
+async fn timeout<T>(
+    duration: Duration,
+    future: impl Future<Output = io::Result<T>>,
+) -> io::Result<T> {
+    future::or(future, async {
+        Timer::after(duration).await;
+        Err(io::ErrorKind::TimedOut.into())
+    })
+    .await
+}
+
+// The error of a roundtrip, recording whether any byte of the response was received,
+// in which case the server may have processed the request.
+struct RoundtripError {
+    error: io::Error,
+    before_response: bool,
+}
+
+impl RoundtripError {
+    fn before_response(error: io::Error) -> Self {
+        Self {
+            error,
+            before_response: true,
+        }
+    }
+
+    fn after_response(error: io::Error) -> Self {
+        Self {
+            error,
+            before_response: false,
+        }
+    }
+
+    fn into_http_error(self) -> crate::Error {
+        let status = if self.error.kind() == io::ErrorKind::TimedOut {
+            StatusCode::GATEWAY_TIMEOUT
+        } else {
+            StatusCode::BAD_GATEWAY
+        };
+        crate::Error::new(self.error, status)
+    }
+}
+
+// Return true if the method is idempotent (RFC 9110 Section 9.2.2).
+fn is_idempotent(method: &Method) -> bool {
+    [
+        Method::GET,
+        Method::HEAD,
+        Method::OPTIONS,
+        Method::TRACE,
+        Method::PUT,
+        Method::DELETE,
+    ]
+    .contains(method)
+}
+
+#[async_trait]
+impl Endpoint for Connector {
+    async fn call_endpoint(&self, request: &mut Request) -> Result<Response> {
+        let target = self.target(request)?;
+        let method = request.method().clone();
+        let body = request.take_body()?;
+        // An idempotent request without body can be safely resent if a pooled connection turns out to be closed.
+        let resendable = body.len() == Some(0) && is_idempotent(&method);
+        request.replace_body(body);
+
+        let pooled = self.pool.take(&target);
+        let reused = pooled.is_some();
+        let result = match pooled {
+            Some(connection) => self.roundtrip(connection, request, &method).await,
+            None => self.connect_and_roundtrip(&target, request, &method).await,
+        };
+        let (connection, mut response, framing) = match result {
+            Err(error) if reused && resendable && error.before_response => {
+                request.replace_body(Body::empty());
+                self.connect_and_roundtrip(&target, request, &method).await
+            }
+            result => result,
+        }
+        .map_err(RoundtripError::into_http_error)?;
+
+        // A body delimited by closing the connection leaves nothing to reuse.
+        let reuse = framing != h1::Framing::Close
+            && response.status() != StatusCode::SWITCHING_PROTOCOLS
+            && h1::wants_keep_alive(request.version(), request.headers())
+            && h1::wants_keep_alive(response.version(), response.headers());
+        let decoder = BodyDecoder::new(connection, framing);
+        if decoder.is_done() {
+            if reuse {
+                self.pool.put(target, decoder.into_inner());
+            }
+        } else {
+            response.replace_body(decoder.into_body(|decoder| ResponseBody {
+                decoder: Some(decoder),
+                release: reuse.then(|| (self.pool.clone(), target)),
+            }));
+        }
+        Ok(response)
+    }
+}
+
+// The response body, which gives the connection back to the pool once it is completely read.
+struct ResponseBody {
+    decoder: Option<BodyDecoder<Connection>>,
+    release: Option<(Arc<Pool>, Target)>,
+}
+
+impl ResponseBody {
+    fn release_if_done(&mut self) {
+        if self.decoder.as_ref().is_some_and(BodyDecoder::is_done) {
+            let decoder = self.decoder.take().expect("Decoder is checked");
+            if let Some((pool, target)) = self.release.take() {
+                pool.put(target, decoder.into_inner());
+            }
+        }
+    }
+}
+
+impl Drop for ResponseBody {
+    fn drop(&mut self) {
+        self.release_if_done();
+    }
+}
+
+impl AsyncRead for ResponseBody {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.release_if_done();
+        match &mut self.decoder {
+            Some(decoder) => Pin::new(decoder).poll_read(cx, buf),
+            None => Poll::Ready(Ok(0)),
+        }
+    }
+}
+
+impl AsyncBufRead for ResponseBody {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        this.release_if_done();
+        match &mut this.decoder {
+            Some(decoder) => Pin::new(decoder).poll_fill_buf(cx),
+            None => Poll::Ready(Ok(&[])),
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        if let Some(decoder) = &mut self.get_mut().decoder {
+            Pin::new(decoder).consume(amt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        thread,
+        time::Duration,
+    };
+
+    use futures_lite::future::block_on;
+    use http::StatusCode;
+
+    use super::Connector;
+    use crate::{Endpoint, Request};
+
+    const OK: &[u8] = b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+
+    type Handler = fn(&mut BufReader<TcpStream>, &mut TcpStream) -> std::io::Result<()>;
+
+    // Serve each connection accepted by the listener with the function, one connection after another.
+    fn serve(handlers: Vec<Handler>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for handler in handlers {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let _ = handler(&mut reader, &mut stream);
+            }
+        });
+        addr
+    }
+
+    fn read_head(reader: &mut impl BufRead) -> std::io::Result<()> {
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+        Ok(())
+    }
+
+    // Answer one request, then close the connection after reading the next one.
+    fn once_then_close(
+        reader: &mut BufReader<TcpStream>,
+        stream: &mut TcpStream,
+    ) -> std::io::Result<()> {
+        read_head(reader)?;
+        stream.write_all(OK)?;
+        read_head(reader)
+    }
+
+    fn answer(reader: &mut BufReader<TcpStream>, stream: &mut TcpStream) -> std::io::Result<()> {
+        read_head(reader)?;
+        stream.write_all(OK)
+    }
+
+    fn connector() -> Connector {
+        Connector::new().response_timeout(Duration::from_secs(5))
+    }
+
+    fn send(connector: &Connector, mut request: Request) -> crate::Result<String> {
+        block_on(async {
+            let mut response = connector.call_endpoint(&mut request).await?;
+            Ok(response.into_string().await?.to_string())
+        })
+    }
+
+    fn idle_connections(connector: &Connector) -> usize {
+        connector.pool.state().idle.values().map(Vec::len).sum()
+    }
+
+    #[test]
+    fn connection_is_reused() {
+        fn twice(reader: &mut BufReader<TcpStream>, stream: &mut TcpStream) -> std::io::Result<()> {
+            answer(reader, stream)?;
+            answer(reader, stream)
+        }
+        let addr = serve(vec![twice]);
+        let connector = connector();
+        for _ in 0..2 {
+            let body = send(&connector, Request::get(format!("http://{addr}/"))).unwrap();
+            assert_eq!(body, "ok");
+            assert_eq!(idle_connections(&connector), 1);
+        }
+    }
+
+    #[test]
+    fn pool_settings_while_shared() {
+        let addr = serve(vec![answer]);
+        let connector = connector();
+        let response = block_on(async {
+            let mut request = Request::get(format!("http://{addr}/"));
+            connector.call_endpoint(&mut request).await.unwrap()
+        });
+        // The unread body of the response still holds the pool.
+        let connector = connector
+            .max_idle_per_host(0)
+            .idle_timeout(Duration::from_secs(1));
+        drop(response);
+        assert_eq!(idle_connections(&connector), 0);
+    }
+
+    #[test]
+    fn close_delimited_response_is_not_pooled() {
+        fn close_delimited(
+            reader: &mut BufReader<TcpStream>,
+            stream: &mut TcpStream,
+        ) -> std::io::Result<()> {
+            read_head(reader)?;
+            stream.write_all(b"HTTP/1.1 200 OK\r\n\r\nhello")
+        }
+        let addr = serve(vec![close_delimited]);
+        let connector = connector();
+        let body = send(&connector, Request::get(format!("http://{addr}/"))).unwrap();
+        assert_eq!(body, "hello");
+        assert_eq!(idle_connections(&connector), 0);
+    }
+
+    #[test]
+    fn idempotent_request_is_resent() {
+        let addr = serve(vec![once_then_close, answer]);
+        let connector = connector();
+        send(&connector, Request::get(format!("http://{addr}/"))).unwrap();
+        let body = send(&connector, Request::get(format!("http://{addr}/"))).unwrap();
+        assert_eq!(body, "ok");
+    }
+
+    #[test]
+    fn non_idempotent_request_is_not_resent() {
+        let addr = serve(vec![once_then_close, answer]);
+        let connector = connector();
+        send(&connector, Request::get(format!("http://{addr}/"))).unwrap();
+        let error = send(&connector, Request::post(format!("http://{addr}/"))).unwrap_err();
+        assert_eq!(error.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn partial_response_is_not_resent() {
+        fn once_then_partial(
+            reader: &mut BufReader<TcpStream>,
+            stream: &mut TcpStream,
+        ) -> std::io::Result<()> {
+            answer(reader, stream)?;
+            read_head(reader)?;
+            stream.write_all(b"HTTP/1.1 20")
+        }
+        let addr = serve(vec![once_then_partial, answer]);
+        let connector = connector();
+        send(&connector, Request::get(format!("http://{addr}/"))).unwrap();
+        let error = send(&connector, Request::get(format!("http://{addr}/"))).unwrap_err();
+        assert_eq!(error.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn response_timeout() {
+        fn silent(reader: &mut BufReader<TcpStream>, _: &mut TcpStream) -> std::io::Result<()> {
+            read_head(reader)?;
+            thread::sleep(Duration::from_secs(2));
+            Ok(())
+        }
+        let addr = serve(vec![silent]);
+        let connector = Connector::new().response_timeout(Duration::from_millis(100));
+        let error = send(&connector, Request::get(format!("http://{addr}/"))).unwrap_err();
+        assert_eq!(error.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+}
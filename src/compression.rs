@@ -0,0 +1,596 @@
+//! Transparent response compression and request decompression.
+//!
+//! Each content coding is enabled by the cargo feature of the same name: `gzip`, `deflate`, `brotli` and `zstd`.
+//!
+//! # Example
+//! ```rust
+//! # #[cfg(feature = "gzip")]
+//! # async_std::task::block_on(async {
+//! use http_kit::{compression::Compression, endpoint_fn, header, App, Request, Response};
+//!
+//! let app = App::new(endpoint_fn(async |_request| {
+//!     Ok(Response::from("Hello,world".repeat(100)))
+//! }))
+//! .middleware(Compression::new());
+//!
+//! let request = Request::get("/").header(header::ACCEPT_ENCODING, "gzip");
+//! let response = app.run(request).await.unwrap();
+//! assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+//! assert_eq!(response.headers()[header::VARY], "accept-encoding");
+//! # })
+//! ```
+
+use std::{
+    fmt::Display,
+    io,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+};
+
+use async_trait::async_trait;
+use futures_lite::{io::BufReader, AsyncBufRead, AsyncRead};
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+
+use crate::{middleware::Next, Body, BodyError, Middleware, Request, Response, Result, ResultExt};
+
+impl_error!(UnsupportedEncoding, "Unsupported content coding");
+
+/// A content coding supported by this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// The `gzip` coding.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// The `deflate` coding, which is the zlib format.
+    #[cfg(feature = "deflate")]
+    Deflate,
+    /// The `br` coding.
+    #[cfg(feature = "brotli")]
+    Brotli,
+    /// The `zstd` coding.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+// Reader adapters of a body.
+type BoxReader = Pin<Box<dyn AsyncBufRead + Send + Sync + 'static>>;
+
+impl Encoding {
+    /// All enabled codings, in the order of preference when the client accepts several of them equally.
+    pub const ALL: &'static [Encoding] = &[
+        #[cfg(feature = "brotli")]
+        Encoding::Brotli,
+        #[cfg(feature = "zstd")]
+        Encoding::Zstd,
+        #[cfg(feature = "gzip")]
+        Encoding::Gzip,
+        #[cfg(feature = "deflate")]
+        Encoding::Deflate,
+    ];
+
+    /// Return the name of the coding used in `Content-Encoding`.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => "gzip",
+            #[cfg(feature = "deflate")]
+            Self::Deflate => "deflate",
+            #[cfg(feature = "brotli")]
+            Self::Brotli => "br",
+            #[cfg(feature = "zstd")]
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Wrap `reader` into a streaming encoder.
+    pub(crate) fn encode(self, reader: impl AsyncBufRead + Send + Sync + 'static) -> BoxReader {
+        use async_compression::futures::bufread;
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Box::pin(BufReader::new(bufread::GzipEncoder::new(reader))),
+            #[cfg(feature = "deflate")]
+            Self::Deflate => Box::pin(BufReader::new(bufread::ZlibEncoder::new(reader))),
+            #[cfg(feature = "brotli")]
+            Self::Brotli => Box::pin(BufReader::new(bufread::BrotliEncoder::new(reader))),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Box::pin(BufReader::new(bufread::ZstdEncoder::new(reader))),
+        }
+    }
+
+    /// Wrap `reader` into a streaming decoder, corrupt data fails with `BodyError::Decode`.
+    pub(crate) fn decode(self, reader: impl AsyncBufRead + Send + Sync + 'static) -> BoxReader {
+        use async_compression::futures::bufread;
+        let reader = Encoded(Box::pin(reader));
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                let mut decoder = bufread::GzipDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::pin(BufReader::new(Decoded(decoder)))
+            }
+            #[cfg(feature = "deflate")]
+            Self::Deflate => Box::pin(BufReader::new(Decoded(bufread::ZlibDecoder::new(reader)))),
+            #[cfg(feature = "brotli")]
+            Self::Brotli => Box::pin(BufReader::new(Decoded(bufread::BrotliDecoder::new(reader)))),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Box::pin(BufReader::new(Decoded(bufread::ZstdDecoder::new(reader)))),
+        }
+    }
+}
+
+// An error of the encoded data source, wrapped so that it is told apart from decoding errors.
+#[derive(Debug)]
+struct SourceError(io::Error);
+
+impl Display for SourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+fn wrap_source_error(error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), SourceError(error))
+}
+
+// The encoded data fed to a decoder.
+struct Encoded(BoxReader);
+
+impl AsyncRead for Encoded {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.0
+            .as_mut()
+            .poll_read(cx, buf)
+            .map_err(wrap_source_error)
+    }
+}
+
+impl AsyncBufRead for Encoded {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.get_mut()
+            .0
+            .as_mut()
+            .poll_fill_buf(cx)
+            .map_err(wrap_source_error)
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        self.0.as_mut().consume(amt);
+    }
+}
+
+// The output of a decoder, whose own errors are reported as `BodyError::Decode`.
+struct Decoded<R>(R);
+
+impl<R: AsyncRead + Unpin> AsyncRead for Decoded<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_read(cx, buf).map_err(|error| {
+            if error
+                .get_ref()
+                .is_some_and(|inner| inner.is::<SourceError>())
+            {
+                let inner = error.into_inner().expect("Inner error is checked");
+                inner.downcast::<SourceError>().expect("Type is checked").0
+            } else {
+                io::Error::other(BodyError::Decode(error))
+            }
+        })
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = UnsupportedEncoding;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        let name = name.trim();
+        #[cfg(feature = "gzip")]
+        if name.eq_ignore_ascii_case("x-gzip") {
+            return Ok(Self::Gzip);
+        }
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|encoding| encoding.as_str().eq_ignore_ascii_case(name))
+            .ok_or_else(UnsupportedEncoding::new)
+    }
+}
+
+// Choose the most preferred coding among `supported`.
+fn negotiate(headers: &HeaderMap, supported: &[Encoding]) -> Option<Encoding> {
+    let names: Vec<_> = supported.iter().map(|encoding| encoding.as_str()).collect();
+    let name = crate::negotiate::encoding(headers, &names)?;
+    supported
+        .iter()
+        .copied()
+        .find(|encoding| encoding.as_str() == name)
+}
+
+// Media types which are already compressed, or must be flushed promptly.
+fn is_incompressible(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if essence == "image/svg+xml" {
+        return false;
+    }
+    essence.starts_with("image/")
+        || essence.starts_with("audio/")
+        || essence.starts_with("video/")
+        || essence.starts_with("font/woff")
+        || matches!(
+            essence.as_str(),
+            "application/gzip"
+                | "application/x-gzip"
+                | "application/zip"
+                | "application/zstd"
+                | "application/x-bzip2"
+                | "application/x-xz"
+                | "application/x-7z-compressed"
+                | "application/x-rar-compressed"
+                | "application/vnd.rar"
+                | "application/pdf"
+                | "text/event-stream"
+        )
+}
+
+/// A middleware compressing response bodies with the coding negotiated from `Accept-Encoding`.
+///
+/// The body keeps streaming while being compressed, `Content-Length` is removed and a strong `ETag` is weakened.
+/// Responses are left untouched if they are already encoded, have a partial content,
+/// carry `Cache-Control: no-transform`, are smaller than the threshold or have an incompressible media type.
+#[derive(Debug, Clone)]
+pub struct Compression {
+    encodings: Vec<Encoding>,
+    min_size: usize,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compression {
+    /// Create a middleware supporting all enabled codings, with a threshold of 1 KiB.
+    pub fn new() -> Self {
+        Self {
+            encodings: Encoding::ALL.to_vec(),
+            min_size: 1024,
+        }
+    }
+
+    /// Set the supported codings, in the order of preference.
+    pub fn encodings(mut self, encodings: impl IntoIterator<Item = Encoding>) -> Self {
+        self.encodings = encodings.into_iter().collect();
+        self
+    }
+
+    /// Set the size in bytes below which bodies of known length are not compressed.
+    pub const fn min_size(mut self, size: usize) -> Self {
+        self.min_size = size;
+        self
+    }
+
+    fn is_compressible(&self, response: &Response, body: &Body) -> bool {
+        let status = response.status();
+        let headers = response.headers();
+        let no_transform = headers
+            .get_all(header::CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|item| item.trim().eq_ignore_ascii_case("no-transform"));
+        !(status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+            || status == StatusCode::PARTIAL_CONTENT
+            || headers.contains_key(header::CONTENT_ENCODING)
+            || headers.contains_key(header::CONTENT_RANGE)
+            || no_transform
+            || headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(is_incompressible)
+            || body.is_frozen()
+            || body.len().is_some_and(|len| len < self.min_size))
+    }
+}
+
+#[async_trait]
+impl Middleware for Compression {
+    async fn call_middleware(&self, request: &mut Request, next: Next<'_>) -> Result<Response> {
+        let encoding = negotiate(request.headers(), &self.encodings);
+        let mut response = next.run(request).await?;
+
+        let body = response.take_body()?;
+        if !self.is_compressible(&response, &body) {
+            response.replace_body(body);
+            return Ok(response);
+        }
+
+        let headers = response.headers_mut();
+        crate::negotiate::append_vary(headers, "accept-encoding");
+        let Some(encoding) = encoding else {
+            response.replace_body(body);
+            return Ok(response);
+        };
+
+        headers.remove(header::CONTENT_LENGTH);
+        headers.insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static(encoding.as_str()),
+        );
+        if let Some(etag) = headers.get(header::ETAG) {
+            if !etag.as_bytes().starts_with(b"W/") {
+                let mut weak = b"W/".to_vec();
+                weak.extend_from_slice(etag.as_bytes());
+                if let Ok(weak) = HeaderValue::from_bytes(&weak) {
+                    headers.insert(header::ETAG, weak);
+                }
+            }
+        }
+        response.replace_body(body.encode(encoding));
+        Ok(response)
+    }
+}
+
+/// A middleware decoding request bodies according to `Content-Encoding`.
+///
+/// The body is decompressed while being read, and `Content-Encoding` and `Content-Length` are removed.
+/// Requests with an unsupported coding are rejected with `415 Unsupported Media Type`,
+/// and reading more than the maximum decoded size fails with `BodyError::TooLarge`, guarding against decompression bombs.
+/// Reading corrupt data fails with `BodyError::Decode`, which is reported as `400 Bad Request`.
+#[derive(Debug, Clone)]
+pub struct Decompression {
+    max_size: usize,
+}
+
+impl Default for Decompression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decompression {
+    /// Create a middleware with a maximum decoded size of 16 MiB.
+    pub const fn new() -> Self {
+        Self {
+            max_size: 16 * 1024 * 1024,
+        }
+    }
+
+    /// Set the maximum size of a decoded body.
+    pub const fn max_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+}
+
+#[async_trait]
+impl Middleware for Decompression {
+    async fn call_middleware(&self, request: &mut Request, next: Next<'_>) -> Result<Response> {
+        let mut encodings = Vec::new();
+        for coding in request
+            .headers()
+            .get_all(header::CONTENT_ENCODING)
+            .iter()
+            .flat_map(|value| value.to_str().unwrap_or("\0").split(','))
+            .map(str::trim)
+            .filter(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"))
+        {
+            encodings.push(
+                coding
+                    .parse::<Encoding>()
+                    .status(StatusCode::UNSUPPORTED_MEDIA_TYPE)?,
+            );
+        }
+
+        if !encodings.is_empty() {
+            // Codings are listed in the order they were applied.
+            let body = encodings
+                .into_iter()
+                .rev()
+                .fold(request.take_body()?, Body::decode);
+            request.replace_body(body.limit(self.max_size));
+            let headers = request.headers_mut();
+            headers.remove(header::CONTENT_ENCODING);
+            headers.remove(header::CONTENT_LENGTH);
+        }
+        next.run(request).await
+    }
+}
+
+#[cfg(all(test, feature = "gzip"))]
+mod tests {
+    use std::io;
+
+    use futures_lite::{future::block_on, stream};
+    use http::{header, StatusCode};
+
+    use super::{Compression, Decompression, Encoding};
+    use crate::{endpoint_fn, App, Body, BodyError, Endpoint, Request, Response};
+
+    #[test]
+    fn round_trip() {
+        let body = Body::from("Hello,world").encode(Encoding::Gzip);
+        let body = block_on(body.decode(Encoding::Gzip).into_string()).unwrap();
+        assert_eq!(body, "Hello,world");
+    }
+
+    #[test]
+    fn corrupt_body_is_bad_request() {
+        let app = App::new(endpoint_fn(async |request: &mut Request| {
+            let error = request.into_bytes().await.unwrap_err();
+            assert!(matches!(error, BodyError::Decode(_)));
+            Err::<Response, _>(error.into_http_error())
+        }))
+        .middleware(Decompression::new());
+        let mut request = Request::post("/").header(header::CONTENT_ENCODING, "gzip");
+        request.replace_body("garbage");
+        let error = block_on(app.run(request)).unwrap_err();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn source_error_is_kept() {
+        let body = Body::from_stream(stream::once(Err::<&[u8], _>(io::Error::from(
+            io::ErrorKind::ConnectionReset,
+        ))));
+        let error = block_on(body.decode(Encoding::Gzip).into_bytes()).unwrap_err();
+        assert!(matches!(error, BodyError::Io(_)));
+    }
+
+    // An endpoint answering with the response built by the function.
+    struct Reply(fn() -> Response);
+
+    #[async_trait::async_trait]
+    impl Endpoint for Reply {
+        async fn call_endpoint(&self, _request: &mut Request) -> crate::Result<Response> {
+            Ok((self.0)())
+        }
+    }
+
+    fn compress(compression: Compression, accept: &str, reply: fn() -> Response) -> Response {
+        let app = App::new(Reply(reply)).middleware(compression);
+        let request = Request::get("/").header(header::ACCEPT_ENCODING, accept);
+        block_on(app.run(request)).unwrap()
+    }
+
+    fn encoding(response: &Response) -> Option<&str> {
+        response
+            .get_header(header::CONTENT_ENCODING)
+            .map(|value| value.to_str().unwrap())
+    }
+
+    fn large() -> Response {
+        Response::from("Hello,world".repeat(100))
+    }
+
+    #[test]
+    fn size_threshold() {
+        let small = || Response::from("Hello,world");
+        let response = compress(Compression::new(), "gzip", small);
+        assert_eq!(encoding(&response), None);
+        assert!(response.get_header(header::VARY).is_none());
+        assert_eq!(response.get_header(header::CONTENT_LENGTH), None);
+
+        let response = compress(Compression::new().min_size(5), "gzip", small);
+        assert_eq!(encoding(&response), Some("gzip"));
+
+        let mut response = compress(Compression::new(), "gzip", large);
+        assert_eq!(encoding(&response), Some("gzip"));
+        assert!(response.get_header(header::CONTENT_LENGTH).is_none());
+        let body = response.take_body().unwrap().decode(Encoding::Gzip);
+        assert_eq!(
+            block_on(body.into_string()).unwrap(),
+            "Hello,world".repeat(100)
+        );
+
+        // A body of unknown length is compressed regardless of the threshold.
+        let streaming = || {
+            Response::new(
+                StatusCode::OK,
+                Body::from_stream(stream::iter([Ok::<_, io::Error>("Hello,world")])),
+            )
+        };
+        let response = compress(Compression::new(), "gzip", streaming);
+        assert_eq!(encoding(&response), Some("gzip"));
+    }
+
+    #[test]
+    fn incompressible_media_types() {
+        fn typed(content_type: &'static str) -> Response {
+            large().header(header::CONTENT_TYPE, content_type)
+        }
+        let png = || typed("image/png");
+        let events = || typed("text/event-stream");
+        let zip = || typed("application/zip");
+        for reply in [png as fn() -> Response, events, zip] {
+            let response = compress(Compression::new(), "gzip", reply);
+            assert_eq!(encoding(&response), None);
+        }
+        let svg = || typed("image/svg+xml");
+        let html = || typed("Text/HTML; charset=utf-8");
+        for reply in [svg as fn() -> Response, html] {
+            let response = compress(Compression::new(), "gzip", reply);
+            assert_eq!(encoding(&response), Some("gzip"));
+        }
+
+        let no_transform = || large().header(header::CACHE_CONTROL, "public, no-transform");
+        let encoded = || large().header(header::CONTENT_ENCODING, "br");
+        for reply in [no_transform as fn() -> Response, encoded] {
+            let response = compress(Compression::new(), "gzip", reply);
+            assert_ne!(encoding(&response), Some("gzip"));
+        }
+    }
+
+    #[test]
+    fn vary_is_appended() {
+        let reply = || large().header(header::VARY, "origin");
+        let response = compress(Compression::new(), "gzip", reply);
+        let vary: Vec<_> = response.headers().get_all(header::VARY).iter().collect();
+        assert_eq!(vary, ["origin", "accept-encoding"]);
+
+        // The response varies even if it is not compressed for this request.
+        let response = compress(Compression::new(), "identity", reply);
+        assert_eq!(encoding(&response), None);
+        let vary: Vec<_> = response.headers().get_all(header::VARY).iter().collect();
+        assert_eq!(vary, ["origin", "accept-encoding"]);
+
+        let reply = || large().header(header::VARY, "Accept-Encoding");
+        let response = compress(Compression::new(), "gzip", reply);
+        assert_eq!(response.headers().get_all(header::VARY).iter().count(), 1);
+    }
+
+    #[test]
+    fn etag_is_weakened() {
+        let strong = || large().header(header::ETAG, "\"abc\"");
+        let response = compress(Compression::new(), "gzip", strong);
+        assert_eq!(response.headers()[header::ETAG], "W/\"abc\"");
+
+        let weak = || large().header(header::ETAG, "W/\"abc\"");
+        let response = compress(Compression::new(), "gzip", weak);
+        assert_eq!(response.headers()[header::ETAG], "W/\"abc\"");
+
+        let response = compress(Compression::new(), "identity", strong);
+        assert_eq!(response.headers()[header::ETAG], "\"abc\"");
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn quality_values() {
+        let compression = || Compression::new().encodings([Encoding::Gzip, Encoding::Deflate]);
+        for (accept, expected) in [
+            ("gzip, deflate", Some("gzip")),
+            ("deflate, gzip", Some("gzip")),
+            ("gzip;q=0.5, deflate", Some("deflate")),
+            ("gzip;q=0, deflate;q=0.1", Some("deflate")),
+            ("*;q=0.5, gzip;q=0.2", Some("deflate")),
+            ("gzip;q=0, *;q=0", None),
+            ("br", None),
+        ] {
+            let response = compress(compression(), accept, large);
+            assert_eq!(encoding(&response), expected, "{accept}");
+        }
+    }
+}
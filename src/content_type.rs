@@ -0,0 +1,219 @@
+//! Matching of the `Content-Type` header against accepted media types.
+//!
+//! Parameters and case are ignored, and structured syntax suffixes like `+json` can be accepted.
+//! # Example
+//! ```rust
+//! use http_kit::{content_type::ContentTypePolicy, Request};
+//!
+//! let request = Request::post("/").header(
+//!     http_kit::header::CONTENT_TYPE,
+//!     "application/vnd.api+json; charset=utf-8",
+//! );
+//! assert!(ContentTypePolicy::json().check(request.headers()).is_ok());
+//! assert!(ContentTypePolicy::form().check(request.headers()).is_err());
+//! ```
+
+use std::borrow::Cow;
+
+use http::{header, HeaderMap, StatusCode};
+
+impl_error!(ContentTypeMismatched, "Content-type is mismatched");
+
+impl_error!(MissingContentType, "Content-type is missing");
+
+/// A list of media types accepted for a body.
+///
+/// A message without `Content-Type` is rejected in strict mode (the default),
+/// and accepted in lenient mode.
+#[derive(Debug, Clone, Default)]
+pub struct ContentTypePolicy {
+    types: Vec<Cow<'static, str>>,
+    suffixes: Vec<Cow<'static, str>>,
+    lenient: bool,
+}
+
+impl ContentTypePolicy {
+    /// Create a policy accepting nothing.
+    pub const fn new() -> Self {
+        Self {
+            types: Vec::new(),
+            suffixes: Vec::new(),
+            lenient: false,
+        }
+    }
+
+    /// Accept `application/json` and media types with the `+json` suffix.
+    pub fn json() -> Self {
+        Self::new().accept("application/json").suffix("json")
+    }
+
+    /// Accept `application/x-www-form-urlencoded`.
+    pub fn form() -> Self {
+        Self::new().accept("application/x-www-form-urlencoded")
+    }
+
+    /// Accept a media type like `application/json`, or a range like `text/*`.
+    pub fn accept(mut self, media_type: impl Into<Cow<'static, str>>) -> Self {
+        self.types.push(media_type.into());
+        self
+    }
+
+    /// Accept media types with a structured syntax suffix, `suffix("json")` accepts `application/vnd.api+json`.
+    pub fn suffix(mut self, suffix: impl Into<Cow<'static, str>>) -> Self {
+        self.suffixes.push(suffix.into());
+        self
+    }
+
+    /// Accept a missing `Content-Type`.
+    pub const fn lenient(mut self) -> Self {
+        self.lenient = true;
+        self
+    }
+
+    /// Reject a missing `Content-Type`.
+    pub const fn strict(mut self) -> Self {
+        self.lenient = false;
+        self
+    }
+
+    /// Return `true` if the value of a `Content-Type` header is accepted.
+    pub fn matches(&self, content_type: &str) -> bool {
+        let Some((ty, subtype)) = essence(content_type) else {
+            return false;
+        };
+        let accepted = self.types.iter().any(|accepted| {
+            let (accepted_ty, accepted_subtype) =
+                accepted.split_once('/').unwrap_or((accepted, ""));
+            (accepted_ty == "*" || ty.eq_ignore_ascii_case(accepted_ty))
+                && (accepted_subtype == "*" || subtype.eq_ignore_ascii_case(accepted_subtype))
+        });
+        accepted
+            || subtype.rsplit_once('+').is_some_and(|(_, suffix)| {
+                self.suffixes
+                    .iter()
+                    .any(|accepted| suffix.eq_ignore_ascii_case(accepted))
+            })
+    }
+
+    /// Check the `Content-Type` of `headers`, fail with `415 Unsupported Media Type` if it is not accepted.
+    pub fn check(&self, headers: &HeaderMap) -> Result<(), crate::Error> {
+        match headers.get(header::CONTENT_TYPE) {
+            None if self.lenient => Ok(()),
+            None => Err(crate::Error::new(
+                MissingContentType::new(),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            )),
+            Some(value) => match value.to_str() {
+                Ok(value) if self.matches(value) => Ok(()),
+                _ => Err(crate::Error::new(
+                    ContentTypeMismatched::new(),
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                )),
+            },
+        }
+    }
+}
+
+/// Return whether `value` is a non-empty RFC 9110 token.
+pub(crate) fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+/// Split the essence of a media type into the type and the subtype, parameters are ignored.
+pub(crate) fn essence(media_type: &str) -> Option<(&str, &str)> {
+    let essence = media_type.split(';').next().unwrap_or_default().trim();
+    let (ty, subtype) = essence.split_once('/')?;
+    (is_token(ty) && is_token(subtype)).then_some((ty, subtype))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header, HeaderMap, HeaderValue, StatusCode};
+
+    use super::{ContentTypeMismatched, ContentTypePolicy, MissingContentType};
+
+    fn headers(content_type: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers
+    }
+
+    #[test]
+    fn essence() {
+        let json = ContentTypePolicy::json();
+        assert!(json.matches("application/json"));
+        assert!(json.matches("Application/JSON"));
+        assert!(json.matches("application/json; charset=utf-8"));
+        assert!(json.matches(" application/json ;charset=\"utf-8\""));
+        assert!(!json.matches("application/jsonp"));
+        assert!(!json.matches("text/json"));
+        assert!(!json.matches("application/json/x"));
+        assert!(!json.matches("application"));
+        assert!(!json.matches(""));
+
+        let text = ContentTypePolicy::new().accept("text/*");
+        assert!(text.matches("text/plain; charset=utf-8"));
+        assert!(text.matches("TEXT/html"));
+        assert!(!text.matches("application/xml"));
+        assert!(ContentTypePolicy::new().accept("*/*").matches("image/png"));
+        assert!(!ContentTypePolicy::new().matches("application/json"));
+    }
+
+    #[test]
+    fn suffixes() {
+        let json = ContentTypePolicy::json();
+        assert!(json.matches("application/vnd.api+json"));
+        assert!(json.matches("application/problem+JSON; charset=utf-8"));
+        assert!(!json.matches("application/vnd.api+xml"));
+        assert!(!json.matches("application/json+"));
+
+        let xml = ContentTypePolicy::new().accept("text/xml").suffix("xml");
+        assert!(xml.matches("image/svg+xml"));
+        assert!(xml.matches("application/atom+xml"));
+        assert!(!xml.matches("application/xml-dtd"));
+        assert!(!xml.matches("application/vnd.api+json"));
+    }
+
+    #[test]
+    fn missing_content_type() {
+        let error = ContentTypePolicy::json()
+            .check(&HeaderMap::new())
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(error.downcast_ref::<MissingContentType>().is_some());
+
+        let lenient = ContentTypePolicy::json().lenient();
+        assert!(lenient.check(&HeaderMap::new()).is_ok());
+        assert!(lenient.clone().strict().check(&HeaderMap::new()).is_err());
+        // Leniency does not accept a mismatched type.
+        assert!(lenient.check(&headers("text/plain")).is_err());
+    }
+
+    #[test]
+    fn mismatched_content_type() {
+        let form = ContentTypePolicy::form();
+        assert!(form
+            .check(&headers("application/x-www-form-urlencoded; charset=utf-8"))
+            .is_ok());
+        for content_type in [
+            "application/json",
+            "multipart/form-data; boundary=x",
+            "nonsense",
+        ] {
+            let error = form.check(&headers(content_type)).unwrap_err();
+            assert_eq!(error.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+            assert!(error.downcast_ref::<ContentTypeMismatched>().is_some());
+        }
+
+        let mut invalid = HeaderMap::new();
+        invalid.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_bytes(b"application/x-www-form-urlencoded; name=\xe9").unwrap(),
+        );
+        let error = form.check(&invalid).unwrap_err();
+        assert_eq!(error.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
@@ -0,0 +1,512 @@
+//! Cookies of requests and responses, and a cookie store for clients.
+//!
+//! Servers read cookies with [`Request::cookies`] and emit them with [`Response::set_cookie`],
+//! clients keep cookies across requests with the [`CookieStore`] middleware.
+//! With the `secure-cookie` feature, cookies can be signed or encrypted with the keys of a `Keyring`.
+//!
+//! # Example
+//! ```rust
+//! use http_kit::{cookie::{Cookie, SameSite}, header, Request, Response};
+//!
+//! let request = Request::get("/").header(header::COOKIE, "theme=dark; lang=en");
+//! let jar = request.cookies();
+//! assert_eq!(jar.get("theme").unwrap().value(), "dark");
+//!
+//! let mut response = Response::empty();
+//! response.set_cookie(
+//!     Cookie::build(("session", "abc"))
+//!         .path("/")
+//!         .secure(true)
+//!         .http_only(true)
+//!         .same_site(SameSite::Lax),
+//! )?;
+//! response.remove_cookie("theme")?;
+//! let cookies: Vec<_> = response.headers().get_all(header::SET_COOKIE).iter().collect();
+//! assert_eq!(cookies[0], "session=abc; HttpOnly; SameSite=Lax; Secure; Path=/");
+//! # Ok::<(), http_kit::header::InvalidHeaderValue>(())
+//! ```
+//!
+//! Signed cookies, verified with the current or a previous key:
+//! ```rust
+//! # #[cfg(feature = "secure-cookie")]
+//! # {
+//! use http_kit::{cookie::{CookieJar, Keyring}, header, Request, Response};
+//!
+//! let old = Keyring::new(&[1; 32]);
+//! let keys = Keyring::new(&[2; 32]).previous(&[1; 32]);
+//!
+//! let mut jar = CookieJar::new();
+//! old.signed(&mut jar).add(("user", "alice"));
+//! let mut response = Response::empty();
+//! response.set_cookies(&jar).unwrap();
+//!
+//! let set_cookie = response.get_header(header::SET_COOKIE).unwrap().clone();
+//! let request = Request::get("/").header(header::COOKIE, set_cookie);
+//! let jar = request.cookies();
+//! assert_eq!(keys.signed(&jar).get("user").unwrap().value(), "alice");
+//! assert!(Keyring::new(&[3; 32]).signed(&jar).get("user").is_none());
+//! # }
+//! ```
+
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use http::{header, HeaderValue, Uri};
+
+pub use ::cookie::{time, Cookie, CookieBuilder, CookieJar, Expiration, ParseError, SameSite};
+
+#[cfg(feature = "secure-cookie")]
+mod secure;
+#[cfg(feature = "secure-cookie")]
+pub use ::cookie::Key;
+#[cfg(feature = "secure-cookie")]
+pub use secure::{Keyring, PrivateJar, SignedJar};
+
+use crate::{middleware::Next, Middleware, Request, Response, Result};
+use time::OffsetDateTime;
+
+// The maximum number of cookies kept by a `CookieStore`, and for a single domain (RFC 6265, section 6.1).
+const MAX_COOKIES: usize = 3000;
+const MAX_COOKIES_PER_DOMAIN: usize = 50;
+
+// A cookie stored by `CookieStore`, following the storage model of RFC 6265.
+#[derive(Debug, Clone)]
+struct StoredCookie {
+    cookie: Cookie<'static>,
+    domain: String,
+    host_only: bool,
+    path: String,
+    expires: Option<OffsetDateTime>,
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: OffsetDateTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn matches(&self, host: &str, path: &str, secure: bool) -> bool {
+        let domain_matched = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(host, &self.domain)
+        };
+        domain_matched
+            && path_match(path, &self.path)
+            && (secure || self.cookie.secure() != Some(true))
+    }
+}
+
+// Return `true` if `host` is `domain` or a subdomain of it, an IP address only matches itself.
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+            && host.parse::<std::net::IpAddr>().is_err()
+}
+
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || path
+            .strip_prefix(cookie_path)
+            .is_some_and(|rest| cookie_path.ends_with('/') || rest.starts_with('/'))
+}
+
+// The directory of the request path, used when a cookie has no `Path`.
+fn default_path(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(index) => &path[..index],
+    }
+}
+
+// Return the lowercase host and whether the request is sent over a secure channel.
+fn origin(request: &Request) -> Option<(String, bool)> {
+    let uri = request.uri();
+    let host = match uri.host() {
+        Some(host) => host,
+        None => {
+            let host = request.get_header(header::HOST)?.to_str().ok()?;
+            host.rsplit_once(':')
+                .filter(|(_, port)| port.bytes().all(|byte| byte.is_ascii_digit()))
+                .map_or(host, |(host, _)| host)
+        }
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    Some((host.to_ascii_lowercase(), uri.scheme_str() == Some("https")))
+}
+
+/// A client-side middleware storing cookies set by responses, and sending them with later requests.
+///
+/// Cookies are matched by domain, path, `Secure` and expiry as described in RFC 6265.
+/// Public suffixes are not recognized, so the store should only be used with trusted servers.
+/// The store keeps at most 3000 cookies and 50 cookies per domain, evicting the oldest ones first.
+/// Clones of the store share the same cookies.
+#[derive(Clone, Default)]
+pub struct CookieStore {
+    cookies: Arc<Mutex<Vec<StoredCookie>>>,
+}
+
+impl Debug for CookieStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CookieStore")
+            .field("cookies", &self.cookies.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl CookieStore {
+    /// Create an empty cookie store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the cookies which would be sent to `uri`.
+    pub fn get(&self, uri: &Uri) -> Vec<Cookie<'static>> {
+        let Some(host) = uri.host() else {
+            return Vec::new();
+        };
+        let host = host.to_ascii_lowercase();
+        let secure = uri.scheme_str() == Some("https");
+        self.matched(&host, uri.path(), secure)
+    }
+
+    /// Store a cookie as if it was set by a response to `uri`.
+    pub fn insert(&self, cookie: Cookie<'static>, uri: &Uri) {
+        if let Some(host) = uri.host() {
+            let host = host.to_ascii_lowercase();
+            self.store(cookie, &host, uri.path(), uri.scheme_str() == Some("https"));
+        }
+    }
+
+    /// Remove all cookies.
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+    }
+
+    fn matched(&self, host: &str, path: &str, secure: bool) -> Vec<Cookie<'static>> {
+        let now = OffsetDateTime::now_utc();
+        let mut cookies = self.cookies.lock().unwrap();
+        cookies.retain(|cookie| !cookie.is_expired(now));
+        let mut matched: Vec<_> = cookies
+            .iter()
+            .filter(|cookie| cookie.matches(host, path, secure))
+            .collect();
+        // Cookies with longer paths are listed first.
+        matched.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        matched
+            .into_iter()
+            .map(|cookie| cookie.cookie.clone())
+            .collect()
+    }
+
+    fn store(&self, cookie: Cookie<'static>, host: &str, path: &str, secure: bool) {
+        if cookie.secure() == Some(true) && !secure {
+            return;
+        }
+        let now = OffsetDateTime::now_utc();
+        let expires = match (cookie.max_age(), cookie.expires_datetime()) {
+            (Some(max_age), _) => Some(now.saturating_add(max_age)),
+            (None, expires) => expires,
+        };
+        let (domain, host_only) = match cookie.domain() {
+            Some(domain) if !domain.is_empty() => {
+                let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+                if !domain_match(host, &domain) {
+                    return;
+                }
+                (domain, false)
+            }
+            _ => (host.to_owned(), true),
+        };
+        let path = match cookie.path() {
+            Some(path) if path.starts_with('/') => path.to_owned(),
+            _ => default_path(path).to_owned(),
+        };
+
+        let mut cookies = self.cookies.lock().unwrap();
+        cookies.retain(|stored| {
+            !(stored.is_expired(now)
+                || stored.cookie.name() == cookie.name()
+                    && stored.domain == domain
+                    && stored.path == path)
+        });
+        let stored = StoredCookie {
+            cookie,
+            domain,
+            host_only,
+            path,
+            expires,
+        };
+        if stored.is_expired(now) {
+            return;
+        }
+        // Cookies are kept in insertion order, so that the oldest ones are evicted first.
+        let same_domain = cookies
+            .iter()
+            .filter(|cookie| cookie.domain == stored.domain)
+            .count();
+        if same_domain >= MAX_COOKIES_PER_DOMAIN {
+            let oldest = cookies
+                .iter()
+                .position(|cookie| cookie.domain == stored.domain)
+                .unwrap();
+            cookies.remove(oldest);
+        }
+        if cookies.len() >= MAX_COOKIES {
+            cookies.remove(0);
+        }
+        cookies.push(stored);
+    }
+}
+
+#[async_trait]
+impl Middleware for CookieStore {
+    async fn call_middleware(&self, request: &mut Request, next: Next<'_>) -> Result<Response> {
+        let Some((host, secure)) = origin(request) else {
+            return next.run(request).await;
+        };
+        let path = request.uri().path().to_owned();
+
+        let cookies = self.matched(&host, &path, secure);
+        if !cookies.is_empty() {
+            let mut value = request
+                .get_header(header::COOKIE)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| vec![value.to_owned()])
+                .unwrap_or_default();
+            value.extend(
+                cookies
+                    .iter()
+                    .map(|cookie| cookie.encoded().stripped().to_string()),
+            );
+            if let Ok(value) = HeaderValue::from_str(&value.join("; ")) {
+                request.insert_header(header::COOKIE, value);
+            }
+        }
+
+        let response = next.run(request).await?;
+        for value in response.headers().get_all(header::SET_COOKIE) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            if let Ok(cookie) = Cookie::parse_encoded(value.to_owned()) {
+                self.store(cookie, &host, &path, secure);
+            }
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+    use http::{header, Uri};
+
+    use super::{
+        default_path, path_match, time::Duration, Cookie, CookieStore, MAX_COOKIES_PER_DOMAIN,
+    };
+    use crate::{endpoint_fn, App, Request, Response};
+
+    fn uri(uri: &'static str) -> Uri {
+        Uri::from_static(uri)
+    }
+
+    fn names(store: &CookieStore, to: &'static str) -> Vec<String> {
+        store
+            .get(&uri(to))
+            .iter()
+            .map(|cookie| cookie.name().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn domain_matching() {
+        let store = CookieStore::new();
+        store.insert(Cookie::new("host", "1"), &uri("http://example.com/"));
+        store.insert(
+            Cookie::build(("domain", "1"))
+                .domain(".Example.com")
+                .build(),
+            &uri("http://example.com/"),
+        );
+        // A server can not set cookies for another or a more specific domain.
+        store.insert(
+            Cookie::build(("other", "1")).domain("other.com").build(),
+            &uri("http://example.com/"),
+        );
+        store.insert(
+            Cookie::build(("sub", "1")).domain("a.example.com").build(),
+            &uri("http://example.com/"),
+        );
+
+        assert_eq!(names(&store, "http://example.com/"), ["host", "domain"]);
+        assert_eq!(names(&store, "http://a.EXAMPLE.com/"), ["domain"]);
+        assert!(names(&store, "http://badexample.com/").is_empty());
+        assert!(names(&store, "http://other.com/").is_empty());
+
+        // An IP address only matches itself.
+        store.insert(
+            Cookie::build(("ip", "1")).domain("0.0.1").build(),
+            &uri("http://127.0.0.1/"),
+        );
+        assert!(names(&store, "http://127.0.0.1/").is_empty());
+    }
+
+    #[test]
+    fn path_matching() {
+        assert!(path_match("/a", "/a"));
+        assert!(path_match("/a/b", "/a"));
+        assert!(path_match("/a/b", "/a/"));
+        assert!(path_match("/a/b", "/"));
+        assert!(!path_match("/ab", "/a"));
+        assert!(!path_match("/", "/a"));
+
+        assert_eq!(default_path(""), "/");
+        assert_eq!(default_path("/"), "/");
+        assert_eq!(default_path("/a"), "/");
+        assert_eq!(default_path("/a/b"), "/a");
+        assert_eq!(default_path("/a/b/"), "/a/b");
+
+        let store = CookieStore::new();
+        store.insert(Cookie::new("default", "1"), &uri("http://example.com/a/b"));
+        store.insert(
+            Cookie::build(("root", "1")).path("/").build(),
+            &uri("http://example.com/a/b"),
+        );
+        store.insert(
+            Cookie::build(("relative", "1")).path("c").build(),
+            &uri("http://example.com/a/b"),
+        );
+        // Cookies with longer paths are listed first.
+        assert_eq!(
+            names(&store, "http://example.com/a/c"),
+            ["default", "relative", "root"]
+        );
+        assert_eq!(names(&store, "http://example.com/b"), ["root"]);
+    }
+
+    #[test]
+    fn secure_cookies() {
+        let store = CookieStore::new();
+        let secure = || Cookie::build(("secure", "1")).secure(true).build();
+        // A secure cookie can not be set over an insecure channel.
+        store.insert(secure(), &uri("http://example.com/"));
+        assert!(names(&store, "https://example.com/").is_empty());
+
+        store.insert(secure(), &uri("https://example.com/"));
+        assert_eq!(names(&store, "https://example.com/"), ["secure"]);
+        assert!(names(&store, "http://example.com/").is_empty());
+    }
+
+    #[test]
+    fn expiry() {
+        let store = CookieStore::new();
+        let past = super::OffsetDateTime::now_utc() - Duration::days(1);
+        let future = super::OffsetDateTime::now_utc() + Duration::days(1);
+        store.insert(
+            Cookie::build(("expired", "1")).expires(past).build(),
+            &uri("http://example.com/"),
+        );
+        store.insert(
+            Cookie::build(("kept", "1")).expires(future).build(),
+            &uri("http://example.com/"),
+        );
+        // `Max-Age` takes precedence over `Expires`.
+        store.insert(
+            Cookie::build(("max-age", "1"))
+                .expires(past)
+                .max_age(Duration::days(1))
+                .build(),
+            &uri("http://example.com/"),
+        );
+        store.insert(
+            Cookie::build(("zero", "1"))
+                .expires(future)
+                .max_age(Duration::ZERO)
+                .build(),
+            &uri("http://example.com/"),
+        );
+        assert_eq!(names(&store, "http://example.com/"), ["kept", "max-age"]);
+
+        // An expired cookie evicts the stored one.
+        let mut removal = Cookie::new("kept", "");
+        removal.make_removal();
+        store.insert(removal, &uri("http://example.com/"));
+        assert_eq!(names(&store, "http://example.com/"), ["max-age"]);
+        assert_eq!(store.cookies.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn replacement() {
+        let store = CookieStore::new();
+        let to = uri("http://example.com/a/b");
+        store.insert(Cookie::new("a", "1"), &to);
+        store.insert(Cookie::new("a", "2"), &to);
+        // A cookie with another path is kept.
+        store.insert(Cookie::build(("a", "3")).path("/").build(), &to);
+        let values = |to| -> Vec<_> {
+            store
+                .get(&uri(to))
+                .iter()
+                .map(|cookie| cookie.value().to_owned())
+                .collect()
+        };
+        assert_eq!(values("http://example.com/a/b"), ["2", "3"]);
+
+        // A cookie with the same name, domain and path replaces the stored one, even if it is not host-only.
+        store.insert(Cookie::build(("a", "4")).domain("example.com").build(), &to);
+        assert_eq!(values("http://example.com/a/b"), ["4", "3"]);
+        assert_eq!(values("http://www.example.com/a/b"), ["4"]);
+    }
+
+    #[test]
+    fn number_of_cookies_is_limited() {
+        let store = CookieStore::new();
+        for index in 0..MAX_COOKIES_PER_DOMAIN + 10 {
+            store.insert(
+                Cookie::new(format!("c{index}"), "1"),
+                &uri("http://example.com/"),
+            );
+        }
+        store.insert(Cookie::new("other", "1"), &uri("http://other.com/"));
+        let cookies = names(&store, "http://example.com/");
+        assert_eq!(cookies.len(), MAX_COOKIES_PER_DOMAIN);
+        // The oldest cookies are evicted.
+        assert_eq!(cookies[0], "c10");
+        assert_eq!(names(&store, "http://other.com/"), ["other"]);
+    }
+
+    #[test]
+    fn middleware() {
+        let store = CookieStore::new();
+        let app = App::new(endpoint_fn(async |request: &mut Request| {
+            let cookie = request
+                .get_header(header::COOKIE)
+                .map(|value| value.to_str().unwrap().to_owned())
+                .unwrap_or_default();
+            let mut response = Response::from(cookie);
+            response.set_cookie(("session", "abc"))?;
+            Ok(response)
+        }))
+        .middleware(store.clone());
+
+        let mut response = block_on(app.run(Request::get("http://example.com/"))).unwrap();
+        assert_eq!(block_on(response.into_string()).unwrap(), "");
+        assert_eq!(names(&store, "http://example.com/"), ["session"]);
+
+        // Stored cookies are appended to the cookies of the request.
+        let request = Request::get("http://example.com/").header(header::COOKIE, "theme=dark");
+        let mut response = block_on(app.run(request)).unwrap();
+        assert_eq!(
+            block_on(response.into_string()).unwrap(),
+            "theme=dark; session=abc"
+        );
+
+        let mut response = block_on(app.run(Request::get("http://other.com/"))).unwrap();
+        assert_eq!(block_on(response.into_string()).unwrap(), "");
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    borrow::{Borrow, BorrowMut},
+    fmt::Debug,
+};
+
+use super::{Cookie, CookieJar, Key};
+
+/// The keys protecting signed and private cookies, the first key is used to protect new cookies
+/// while the previous keys are only used to verify existing ones, so that keys can be rotated.
+#[derive(Clone)]
+pub struct Keyring {
+    keys: Vec<Key>,
+}
+
+impl Debug for Keyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keyring")
+            .field("keys", &self.keys.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Keyring {
+    /// Derive the signing and encryption keys from a master key.
+    ///
+    /// # Panics
+    /// Panics if `master` is shorter than 32 bytes.
+    pub fn new(master: &[u8]) -> Self {
+        Self::from_key(Key::derive_from(master))
+    }
+
+    /// Create a keyring from a key.
+    pub fn from_key(key: Key) -> Self {
+        Self { keys: vec![key] }
+    }
+
+    /// Create a keyring with a randomly generated key, which is lost once the process exits.
+    pub fn generate() -> Self {
+        Self::from_key(Key::generate())
+    }
+
+    /// Accept cookies protected by a previous master key.
+    ///
+    /// # Panics
+    /// Panics if `master` is shorter than 32 bytes.
+    pub fn previous(mut self, master: &[u8]) -> Self {
+        self.keys.push(Key::derive_from(master));
+        self
+    }
+
+    /// Return the key protecting new cookies.
+    pub fn current(&self) -> &Key {
+        &self.keys[0]
+    }
+
+    /// Return a view of `jar` signing and verifying cookies.
+    pub fn signed<J>(&self, jar: J) -> SignedJar<'_, J> {
+        SignedJar {
+            parent: jar,
+            keys: self,
+        }
+    }
+
+    /// Return a view of `jar` encrypting and decrypting cookies.
+    pub fn private<J>(&self, jar: J) -> PrivateJar<'_, J> {
+        PrivateJar {
+            parent: jar,
+            keys: self,
+        }
+    }
+}
+
+/// A view of a [`CookieJar`] whose cookies are signed with HMAC-SHA256.
+///
+/// Values can be read by the client but not modified, cookies failing verification are ignored.
+#[derive(Debug)]
+pub struct SignedJar<'k, J> {
+    parent: J,
+    keys: &'k Keyring,
+}
+
+impl<J> SignedJar<'_, J> {
+    /// Verify a signed cookie with every key, return it with the original value if it is authentic.
+    pub fn verify(&self, cookie: Cookie<'static>) -> Option<Cookie<'static>> {
+        let jar = CookieJar::new();
+        self.keys
+            .keys
+            .iter()
+            .find_map(|key| jar.signed(key).verify(cookie.clone()))
+    }
+}
+
+impl<J: Borrow<CookieJar>> SignedJar<'_, J> {
+    /// Return the authentic cookie named `name`.
+    pub fn get(&self, name: &str) -> Option<Cookie<'static>> {
+        self.verify(self.parent.borrow().get(name)?.clone())
+    }
+}
+
+impl<J: BorrowMut<CookieJar>> SignedJar<'_, J> {
+    /// Sign `cookie` with the current key and add it to the jar.
+    pub fn add<C: Into<Cookie<'static>>>(&mut self, cookie: C) {
+        self.parent
+            .borrow_mut()
+            .signed_mut(self.keys.current())
+            .add(cookie);
+    }
+
+    /// Remove `cookie` from the jar.
+    pub fn remove<C: Into<Cookie<'static>>>(&mut self, cookie: C) {
+        self.parent.borrow_mut().remove(cookie);
+    }
+}
+
+/// A view of a [`CookieJar`] whose cookies are encrypted and authenticated with AES-256-GCM.
+///
+/// Values can neither be read nor modified by the client, cookies failing decryption are ignored.
+#[derive(Debug)]
+pub struct PrivateJar<'k, J> {
+    parent: J,
+    keys: &'k Keyring,
+}
+
+impl<J> PrivateJar<'_, J> {
+    /// Decrypt a private cookie with every key, return it with the original value if it is authentic.
+    pub fn decrypt(&self, cookie: Cookie<'static>) -> Option<Cookie<'static>> {
+        let jar = CookieJar::new();
+        self.keys
+            .keys
+            .iter()
+            .find_map(|key| jar.private(key).decrypt(cookie.clone()))
+    }
+}
+
+impl<J: Borrow<CookieJar>> PrivateJar<'_, J> {
+    /// Return the decrypted cookie named `name`.
+    pub fn get(&self, name: &str) -> Option<Cookie<'static>> {
+        self.decrypt(self.parent.borrow().get(name)?.clone())
+    }
+}
+
+impl<J: BorrowMut<CookieJar>> PrivateJar<'_, J> {
+    /// Encrypt `cookie` with the current key and add it to the jar.
+    pub fn add<C: Into<Cookie<'static>>>(&mut self, cookie: C) {
+        self.parent
+            .borrow_mut()
+            .private_mut(self.keys.current())
+            .add(cookie);
+    }
+
+    /// Remove `cookie` from the jar.
+    pub fn remove<C: Into<Cookie<'static>>>(&mut self, cookie: C) {
+        self.parent.borrow_mut().remove(cookie);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Keyring;
+    use crate::cookie::{Cookie, CookieJar};
+
+    const CURRENT: &[u8] = &[1; 32];
+    const PREVIOUS: &[u8] = &[2; 32];
+    const OTHER: &[u8] = &[3; 32];
+
+    // Replace a character of the cookie value with a different one.
+    fn tamper(cookie: &Cookie<'static>, index: usize) -> Cookie<'static> {
+        let mut value = cookie.value().as_bytes().to_vec();
+        value[index] = if value[index] == b'A' { b'B' } else { b'A' };
+        Cookie::new("session", String::from_utf8(value).unwrap())
+    }
+
+    #[test]
+    fn signed_round_trip_and_rotation() {
+        let mut jar = CookieJar::new();
+        Keyring::new(PREVIOUS)
+            .signed(&mut jar)
+            .add(Cookie::new("session", "alice"));
+        let signed = jar.get("session").unwrap().clone();
+        assert_ne!(signed.value(), "alice");
+
+        let keys = Keyring::new(CURRENT).previous(PREVIOUS);
+        let verified = keys.signed(&jar).get("session").unwrap();
+        assert_eq!(verified.value(), "alice");
+
+        assert!(Keyring::new(OTHER).signed(&jar).get("session").is_none());
+        for index in [0, signed.value().len() - 1] {
+            assert!(keys.signed(&jar).verify(tamper(&signed, index)).is_none());
+        }
+    }
+
+    #[test]
+    fn private_round_trip_and_rotation() {
+        let mut jar = CookieJar::new();
+        Keyring::new(PREVIOUS)
+            .private(&mut jar)
+            .add(Cookie::new("session", "alice"));
+        let encrypted = jar.get("session").unwrap().clone();
+        assert!(!encrypted.value().contains("alice"));
+
+        let keys = Keyring::new(CURRENT).previous(PREVIOUS);
+        let decrypted = keys.private(&jar).get("session").unwrap();
+        assert_eq!(decrypted.value(), "alice");
+
+        assert!(Keyring::new(OTHER).private(&jar).get("session").is_none());
+        for index in [0, encrypted.value().len() / 2, encrypted.value().len() - 1] {
+            assert!(keys
+                .private(&jar)
+                .decrypt(tamper(&encrypted, index))
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn add_uses_current_key() {
+        let keys = Keyring::new(CURRENT).previous(PREVIOUS);
+        let mut jar = CookieJar::new();
+        keys.signed(&mut jar).add(Cookie::new("signed", "alice"));
+        keys.private(&mut jar).add(Cookie::new("private", "bob"));
+
+        let current = Keyring::new(CURRENT);
+        assert_eq!(current.signed(&jar).get("signed").unwrap().value(), "alice");
+        assert_eq!(current.private(&jar).get("private").unwrap().value(), "bob");
+        let previous = Keyring::new(PREVIOUS);
+        assert!(previous.signed(&jar).get("signed").is_none());
+        assert!(previous.private(&jar).get("private").is_none());
+    }
+}
@@ -0,0 +1,162 @@
+//! Formatting and parsing of HTTP-dates.
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+// Convert days since the epoch into a civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// Convert a civil date into days since the epoch.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// Format seconds since the epoch as an IMF-fixdate.
+pub(crate) fn format_http_date(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let (year, month, day) = civil_from_days(days);
+    let time = secs % 86400;
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+// Parse a decimal number of `len` digits, signs are rejected unlike `str::parse`.
+fn number(value: &str, len: std::ops::RangeInclusive<usize>) -> Option<u32> {
+    if !len.contains(&value.len()) || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Parse an HTTP-date in the IMF-fixdate, the obsolete RFC 850 or the asctime format into seconds since the epoch.
+//
+// Years have four digits, two in the RFC 850 format, and impossible dates are rejected.
+pub(crate) fn parse_http_date(value: &str) -> Option<u64> {
+    let tokens: Vec<&str> = value.split_whitespace().collect();
+    let (day, month, year, time) = match tokens[..] {
+        // Sun, 06 Nov 1994 08:49:37 GMT
+        [_, day, month, year, time, "GMT"] => (day, month, number(year, 4..=4)?, time),
+        // Sunday, 06-Nov-94 08:49:37 GMT
+        [_, date, time, "GMT"] => {
+            let mut parts = date.split('-');
+            let (day, month, year) = (parts.next()?, parts.next()?, parts.next()?);
+            let year = number(year, 2..=2)?;
+            let year = if year < 70 { year + 2000 } else { year + 1900 };
+            (day, month, year, time)
+        }
+        // Sun Nov  6 08:49:37 1994
+        [_, month, day, time, year] => (day, month, number(year, 4..=4)?, time),
+        _ => return None,
+    };
+    let year = i64::from(year);
+    let day = number(day, 1..=2)?;
+    let month = MONTHS.iter().position(|name| *name == month)? as u32 + 1;
+    let mut time = time.split(':').map(|part| number(part, 2..=2));
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if time.next().is_some()
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    days.checked_mul(86400)?
+        .checked_add(u64::from(hour * 3600 + minute * 60 + second))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_http_date, parse_http_date};
+
+    #[test]
+    fn formats() {
+        let secs = 784_111_777;
+        assert_eq!(format_http_date(secs), "Sun, 06 Nov 1994 08:49:37 GMT");
+        for value in [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+        ] {
+            assert_eq!(parse_http_date(value), Some(secs), "{value}");
+        }
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT"),
+            Some(1_709_164_800)
+        );
+        assert_eq!(
+            parse_http_date("Fri, 31 Dec 9999 23:59:59 GMT").map(format_http_date),
+            Some("Fri, 31 Dec 9999 23:59:59 GMT".to_owned())
+        );
+    }
+
+    #[test]
+    fn huge_years() {
+        for value in [
+            "Sun, 06 Nov 500000000000 08:49:37 GMT",
+            "Sun, 06 Nov 99999999999999999999 08:49:37 GMT",
+            "Sun, 06 Nov 10000 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 500000000000",
+            "Sunday, 06-Nov-500000000000 08:49:37 GMT",
+        ] {
+            assert_eq!(parse_http_date(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn impossible_dates() {
+        for value in [
+            "Sat, 31 Feb 2024 00:00:00 GMT",
+            "Fri, 29 Feb 2023 00:00:00 GMT",
+            "Thu, 29 Feb 1900 00:00:00 GMT",
+            "Thu, 31 Apr 2024 00:00:00 GMT",
+            "Thu, 00 Apr 2024 00:00:00 GMT",
+            "Thu, 01 Apr 2024 24:00:00 GMT",
+            "Thu, +1 Apr 2024 00:00:00 GMT",
+            "Thu, 01 Apr 2024 00:00:00:00 GMT",
+            "Thu, 01 Apr 1969 00:00:00 GMT",
+        ] {
+            assert_eq!(parse_http_date(value), None, "{value}");
+        }
+    }
+}
@@ -51,8 +51,149 @@ impl Endpoint for () {
     }
 }
 
+/// An owned dynamically typed `Future` with a lifetime, which is the future type returned by async trait methods.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A closure or an async function usable by [`endpoint_fn`], implemented automatically.
+pub trait EndpointFn<'a>: Send + Sync {
+    /// The future returned by the closure.
+    type Future: Future<Output = Result<Response>> + Send + 'a;
+
+    /// Call the closure.
+    fn call(&self, request: &'a mut Request) -> Self::Future;
+}
+
+impl<'a, F, Fut> EndpointFn<'a> for F
+where
+    F: Fn(&'a mut Request) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Response>> + Send + 'a,
+{
+    type Future = Fut;
+
+    fn call(&self, request: &'a mut Request) -> Fut {
+        self(request)
+    }
+}
+
+// Return the type name of `F`, or the path of the function defining it if it is a closure.
+pub(crate) fn fn_name<F>() -> &'static str {
+    let name = type_name::<F>();
+    name.find("::{{closure}}").map_or(name, |end| &name[..end])
+}
+
+/// An endpoint created from a closure by [`endpoint_fn`].
+pub struct FnEndpoint<F> {
+    f: F,
+    name: &'static str,
+}
+
+impl<F> FnEndpoint<F> {
+    /// Set the name reported by `Endpoint::name`, which defaults to the type name of an async function,
+    /// or the path of the function defining a closure.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+}
+
+impl<F> Debug for FnEndpoint<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FnEndpoint").field(&self.name).finish()
+    }
+}
+
+/// Create an endpoint from an async closure or an async function taking `&mut Request`.
+///
+/// The future of an async closure can not borrow the values owned by the closure,
+/// share them with `App::state` or clone them before the closure instead.
+/// # Example
+/// ```rust
+/// use http_kit::{endpoint_fn, App, Request, Response, Result};
+///
+/// let app = App::new(
+///     endpoint_fn(async |request: &mut Request| {
+///         let body = request.into_string().await?;
+///         Ok(Response::from(format!("{} {body}", request.uri().path())))
+///     })
+///     .with_name("echo"),
+/// );
+///
+/// async fn hello(_request: &mut Request) -> Result<Response> {
+///     Ok(Response::from("Hello,world"))
+/// }
+/// let app = App::new(endpoint_fn(hello));
+/// ```
+pub fn endpoint_fn<F>(f: F) -> FnEndpoint<F>
+where
+    F: for<'a> AsyncFn(&'a mut Request) -> Result<Response> + for<'a> EndpointFn<'a>,
+{
+    FnEndpoint {
+        f,
+        name: fn_name::<F>(),
+    }
+}
+
+impl<F> Endpoint for FnEndpoint<F>
+where
+    F: for<'a> EndpointFn<'a>,
+{
+    fn call_endpoint<'life0, 'life1, 'async_trait>(
+        &'life0 self,
+        request: &'life1 mut Request,
+    ) -> BoxFuture<'async_trait, Result<Response>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        Self: 'async_trait,
+    {
+        Box::pin(self.f.call(request))
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
 impl Debug for dyn Endpoint {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.name())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{endpoint_fn, Endpoint};
+    use crate::{middleware_fn, App, Request, Response, Result};
+
+    async fn hello(_request: &mut Request) -> Result<Response> {
+        Ok(Response::from("hello"))
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            endpoint_fn(hello).name(),
+            "http_kit::endpoint::tests::hello"
+        );
+        let closure = endpoint_fn(async |_request| Ok(Response::empty()));
+        assert_eq!(closure.name(), "http_kit::endpoint::tests::names");
+        assert_eq!(closure.with_name("empty").name(), "empty");
+    }
+
+    #[test]
+    fn closures_borrow_request() {
+        let app = App::new(endpoint_fn(async |request| {
+            let body = request.into_string().await?;
+            Ok(Response::from(format!("{} {body}", request.uri().path())))
+        }))
+        .middleware(middleware_fn(async |request, next| {
+            request.replace_body("world");
+            next.run(request).await
+        }));
+
+        async_std::task::block_on(async {
+            let mut response = app.run(Request::get("/hello")).await.unwrap();
+            assert_eq!(response.into_string().await.unwrap(), "/hello world");
+        });
+    }
+}
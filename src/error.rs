@@ -147,12 +147,11 @@ where
     /// Wrap the error type with status code.
     /// # Example
     /// ```
-    /// use std::fs::File;
-    /// use std::io::prelude::*;
     /// use http_kit::{Body,ResultExt};
-    /// use async_fs::File;
-    /// fn handler() -> http_kit::Result<Body>{
-    ///     Ok(Body::from_reader(File::open("index.html").await.status(404)?))
+    /// use async_std::{fs::File,io::BufReader};
+    /// async fn handler() -> http_kit::Result<Body>{
+    ///     let file = File::open("index.html").await.status(404)?;
+    ///     Ok(Body::from_reader(BufReader::new(file),None))
     /// }
     /// ```
     fn status<S>(self, status: S) -> Result<T>
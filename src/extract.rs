@@ -0,0 +1,512 @@
+//! Typed extractors and handler adapters.
+//!
+//! Any type implementing [`FromRequest`] can be extracted from a request.
+//! An async function whose arguments are extractors can be turned into an endpoint by [`handler`].
+//!
+//! # Example
+//! ```rust
+//! # #[cfg(all(feature = "json", feature = "form"))]
+//! # fn main() {
+//! use http_kit::{
+//!     extract::{handler, Json, Query, State},
+//!     App, Result, StatusCode,
+//! };
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Filter {
+//!     dry_run: bool,
+//! }
+//!
+//! #[derive(Clone)]
+//! struct Db;
+//!
+//! async fn create(
+//!     Json(user): Json<User>,
+//!     Query(q): Query<Filter>,
+//!     State(_db): State<Db>,
+//! ) -> Result<(StatusCode, String)> {
+//!     Ok((StatusCode::CREATED, format!("{} {}", user.name, q.dry_run)))
+//! }
+//!
+//! let app = App::new(handler(create)).state(Db);
+//! # }
+//! # #[cfg(not(all(feature = "json", feature = "form")))]
+//! # fn main() {}
+//! ```
+
+use std::{
+    fmt::Debug,
+    future::Future,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use bytestr::ByteStr;
+use http::{HeaderMap, Method, StatusCode, Uri, Version};
+
+use crate::{
+    middleware::Next, multipart::Multipart, router::Params, Body, BodyError, Endpoint,
+    IntoResponse, Middleware, Request, Response, Result,
+};
+
+#[cfg(feature = "json")]
+use crate::ResultExt;
+
+/// Types that can be created from a request.
+#[async_trait]
+pub trait FromRequest: Sized {
+    /// Extract `Self` from the request.
+    async fn from_request(request: &mut Request) -> Result<Self>;
+}
+
+macro_rules! impl_wrapper {
+    ($($ty:ident),*) => {
+        $(
+            impl<T> $ty<T> {
+                /// Consume the wrapper and return the inner value.
+                pub fn into_inner(self) -> T {
+                    self.0
+                }
+            }
+
+            impl<T> Deref for $ty<T> {
+                type Target = T;
+
+                fn deref(&self) -> &Self::Target {
+                    &self.0
+                }
+            }
+
+            impl<T> DerefMut for $ty<T> {
+                fn deref_mut(&mut self) -> &mut Self::Target {
+                    &mut self.0
+                }
+            }
+        )*
+    };
+}
+
+/// Extract a JSON body, rejecting requests whose `Content-Type` is not JSON.
+/// As a response, the inner value will be serialized into a JSON body.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+#[cfg(feature = "json")]
+impl_wrapper![Json];
+
+#[cfg(feature = "json")]
+#[async_trait]
+impl<T: serde::de::DeserializeOwned> FromRequest for Json<T> {
+    async fn from_request(request: &mut Request) -> Result<Self> {
+        Ok(Self(request.into_json().await?))
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: serde::Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        IntoResponse::into_response(self.into_result())
+    }
+
+    fn into_result(self) -> Result<Response> {
+        Response::empty()
+            .json(&self.0)
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// Extract an URL-encoded form body, rejecting requests whose `Content-Type` is not a form.
+#[cfg(feature = "form")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Form<T>(pub T);
+
+#[cfg(feature = "form")]
+impl_wrapper![Form];
+
+#[cfg(feature = "form")]
+#[async_trait]
+impl<T: serde::de::DeserializeOwned> FromRequest for Form<T> {
+    async fn from_request(request: &mut Request) -> Result<Self> {
+        Ok(Self(request.into_form().await?))
+    }
+}
+
+/// Extract the query string of the URI, repeated keys are deserialized into sequences.
+#[cfg(feature = "form")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+#[cfg(feature = "form")]
+impl_wrapper![Query];
+
+#[cfg(feature = "form")]
+#[async_trait]
+impl<T: serde::de::DeserializeOwned> FromRequest for Query<T> {
+    async fn from_request(request: &mut Request) -> Result<Self> {
+        request.query().map(Self)
+    }
+}
+
+/// Extract a clone of the shared state stored in request extensions, see `App::state`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct State<T>(pub T);
+
+impl_wrapper![State];
+
+impl_error!(
+    StateMissing,
+    "State is missing from request extensions, you may forget to call `App::state`"
+);
+
+#[async_trait]
+impl<T: Clone + Send + Sync + 'static> FromRequest for State<T> {
+    async fn from_request(request: &mut Request) -> Result<Self> {
+        request
+            .get_extension::<T>()
+            .cloned()
+            .map(Self)
+            .ok_or_else(|| {
+                crate::Error::new(StateMissing::new(), StatusCode::INTERNAL_SERVER_ERROR)
+            })
+    }
+}
+
+// A middleware inserting a clone of the state into every request.
+pub(crate) struct AddState<T>(pub(crate) T);
+
+#[async_trait]
+impl<T: Clone + Send + Sync + 'static> Middleware for AddState<T> {
+    async fn call_middleware(&self, request: &mut Request, next: Next<'_>) -> Result<Response> {
+        request.insert_extension(self.0.clone());
+        next.run(request).await
+    }
+}
+
+#[async_trait]
+impl FromRequest for Body {
+    async fn from_request(request: &mut Request) -> Result<Self> {
+        Ok(request.take_body()?)
+    }
+}
+
+#[async_trait]
+impl FromRequest for Bytes {
+    async fn from_request(request: &mut Request) -> Result<Self> {
+        request
+            .into_bytes()
+            .await
+            .map_err(BodyError::into_http_error)
+    }
+}
+
+#[async_trait]
+impl FromRequest for ByteStr {
+    async fn from_request(request: &mut Request) -> Result<Self> {
+        request
+            .into_string()
+            .await
+            .map_err(BodyError::into_http_error)
+    }
+}
+
+#[async_trait]
+impl FromRequest for Multipart {
+    async fn from_request(request: &mut Request) -> Result<Self> {
+        request.into_multipart()
+    }
+}
+
+#[async_trait]
+impl FromRequest for Params {
+    async fn from_request(request: &mut Request) -> Result<Self> {
+        Ok(request
+            .get_extension::<Params>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+macro_rules! impl_from_request_parts {
+    ($(($ty:ty,$method:ident)),*) => {
+        $(
+            #[async_trait]
+            impl FromRequest for $ty {
+                async fn from_request(request: &mut Request) -> Result<Self> {
+                    Ok(request.$method().clone())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_request_parts![
+    (Method, method),
+    (Uri, uri),
+    (Version, version),
+    (HeaderMap, headers)
+];
+
+/// An async function whose arguments can be extracted from a request.
+#[async_trait]
+pub trait Handler<Args>: Send + Sync {
+    /// Extract the arguments from the request and call the handler.
+    async fn call_handler(&self, request: &mut Request) -> Result<Response>;
+}
+
+macro_rules! impl_handler {
+    ($($ty:ident),*) => {
+        #[async_trait]
+        impl<F, Fut, $($ty,)*> Handler<($($ty,)*)> for F
+        where
+            F: Fn($($ty),*) -> Fut + Send + Sync,
+            Fut: Future + Send,
+            Fut::Output: IntoResponse,
+            $($ty: FromRequest + Send,)*
+        {
+            #[allow(non_snake_case, unused_variables)]
+            async fn call_handler(&self, request: &mut Request) -> Result<Response> {
+                $(let $ty = $ty::from_request(request).await?;)*
+                (self)($($ty),*).await.into_result()
+            }
+        }
+    };
+}
+
+impl_handler!();
+impl_handler!(T1);
+impl_handler!(T1, T2);
+impl_handler!(T1, T2, T3);
+impl_handler!(T1, T2, T3, T4);
+impl_handler!(T1, T2, T3, T4, T5);
+impl_handler!(T1, T2, T3, T4, T5, T6);
+impl_handler!(T1, T2, T3, T4, T5, T6, T7);
+impl_handler!(T1, T2, T3, T4, T5, T6, T7, T8);
+
+/// An endpoint created from a handler by [`handler`].
+pub struct HandlerEndpoint<H, Args> {
+    handler: H,
+    _marker: PhantomData<fn() -> Args>,
+}
+
+impl<H, Args> Debug for HandlerEndpoint<H, Args> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("HandlerEndpoint")
+            .field(&std::any::type_name::<H>())
+            .finish()
+    }
+}
+
+/// Turn an async function taking extractors and returning any `IntoResponse` into an endpoint.
+/// Arguments are extracted in order, the first failing extraction is returned as the error.
+pub fn handler<H, Args>(handler: H) -> HandlerEndpoint<H, Args>
+where
+    H: Handler<Args>,
+{
+    HandlerEndpoint {
+        handler,
+        _marker: PhantomData,
+    }
+}
+
+#[async_trait]
+impl<H, Args> Endpoint for HandlerEndpoint<H, Args>
+where
+    H: Handler<Args>,
+{
+    async fn call_endpoint(&self, request: &mut Request) -> Result<Response> {
+        self.handler.call_handler(request).await
+    }
+
+    fn name(&self) -> &'static str {
+        std::any::type_name::<H>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures_lite::future::block_on;
+    use http::{header, Method, StatusCode};
+
+    use super::{handler, FromRequest, State};
+    use crate::{App, IntoResponse, Request, Response, Result};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Db(&'static str);
+
+    fn status<T: FromRequest>(mut request: Request) -> StatusCode {
+        match block_on(T::from_request(&mut request)) {
+            Ok(_) => StatusCode::OK,
+            Err(error) => error.status(),
+        }
+    }
+
+    fn run<E: crate::Endpoint>(app: &App<E>, request: Request) -> (StatusCode, String) {
+        block_on(async {
+            let mut response = app
+                .run(request)
+                .await
+                .unwrap_or_else(IntoResponse::into_response);
+            let body = response.into_string().await.unwrap().to_string();
+            (response.status(), body)
+        })
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json() {
+        use super::Json;
+
+        #[derive(serde::Deserialize)]
+        struct User {
+            name: String,
+        }
+
+        let json = |content_type: &str, body: &'static str| {
+            Request::post("/")
+                .header(header::CONTENT_TYPE, content_type)
+                .map_body(|_| body.into())
+        };
+        let mut request = json("application/json", r#"{"name":"alice"}"#);
+        let Json(user) = block_on(Json::<User>::from_request(&mut request)).unwrap();
+        assert_eq!(user.name, "alice");
+
+        let request = json("application/json", r#"{"name":"#);
+        assert_eq!(status::<Json<User>>(request), StatusCode::BAD_REQUEST);
+        let request = json("application/json", r#"{"age":1}"#);
+        assert_eq!(status::<Json<User>>(request), StatusCode::BAD_REQUEST);
+        let request = json("text/plain", r#"{"name":"alice"}"#);
+        assert_eq!(
+            status::<Json<User>>(request),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+    }
+
+    #[cfg(feature = "form")]
+    #[test]
+    fn form_and_query() {
+        use super::{Form, Query};
+
+        #[derive(serde::Deserialize)]
+        struct Login {
+            name: String,
+            remember: bool,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Filter {
+            tag: Vec<String>,
+            limit: u32,
+        }
+
+        let form = |body: &'static str| {
+            Request::post("/")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .map_body(|_| body.into())
+        };
+        let mut request = form("name=alice&remember=true");
+        let Form(login) = block_on(Form::<Login>::from_request(&mut request)).unwrap();
+        assert_eq!((login.name.as_str(), login.remember), ("alice", true));
+        assert_eq!(
+            status::<Form<Login>>(form("name=alice&remember=maybe")),
+            StatusCode::BAD_REQUEST
+        );
+
+        let mut request = Request::get("/?tag=a&tag=b&limit=5");
+        let Query(filter) = block_on(Query::<Filter>::from_request(&mut request)).unwrap();
+        assert_eq!(
+            (filter.tag, filter.limit),
+            (vec!["a".to_owned(), "b".to_owned()], 5)
+        );
+        assert_eq!(
+            status::<Query<Filter>>(Request::get("/?tag=a")),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn state() {
+        assert_eq!(
+            status::<State<Db>>(Request::get("/")),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        async fn name(State(db): State<Db>) -> &'static str {
+            db.0
+        }
+        let (status, body) = run(
+            &App::new(handler(name)).state(Db("main")),
+            Request::get("/"),
+        );
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "main"));
+        let (status, _) = run(&App::new(handler(name)), Request::get("/"));
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+        // Middlewares see the state whether they are added before or after it.
+        fn seen() -> impl crate::Middleware {
+            crate::middleware_fn(async |request, next| {
+                let State(db) = State::<Db>::from_request(request).await?;
+                let mut response = next.run(request).await?;
+                response.insert_header(header::SERVER, http::HeaderValue::from_static(db.0));
+                Ok(response)
+            })
+        }
+        let app = App::new(handler(name))
+            .middleware(seen())
+            .state(Db("main"))
+            .middleware(seen());
+        let (status, body) = run(&app, Request::get("/"));
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "main"));
+    }
+
+    #[test]
+    fn parts_and_bodies() {
+        let mut request = Request::post("/path").header(header::ACCEPT, "text/plain");
+        assert_eq!(
+            block_on(Method::from_request(&mut request)).unwrap(),
+            Method::POST
+        );
+        let headers = block_on(http::HeaderMap::from_request(&mut request)).unwrap();
+        assert_eq!(headers[header::ACCEPT], "text/plain");
+
+        let mut request = Request::post("/").map_body(|_| "Hello".into());
+        assert_eq!(
+            block_on(Bytes::from_request(&mut request)).unwrap(),
+            "Hello"
+        );
+        let request = Request::post("/").map_body(|_| vec![0xFF, 0xFE].into());
+        assert_eq!(status::<bytestr::ByteStr>(request), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn several_arguments() {
+        async fn echo(method: Method, State(db): State<Db>, body: Bytes) -> Result<Response> {
+            Ok(Response::from(format!(
+                "{method} {} {}",
+                db.0,
+                String::from_utf8_lossy(&body)
+            )))
+        }
+        let app = App::new(handler(echo)).state(Db("main"));
+        let request = Request::post("/").map_body(|_| "Hello".into());
+        let (status, body) = run(&app, request);
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "POST main Hello"));
+
+        // The first failing extraction is returned, the handler is not called.
+        let request = Request::post("/").map_body(|_| vec![0xFF].into());
+        async fn text(State(_db): State<Db>, _text: bytestr::ByteStr) -> &'static str {
+            unreachable!()
+        }
+        let (status, _) = run(&App::new(handler(text)), request);
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,782 @@
+use std::{
+    io,
+    mem::take,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures_lite::{ready, AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt};
+use http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri, Version};
+
+use crate::{Body, Request, Response};
+
+/// The default maximum size of a message head, including the start line and headers.
+pub const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// The default maximum number of headers in a message head.
+pub const MAX_HEADERS: usize = 100;
+
+// The maximum size of a chunk-size line or a trailer line.
+const MAX_LINE_SIZE: usize = 8 * 1024;
+
+impl_error!(HeadTooLarge, "Message head is too large");
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn head_too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, HeadTooLarge::new())
+}
+
+/// Read a message head terminated by an empty line.
+/// Return `None` if the stream reached EOF before any byte was read.
+pub(crate) async fn read_head<R>(reader: &mut R, limit: usize) -> io::Result<Option<Vec<u8>>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut head = Vec::new();
+    loop {
+        let start = head.len();
+        let remain = limit.saturating_sub(start) as u64;
+        if remain == 0 {
+            return Err(head_too_large());
+        }
+        let read = (&mut *reader)
+            .take(remain)
+            .read_until(b'\n', &mut head)
+            .await?;
+        if read == 0 {
+            return if head.is_empty() {
+                Ok(None)
+            } else {
+                Err(io::ErrorKind::UnexpectedEof.into())
+            };
+        }
+        let line = &head[start..];
+        if !line.ends_with(b"\n") {
+            continue;
+        }
+        if line == b"\r\n" || line == b"\n" {
+            // Ignore empty lines preceding the start line (RFC 9112 Section 2.2).
+            if start == 0 {
+                head.clear();
+                continue;
+            }
+            return Ok(Some(head));
+        }
+    }
+}
+
+fn parse_headers(parsed: &[httparse::Header<'_>], headers: &mut HeaderMap) -> io::Result<()> {
+    for header in parsed {
+        let name = HeaderName::from_bytes(header.name.as_bytes())
+            .map_err(|_| invalid_data("Invalid header name"))?;
+        let value = HeaderValue::from_bytes(header.value)
+            .map_err(|_| invalid_data("Invalid header value"))?;
+        headers.append(name, value);
+    }
+    Ok(())
+}
+
+fn check_status(status: Result<httparse::Status<usize>, httparse::Error>) -> io::Result<()> {
+    match status {
+        Ok(httparse::Status::Complete(_)) => Ok(()),
+        Ok(httparse::Status::Partial) => Err(invalid_data("Incomplete message head")),
+        Err(httparse::Error::TooManyHeaders) => Err(head_too_large()),
+        Err(_) => Err(invalid_data("Malformed message head")),
+    }
+}
+
+const fn parse_version(version: Option<u8>) -> Version {
+    match version {
+        Some(0) => Version::HTTP_10,
+        _ => Version::HTTP_11,
+    }
+}
+
+/// Parse a request head into a request without body.
+pub(crate) fn parse_request_head(head: &[u8], max_headers: usize) -> io::Result<Request> {
+    let mut headers = vec![httparse::EMPTY_HEADER; max_headers];
+    let mut parsed = httparse::Request::new(&mut headers);
+    check_status(parsed.parse(head))?;
+
+    let method = Method::from_bytes(parsed.method.unwrap_or_default().as_bytes())
+        .map_err(|_| invalid_data("Invalid method"))?;
+    let uri: Uri = parsed
+        .path
+        .unwrap_or_default()
+        .parse()
+        .map_err(|_| invalid_data("Invalid request target"))?;
+    let mut request = Request::new(method, uri);
+    request.set_version(parse_version(parsed.version));
+    parse_headers(parsed.headers, request.headers_mut())?;
+    Ok(request)
+}
+
+/// Parse a response head into a response without body.
+pub(crate) fn parse_response_head(head: &[u8], max_headers: usize) -> io::Result<Response> {
+    let mut headers = vec![httparse::EMPTY_HEADER; max_headers];
+    let mut parsed = httparse::Response::new(&mut headers);
+    check_status(parsed.parse(head))?;
+
+    let status = StatusCode::from_u16(parsed.code.unwrap_or_default())
+        .map_err(|_| invalid_data("Invalid status code"))?;
+    let mut response = Response::new(status, Body::empty());
+    response.set_version(parse_version(parsed.version));
+    parse_headers(parsed.headers, response.headers_mut())?;
+    Ok(response)
+}
+
+#[cfg(any(feature = "server", feature = "client"))]
+fn connection_has(headers: &HeaderMap, token: &str) -> bool {
+    headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|item| item.trim().eq_ignore_ascii_case(token))
+}
+
+/// Return `true` if the sender of a message with `version` and `headers` allows the connection to be reused.
+#[cfg(any(feature = "server", feature = "client"))]
+pub(crate) fn wants_keep_alive(version: Version, headers: &HeaderMap) -> bool {
+    match version {
+        Version::HTTP_11 => !connection_has(headers, "close"),
+        Version::HTTP_10 => connection_has(headers, "keep-alive"),
+        _ => false,
+    }
+}
+
+/// How the length of a message body is determined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Framing {
+    Length(u64),
+    Chunked,
+    // The body is delimited by closing the connection.
+    Close,
+}
+
+// Return `true` if the body is chunked, fail if codings other than `chunked` are applied,
+// since they would be left undecoded.
+fn is_chunked(headers: &HeaderMap) -> io::Result<bool> {
+    if !headers.contains_key(header::TRANSFER_ENCODING) {
+        return Ok(false);
+    }
+    let mut codings = Vec::new();
+    for value in headers.get_all(header::TRANSFER_ENCODING) {
+        let value = value
+            .to_str()
+            .map_err(|_| invalid_data("Invalid transfer coding"))?;
+        codings.extend(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|coding| !coding.is_empty()),
+        );
+    }
+    match codings[..] {
+        [coding] if coding.eq_ignore_ascii_case("chunked") => Ok(true),
+        _ => Err(invalid_data("Unsupported transfer coding")),
+    }
+}
+
+/// Determine the framing of a request body (RFC 9112 Section 6.3).
+///
+/// A request with both `Transfer-Encoding` and `Content-Length` is rejected,
+/// since intermediaries may disagree on its framing.
+pub(crate) fn request_framing(headers: &HeaderMap) -> io::Result<Framing> {
+    if is_chunked(headers)? {
+        if headers.contains_key(header::CONTENT_LENGTH) {
+            return Err(invalid_data(
+                "Both Transfer-Encoding and Content-Length are present",
+            ));
+        }
+        return Ok(Framing::Chunked);
+    }
+    Ok(Framing::Length(content_length(headers)?.unwrap_or(0)))
+}
+
+/// Determine the framing of a response body answering a request with `method` (RFC 9112 Section 6.3).
+pub(crate) fn response_framing(
+    status: StatusCode,
+    method: &Method,
+    headers: &HeaderMap,
+) -> io::Result<Framing> {
+    if method == Method::HEAD
+        || status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+        || (method == Method::CONNECT && status.is_success())
+    {
+        return Ok(Framing::Length(0));
+    }
+    if is_chunked(headers)? {
+        return Ok(Framing::Chunked);
+    }
+    Ok(content_length(headers)?.map_or(Framing::Close, Framing::Length))
+}
+
+fn content_length(headers: &HeaderMap) -> io::Result<Option<u64>> {
+    let mut length = None;
+    for value in headers.get_all(header::CONTENT_LENGTH) {
+        for item in value
+            .to_str()
+            .map_err(|_| invalid_data("Invalid content length"))?
+            .split(',')
+        {
+            let item = item.trim();
+            let item: u64 = item
+                .bytes()
+                .all(|byte| byte.is_ascii_digit())
+                .then(|| item.parse().ok())
+                .flatten()
+                .ok_or_else(|| invalid_data("Invalid content length"))?;
+            if length.is_some_and(|length| length != item) {
+                return Err(invalid_data("Conflicting content length"));
+            }
+            length = Some(item);
+        }
+    }
+    Ok(length)
+}
+
+// Read a line into `line` (including the line break), without blocking on data after the line.
+fn poll_read_line<R>(
+    mut reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    line: &mut Vec<u8>,
+) -> Poll<io::Result<()>>
+where
+    R: AsyncBufRead,
+{
+    loop {
+        let buf = ready!(reader.as_mut().poll_fill_buf(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        let (used, done) = match buf.iter().position(|&byte| byte == b'\n') {
+            Some(index) => (index + 1, true),
+            None => (buf.len(), false),
+        };
+        line.extend_from_slice(&buf[..used]);
+        reader.as_mut().consume(used);
+        if line.len() > MAX_LINE_SIZE {
+            return Poll::Ready(Err(invalid_data("Line is too long")));
+        }
+        if done {
+            return Poll::Ready(Ok(()));
+        }
+    }
+}
+
+fn trim_line(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+// Parse the size of a chunk, which is made of hexadecimal digits only.
+// Whitespace is only allowed before chunk extensions, which are ignored.
+fn parse_chunk_size(line: &[u8]) -> Option<u64> {
+    let size = match line.iter().position(|&byte| byte == b';') {
+        Some(end) => {
+            let size = &line[..end];
+            let len = size.len()
+                - size
+                    .iter()
+                    .rev()
+                    .take_while(|&&byte| byte == b' ' || byte == b'\t')
+                    .count();
+            &size[..len]
+        }
+        None => line,
+    };
+    if size.is_empty() || !size.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    u64::from_str_radix(std::str::from_utf8(size).ok()?, 16).ok()
+}
+
+/// Return `true` if the field must not be sent in trailers, such as framing, routing,
+/// authentication and content metadata fields, which are dropped when encoding and ignored when decoding.
+pub(crate) fn is_forbidden_trailer(name: &HeaderName) -> bool {
+    const FORBIDDEN: [HeaderName; 22] = [
+        header::AUTHORIZATION,
+        header::CACHE_CONTROL,
+        header::CONNECTION,
+        header::CONTENT_ENCODING,
+        header::CONTENT_LENGTH,
+        header::CONTENT_RANGE,
+        header::CONTENT_TYPE,
+        header::COOKIE,
+        header::EXPECT,
+        header::HOST,
+        header::IF_MATCH,
+        header::IF_NONE_MATCH,
+        header::MAX_FORWARDS,
+        header::PRAGMA,
+        header::PROXY_AUTHENTICATE,
+        header::PROXY_AUTHORIZATION,
+        header::RANGE,
+        header::SET_COOKIE,
+        header::TE,
+        header::TRAILER,
+        header::TRANSFER_ENCODING,
+        header::WWW_AUTHENTICATE,
+    ];
+    FORBIDDEN.contains(name) || name == "keep-alive" || name == "proxy-connection"
+}
+
+fn parse_trailer(line: &[u8]) -> io::Result<(HeaderName, HeaderValue)> {
+    let colon = line
+        .iter()
+        .position(|&byte| byte == b':')
+        .ok_or_else(|| invalid_data("Malformed trailer"))?;
+    let name =
+        HeaderName::from_bytes(&line[..colon]).map_err(|_| invalid_data("Invalid trailer name"))?;
+    let value = HeaderValue::from_bytes(line[colon + 1..].trim_ascii())
+        .map_err(|_| invalid_data("Invalid trailer value"))?;
+    Ok((name, value))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkedState {
+    Size,
+    Data(u64),
+    DataEnd,
+    // The number of trailer lines read.
+    Trailers(usize),
+    Done,
+}
+
+/// A reader decoding a message body according to its framing.
+/// The inner reader can be reused for the next message after the body is completely read.
+pub(crate) struct BodyDecoder<R> {
+    reader: R,
+    state: DecoderState,
+    line: Vec<u8>,
+    trailers: HeaderMap,
+    // Filled with the trailers once the chunked body is completely read.
+    trailers_slot: Arc<Mutex<Option<HeaderMap>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DecoderState {
+    Length(u64),
+    Chunked(ChunkedState),
+    Close { eof: bool },
+}
+
+impl<R> BodyDecoder<R> {
+    pub(crate) fn new(reader: R, framing: Framing) -> Self {
+        Self {
+            reader,
+            state: match framing {
+                Framing::Length(length) => DecoderState::Length(length),
+                Framing::Chunked => DecoderState::Chunked(ChunkedState::Size),
+                Framing::Close => DecoderState::Close { eof: false },
+            },
+            line: Vec::new(),
+            trailers: HeaderMap::new(),
+            trailers_slot: Arc::default(),
+        }
+    }
+
+    /// Wrap the decoder into a body by `wrap`, carrying the length and the trailers of the body.
+    pub(crate) fn into_body<B>(self, wrap: impl FnOnce(Self) -> B) -> Body
+    where
+        B: AsyncBufRead + Send + Sync + 'static,
+    {
+        let length = self
+            .remaining()
+            .and_then(|length| usize::try_from(length).ok());
+        let chunked = matches!(self.state, DecoderState::Chunked(_));
+        let slot = self.trailers_slot.clone();
+        let body = Body::from_reader(wrap(self), length);
+        if chunked {
+            // The slot is filled once the body is completely read.
+            body.with_trailers_from(async move { io::Result::Ok(slot.lock().unwrap().take()) })
+        } else {
+            body
+        }
+    }
+
+    /// Return true if the body has been completely read.
+    pub(crate) fn is_done(&self) -> bool {
+        matches!(
+            self.state,
+            DecoderState::Length(0)
+                | DecoderState::Chunked(ChunkedState::Done)
+                | DecoderState::Close { eof: true }
+        )
+    }
+
+    /// Return the exact remaining length of the body, if it is known.
+    pub(crate) fn remaining(&self) -> Option<u64> {
+        match self.state {
+            DecoderState::Length(length) => Some(length),
+            DecoderState::Chunked(ChunkedState::Done) | DecoderState::Close { eof: true } => {
+                Some(0)
+            }
+            DecoderState::Chunked(_) | DecoderState::Close { .. } => None,
+        }
+    }
+
+    #[cfg(any(feature = "server", feature = "client"))]
+    pub(crate) fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> BodyDecoder<R> {
+    /// Read and discard at most `limit` bytes of the remaining body, return true if the body is completely read.
+    #[cfg(feature = "server")]
+    pub(crate) async fn drain(&mut self, limit: u64) -> io::Result<bool> {
+        let mut drained = 0;
+        while drained <= limit {
+            let len = self.fill_buf().await?.len();
+            if len == 0 {
+                return Ok(true);
+            }
+            self.consume_unpin(len);
+            drained += len as u64;
+        }
+        Ok(self.is_done())
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncBufRead for BodyDecoder<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        loop {
+            match this.state {
+                DecoderState::Length(0)
+                | DecoderState::Chunked(ChunkedState::Done)
+                | DecoderState::Close { eof: true } => {
+                    return Poll::Ready(Ok(&[]));
+                }
+                DecoderState::Close { eof: false } => {
+                    let buf = ready!(Pin::new(&mut this.reader).poll_fill_buf(cx))?;
+                    if buf.is_empty() {
+                        this.state = DecoderState::Close { eof: true };
+                    }
+                    return Poll::Ready(Ok(buf));
+                }
+                DecoderState::Length(_) | DecoderState::Chunked(ChunkedState::Data(_)) => break,
+                DecoderState::Chunked(ChunkedState::Size) => {
+                    ready!(poll_read_line(
+                        Pin::new(&mut this.reader),
+                        cx,
+                        &mut this.line
+                    ))?;
+                    let size = parse_chunk_size(trim_line(&this.line))
+                        .ok_or_else(|| invalid_data("Invalid chunk size"))?;
+                    this.line.clear();
+                    this.state = DecoderState::Chunked(if size == 0 {
+                        ChunkedState::Trailers(0)
+                    } else {
+                        ChunkedState::Data(size)
+                    });
+                }
+                DecoderState::Chunked(ChunkedState::DataEnd) => {
+                    ready!(poll_read_line(
+                        Pin::new(&mut this.reader),
+                        cx,
+                        &mut this.line
+                    ))?;
+                    if !trim_line(&this.line).is_empty() {
+                        return Poll::Ready(Err(invalid_data("Missing CRLF after chunk data")));
+                    }
+                    this.line.clear();
+                    this.state = DecoderState::Chunked(ChunkedState::Size);
+                }
+                DecoderState::Chunked(ChunkedState::Trailers(count)) => {
+                    ready!(poll_read_line(
+                        Pin::new(&mut this.reader),
+                        cx,
+                        &mut this.line
+                    ))?;
+                    let line = trim_line(&this.line);
+                    if line.is_empty() {
+                        this.state = DecoderState::Chunked(ChunkedState::Done);
+                        if !this.trailers.is_empty() {
+                            *this.trailers_slot.lock().unwrap() = Some(take(&mut this.trailers));
+                        }
+                    } else {
+                        if count >= MAX_HEADERS {
+                            return Poll::Ready(Err(invalid_data("Too many trailers")));
+                        }
+                        let (name, value) = parse_trailer(line)?;
+                        if !is_forbidden_trailer(&name) {
+                            this.trailers.append(name, value);
+                        }
+                        this.state = DecoderState::Chunked(ChunkedState::Trailers(count + 1));
+                    }
+                    this.line.clear();
+                }
+            }
+        }
+
+        let remaining = match this.state {
+            DecoderState::Length(remaining)
+            | DecoderState::Chunked(ChunkedState::Data(remaining)) => remaining,
+            _ => unreachable!(),
+        };
+        let buf = ready!(Pin::new(&mut this.reader).poll_fill_buf(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        let len = buf
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        Poll::Ready(Ok(&buf[..len]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().consume_unpin(amt);
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> BodyDecoder<R> {
+    fn consume_unpin(&mut self, amt: usize) {
+        if amt == 0 {
+            return;
+        }
+        Pin::new(&mut self.reader).consume(amt);
+        match &mut self.state {
+            DecoderState::Length(remaining) => *remaining -= amt as u64,
+            DecoderState::Chunked(ChunkedState::Data(remaining)) => {
+                *remaining -= amt as u64;
+                if *remaining == 0 {
+                    self.state = DecoderState::Chunked(ChunkedState::DataEnd);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for BodyDecoder<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let data = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.consume(len);
+        Poll::Ready(Ok(len))
+    }
+}
+
+/// An HTTP/1.1 message parser with limits on the size of message heads.
+///
+/// The parsed message carries a streaming body, which reads from the reader lazily
+/// and honours `Content-Length` or chunked transfer encoding.
+#[derive(Debug, Clone, Copy)]
+pub struct Parser {
+    max_head_size: usize,
+    max_headers: usize,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    /// Create a parser with default limits.
+    pub const fn new() -> Self {
+        Self {
+            max_head_size: MAX_HEAD_SIZE,
+            max_headers: MAX_HEADERS,
+        }
+    }
+
+    /// Set the maximum size of a message head, default to 64 KiB.
+    pub const fn max_head_size(mut self, size: usize) -> Self {
+        self.max_head_size = size;
+        self
+    }
+
+    /// Set the maximum number of headers in a message head, default to 100.
+    pub const fn max_headers(mut self, count: usize) -> Self {
+        self.max_headers = count;
+        self
+    }
+
+    /// Read the head of a request, return `None` if the reader reached EOF before any byte was read.
+    /// The body is not read and its framing is returned with the request.
+    pub(crate) async fn read_request_head<R>(
+        &self,
+        reader: &mut R,
+    ) -> io::Result<Option<(Request, Framing)>>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let Some(head) = read_head(reader, self.max_head_size).await? else {
+            return Ok(None);
+        };
+        let request = parse_request_head(&head, self.max_headers)?;
+        let framing = request_framing(request.headers())?;
+        Ok(Some((request, framing)))
+    }
+
+    /// Read the head of a response answering a request with `method`.
+    /// The body is not read and its framing is returned with the response.
+    pub(crate) async fn read_response_head<R>(
+        &self,
+        reader: &mut R,
+        method: &Method,
+    ) -> io::Result<(Response, Framing)>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let head = read_head(reader, self.max_head_size)
+            .await?
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        let mut response = parse_response_head(&head, self.max_headers)?;
+        let framing = response_framing(response.status(), method, response.headers())?;
+        // `Transfer-Encoding` overrides `Content-Length`, which must not be trusted.
+        if framing == Framing::Chunked {
+            response.headers_mut().remove(header::CONTENT_LENGTH);
+        }
+        Ok((response, framing))
+    }
+
+    /// Read a request from `reader`, return `None` if the reader reached EOF before any byte was read.
+    ///
+    /// Oversized heads are rejected with an error of kind `InvalidData`.
+    pub async fn read_request<R>(&self, mut reader: R) -> io::Result<Option<Request>>
+    where
+        R: AsyncBufRead + Unpin + Send + Sync + 'static,
+    {
+        let Some((mut request, framing)) = self.read_request_head(&mut reader).await? else {
+            return Ok(None);
+        };
+        request.replace_body(decode_body(reader, framing));
+        Ok(Some(request))
+    }
+
+    /// Read a response answering a request with `method` from `reader`.
+    ///
+    /// Oversized heads are rejected with an error of kind `InvalidData`.
+    pub async fn read_response<R>(&self, mut reader: R, method: &Method) -> io::Result<Response>
+    where
+        R: AsyncBufRead + Unpin + Send + Sync + 'static,
+    {
+        let (mut response, framing) = self.read_response_head(&mut reader, method).await?;
+        response.replace_body(decode_body(reader, framing));
+        Ok(response)
+    }
+}
+
+fn decode_body<R>(reader: R, framing: Framing) -> Body
+where
+    R: AsyncBufRead + Unpin + Send + Sync + 'static,
+{
+    let decoder = BodyDecoder::new(reader, framing);
+    if decoder.is_done() {
+        Body::empty()
+    } else {
+        decoder.into_body(|decoder| decoder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use futures_lite::{future::block_on, io::Cursor};
+    use http::{header, Method};
+
+    use super::Parser;
+    use crate::{Request, Response};
+
+    fn read_request(head: &str, body: &str) -> io::Result<Option<Request>> {
+        let message = format!("{}\r\n\r\n{body}", head.replace('\n', "\r\n"));
+        block_on(Parser::new().read_request(Cursor::new(message.into_bytes())))
+    }
+
+    fn read_chunked(body: &str) -> io::Result<String> {
+        let mut request =
+            read_request("POST / HTTP/1.1\nTransfer-Encoding: chunked", body)?.unwrap();
+        block_on(request.into_string())
+            .map(|body| body.to_string())
+            .map_err(|error| io::Error::other(error.to_string()))
+    }
+
+    fn read_response(head: &str) -> io::Result<Response> {
+        let message = format!("{}\r\n\r\n", head.replace('\n', "\r\n"));
+        block_on(Parser::new().read_response(Cursor::new(message.into_bytes()), &Method::GET))
+    }
+
+    #[test]
+    fn chunked() {
+        assert_eq!(read_chunked("5\r\nhello\r\n0\r\n\r\n").unwrap(), "hello");
+        assert_eq!(
+            read_chunked("A\r\nhelloworld\r\n0\r\n\r\n").unwrap(),
+            "helloworld"
+        );
+        assert_eq!(
+            read_chunked("5 ;ext=1\r\nhello\r\n0\r\n\r\n").unwrap(),
+            "hello"
+        );
+    }
+
+    #[test]
+    fn invalid_chunk_size() {
+        for size in ["+5", " 5", "5 ", "0x5", "", "-5", "11111111111111111"] {
+            let body = format!("{size}\r\nhello\r\n0\r\n\r\n");
+            assert!(read_chunked(&body).is_err(), "{size:?}");
+        }
+    }
+
+    #[test]
+    fn transfer_encoding_with_content_length() {
+        let error = read_request(
+            "POST / HTTP/1.1\nTransfer-Encoding: chunked\nContent-Length: 5",
+            "5\r\nhello\r\n0\r\n\r\n",
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn unsupported_transfer_coding() {
+        for coding in [
+            "gzip, chunked",
+            "chunked, chunked",
+            "gzip",
+            "chunked\nTransfer-Encoding: chunked",
+        ] {
+            let head = format!("POST / HTTP/1.1\nTransfer-Encoding: {coding}");
+            let error = read_request(&head, "0\r\n\r\n").unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{coding:?}");
+        }
+    }
+
+    #[test]
+    fn content_length() {
+        let mut request = read_request("POST / HTTP/1.1\nContent-Length: 5, 5", "hello")
+            .unwrap()
+            .unwrap();
+        assert_eq!(block_on(request.into_string()).unwrap(), "hello");
+        for length in ["+5", "5, 6", "-1", "0x5"] {
+            let head = format!("POST / HTTP/1.1\nContent-Length: {length}");
+            assert!(read_request(&head, "hello").is_err(), "{length:?}");
+        }
+    }
+
+    #[test]
+    fn response_transfer_encoding_overrides_content_length() {
+        let response =
+            read_response("HTTP/1.1 200 OK\nTransfer-Encoding: chunked\nContent-Length: 5")
+                .unwrap();
+        assert!(response.get_header(header::CONTENT_LENGTH).is_none());
+        assert!(read_response("HTTP/1.1 200 OK\nTransfer-Encoding: gzip").is_err());
+    }
+}
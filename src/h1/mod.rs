@@ -0,0 +1,38 @@
+//! HTTP/1.1 wire codec.
+//!
+//! Messages can be serialized by `Request::write_to` and `Response::write_to`,
+//! and parsed by [`Parser`] into messages carrying a streaming body.
+//!
+//! # Example
+//! ```rust
+//! use http_kit::{h1::Parser, Method, Request};
+//! use futures_lite::io::{BufReader, Cursor};
+//!
+//! # async_std::task::block_on(async {
+//! let mut request = Request::post("http://example.com/echo");
+//! request.replace_body("Hello,world");
+//! let mut wire = Vec::new();
+//! request.write_to(&mut wire).await?;
+//!
+//! let mut parsed = Parser::new()
+//!     .read_request(BufReader::new(Cursor::new(wire)))
+//!     .await?
+//!     .unwrap();
+//! assert_eq!(parsed.method(), Method::POST);
+//! assert_eq!(parsed.into_string().await.unwrap(), "Hello,world");
+//! # std::io::Result::Ok(())
+//! # }).unwrap();
+//! ```
+
+mod decode;
+mod encode;
+
+pub use decode::{Parser, MAX_HEADERS, MAX_HEAD_SIZE};
+
+#[cfg(feature = "server")]
+pub(crate) use decode::HeadTooLarge;
+#[cfg(any(feature = "server", feature = "client"))]
+pub(crate) use decode::{wants_keep_alive, BodyDecoder, Framing};
+#[cfg(feature = "server")]
+pub(crate) use encode::CONTINUE;
+pub(crate) use encode::{write_request, write_response};
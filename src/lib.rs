@@ -5,15 +5,17 @@
 //! ```rust
 //! use http_kit::{Request,Response};
 //!
-//! async fn echo(request:Request) -> http_kit::Result<Response>{
+//! async fn echo(mut request:Request) -> http_kit::Result<Response>{
 //!     let body = request.take_body()?;
 //!     Ok(Response::new(200,body))
 //! }
 //!
+//! # async_std::task::block_on(async{
 //! let mut request = Request::get("/echo");
 //! request.replace_body("Hello,world");
 //! echo(request).await?;
-//!
+//! # http_kit::Result::Ok(())
+//! # }).unwrap();
 //! ```
 #[macro_use]
 mod macros;
@@ -22,25 +24,57 @@ mod error;
 pub use error::{Error, Result, ResultExt};
 
 mod body;
-#[cfg(feature = "fs")]
+mod date;
 pub(crate) mod mime_guess;
 pub use body::Body;
 pub use body::Error as BodyError;
 
 pub mod middleware;
-pub use middleware::Middleware;
+pub use middleware::{middleware_fn, Middleware};
 
 mod endpoint;
-pub use endpoint::Endpoint;
+pub use endpoint::{endpoint_fn, BoxFuture, Endpoint, EndpointFn, FnEndpoint};
+
+pub mod router;
+pub use router::Router;
+
+#[cfg(any(
+    feature = "gzip",
+    feature = "deflate",
+    feature = "brotli",
+    feature = "zstd"
+))]
+pub mod compression;
+pub mod content_type;
+#[cfg(feature = "cookie")]
+pub mod cookie;
+pub mod extract;
+pub mod multipart;
+pub mod negotiate;
+#[cfg(feature = "fs")]
+pub mod serve_dir;
+#[cfg(feature = "session")]
+pub mod session;
+#[cfg(feature = "sse")]
+pub mod sse;
+pub mod typed_header;
+pub use extract::{handler, FromRequest};
 
 mod hook;
 pub use hook::Hook;
 mod app;
 pub use app::App;
 
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "h1")]
+pub mod h1;
+#[cfg(feature = "server")]
+pub mod server;
+
 mod request;
 pub use request::Request;
 mod response;
-pub use response::Response;
+pub use response::{IntoResponse, Response};
 
 pub use http::{header, method, uri, version, Extensions, Method, StatusCode, Uri, Version};
@@ -21,23 +21,3 @@ macro_rules! impl_error {
         impl std::error::Error for $ty {}
     };
 }
-
-#[cfg(any(feature = "json", feature = "form"))]
-macro_rules! assert_content_type {
-    ($mime:expr,$headermap:expr) => {
-        impl_error!(
-            ContentTypeMismatched,
-            concat!("Content-type is mismatched, expected `", $mime, "`")
-        );
-        let content_type = $headermap
-            .get(crate::header::CONTENT_TYPE)
-            .map(|s| s.as_bytes())
-            .unwrap_or(b"");
-        if content_type != $mime.as_bytes() {
-            return Err(crate::Error::new(
-                ContentTypeMismatched::new(),
-                crate::StatusCode::UNSUPPORTED_MEDIA_TYPE,
-            ));
-        }
-    };
-}
@@ -6,18 +6,18 @@
 //! use async_std::future::timeout;
 //! use std::time::Duration;
 //! use async_trait::async_trait;
-//! use http_kit::{Request,Response,middleware::{Middleware,Next}};
+//! use http_kit::{Request,Response,ResultExt,StatusCode,middleware::{Middleware,Next}};
 //! struct TimeOut(Duration);
 //!
 //! #[async_trait]
 //! impl Middleware for TimeOut{
 //!     async fn call_middleware(&self, request: &mut Request, next: Next<'_>) -> http_kit::Result<Response>{
-//!         timeout(self.duration,next.run(request)).await?
+//!         timeout(self.0,next.run(request)).await.status(StatusCode::GATEWAY_TIMEOUT)?
 //!     }
 //! }
 //! ```
 
-use crate::{Endpoint, Request, Response, Result};
+use crate::{endpoint::BoxFuture, BodyError, Endpoint, Request, Response, Result};
 use async_trait::async_trait;
 use std::{any::type_name, fmt::Debug, future::Future, ops::Deref, pin::Pin, sync::Arc};
 
@@ -102,8 +102,193 @@ impl Middleware for () {
     }
 }
 
+/// A middleware limiting the size of request bodies.
+///
+/// Requests declaring a larger `Content-Length` are rejected with `413 Payload Too Large` before the body is read,
+/// other bodies fail with [`BodyError::TooLarge`] once the limit is crossed while being read.
+/// # Example
+/// ```rust
+/// use bytestr::ByteStr;
+/// use http_kit::{extract::handler, middleware::BodyLimit, App, Request, StatusCode};
+///
+/// # async_std::task::block_on(async {
+/// async fn echo(body: ByteStr) -> ByteStr {
+///     body
+/// }
+///
+/// let app = App::new(handler(echo)).middleware(BodyLimit::new(5));
+/// let mut request = Request::post("/");
+/// request.replace_body("Hello,world");
+/// let error = app.run(request).await.unwrap_err();
+/// assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+/// # })
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimit {
+    max_bytes: usize,
+}
+
+impl BodyLimit {
+    /// Create a middleware rejecting bodies larger than `max_bytes`.
+    pub const fn new(max_bytes: usize) -> Self {
+        Self { max_bytes }
+    }
+}
+
+#[async_trait]
+impl Middleware for BodyLimit {
+    async fn call_middleware(&self, request: &mut Request, next: Next<'_>) -> Result<Response> {
+        let content_length = request
+            .get_header(http::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.trim().parse::<u64>().ok());
+        if content_length.is_some_and(|length| length > self.max_bytes as u64) {
+            return Err(BodyError::TooLarge.into_http_error());
+        }
+        let body = request.take_body()?;
+        request.replace_body(body.limit(self.max_bytes));
+        next.run(request).await
+    }
+}
+
+/// A closure or an async function usable by [`middleware_fn`], implemented automatically.
+pub trait MiddlewareFn<'a>: Send + Sync {
+    /// The future returned by the closure.
+    type Future: Future<Output = Result<Response>> + Send + 'a;
+
+    /// Call the closure.
+    fn call(&self, request: &'a mut Request, next: Next<'a>) -> Self::Future;
+}
+
+impl<'a, F, Fut> MiddlewareFn<'a> for F
+where
+    F: Fn(&'a mut Request, Next<'a>) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Response>> + Send + 'a,
+{
+    type Future = Fut;
+
+    fn call(&self, request: &'a mut Request, next: Next<'a>) -> Fut {
+        self(request, next)
+    }
+}
+
+/// A middleware created from a closure by [`middleware_fn`].
+pub struct FnMiddleware<F> {
+    f: F,
+    name: &'static str,
+}
+
+impl<F> FnMiddleware<F> {
+    /// Set the name reported by `Middleware::name`, which defaults to the type name of an async function,
+    /// or the path of the function defining a closure.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+}
+
+impl<F> Debug for FnMiddleware<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FnMiddleware").field(&self.name).finish()
+    }
+}
+
+/// Create a middleware from an async closure or an async function taking `&mut Request` and [`Next`].
+///
+/// The future of an async closure can not borrow the values owned by the closure,
+/// share them with `App::state` or clone them before the closure instead.
+/// # Example
+/// ```rust
+/// use http_kit::{header, middleware_fn, App};
+///
+/// let app = App::new(()).middleware(
+///     middleware_fn(async |request, next| {
+///         let mut response = next.run(request).await?;
+///         response.insert_header(header::SERVER, header::HeaderValue::from_static("http-kit"));
+///         Ok(response)
+///     })
+///     .with_name("server"),
+/// );
+/// assert_eq!(format!("{app:?}"), r#"App { endpoint: "()", middlewares: [server] }"#);
+/// ```
+pub fn middleware_fn<F>(f: F) -> FnMiddleware<F>
+where
+    F: for<'a> AsyncFn(&'a mut Request, Next<'a>) -> Result<Response> + for<'a> MiddlewareFn<'a>,
+{
+    FnMiddleware {
+        f,
+        name: crate::endpoint::fn_name::<F>(),
+    }
+}
+
+impl<F> Middleware for FnMiddleware<F>
+where
+    F: for<'a> MiddlewareFn<'a>,
+{
+    fn call_middleware<'life0, 'life1, 'life2, 'async_trait>(
+        &'life0 self,
+        request: &'life1 mut Request,
+        next: Next<'life2>,
+    ) -> BoxFuture<'async_trait, Result<Response>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        'life2: 'async_trait,
+        Self: 'async_trait,
+    {
+        Box::pin(self.f.call(request, next))
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
 impl Debug for dyn Middleware {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.name())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytestr::ByteStr;
+    use futures_lite::{future::block_on, stream};
+    use http::{header, StatusCode};
+
+    use super::BodyLimit;
+    use crate::{extract::handler, App, Body, Request};
+
+    async fn echo(body: ByteStr) -> ByteStr {
+        body
+    }
+
+    #[test]
+    fn body_limit() {
+        let app = App::new(handler(echo)).middleware(BodyLimit::new(5));
+        let run = |request| block_on(app.run(request));
+
+        let mut request = Request::post("/");
+        request.replace_body("Hello");
+        let mut response = run(request).unwrap();
+        assert_eq!(block_on(response.into_string()).unwrap(), "Hello");
+
+        // The declared length is rejected before the body is read.
+        let mut request = Request::post("/").header(header::CONTENT_LENGTH, "1024");
+        request.replace_body(Body::frozen());
+        let error = run(request).unwrap_err();
+        assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let mut request = Request::post("/");
+        request.replace_body("Hello,world");
+        let error = run(request).unwrap_err();
+        assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let mut request = Request::post("/");
+        request.replace_body(Body::from_stream(stream::iter([
+            Ok::<_, std::io::Error>("Hel"),
+            Ok("lo,world"),
+        ])));
+        let error = run(request).unwrap_err();
+        assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
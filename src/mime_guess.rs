@@ -1,20 +1,301 @@
-pub const fn guess(extension: &[u8]) -> Option<&'static str> {
-    match extension {
-        b"aac" => Some("audio/aac"),
-        b"avi" => Some("video/x-msvideo"),
-        b"css" => Some("text/css"),
-        b"gif" => Some("image/gif"),
-        b"jpeg" | b"jpg" => Some("image/jpeg"),
-        b"js" => Some("text/javascript"),
-        b"json" => Some("application/json"),
-        b"mp3" => Some("audio/mpeg"),
-        b"mp4" => Some("video/mp4"),
-        b"png" => Some("image/png"),
-        b"svg" => Some("image/svg+xml"),
-        b"ttf" => Some("font/ttf"),
-        b"txt" => Some("text/plain"),
-        b"wav" => Some("audio/wav"),
-        b"webp" => Some("image/webp"),
-        _ => None,
+/// The number of bytes inspected by [`sniff`].
+pub const SNIFF_LEN: usize = 512;
+
+/// Guess the MIME of a file by its extension, the lookup is case-insensitive.
+/// Text types carry a `charset=utf-8` parameter.
+///
+/// Ambiguous extensions such as `ts`, either TypeScript or MPEG transport streams, are not guessed.
+#[cfg_attr(not(feature = "fs"), allow(dead_code))]
+pub fn guess(extension: &[u8]) -> Option<&'static str> {
+    let mut buf = [0u8; 16];
+    let lowercase = buf.get_mut(..extension.len())?;
+    lowercase.copy_from_slice(extension);
+    lowercase.make_ascii_lowercase();
+
+    let mime = match &*lowercase {
+        // Text
+        b"css" => "text/css; charset=utf-8",
+        b"csv" => "text/csv; charset=utf-8",
+        b"htm" | b"html" => "text/html; charset=utf-8",
+        b"ics" => "text/calendar; charset=utf-8",
+        b"js" | b"mjs" | b"cjs" => "text/javascript; charset=utf-8",
+        b"markdown" | b"md" => "text/markdown; charset=utf-8",
+        b"txt" | b"text" | b"log" | b"conf" | b"ini" => "text/plain; charset=utf-8",
+        b"tsv" => "text/tab-separated-values; charset=utf-8",
+        b"vtt" => "text/vtt; charset=utf-8",
+        b"xml" | b"xsl" | b"xsd" => "text/xml; charset=utf-8",
+        b"yaml" | b"yml" => "application/yaml",
+        // Application
+        b"7z" => "application/x-7z-compressed",
+        b"bin" | b"exe" | b"dll" | b"so" => "application/octet-stream",
+        b"bz2" => "application/x-bzip2",
+        b"doc" => "application/msword",
+        b"docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        b"epub" => "application/epub+zip",
+        b"gz" | b"tgz" => "application/gzip",
+        b"jar" => "application/java-archive",
+        b"json" | b"map" => "application/json",
+        b"jsonld" => "application/ld+json",
+        b"odp" => "application/vnd.oasis.opendocument.presentation",
+        b"ods" => "application/vnd.oasis.opendocument.spreadsheet",
+        b"odt" => "application/vnd.oasis.opendocument.text",
+        b"ogx" => "application/ogg",
+        b"pdf" => "application/pdf",
+        b"ppt" => "application/vnd.ms-powerpoint",
+        b"pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        b"ps" | b"eps" => "application/postscript",
+        b"rar" => "application/vnd.rar",
+        b"rtf" => "application/rtf",
+        b"tar" => "application/x-tar",
+        b"toml" => "application/toml",
+        b"wasm" => "application/wasm",
+        b"webmanifest" => "application/manifest+json",
+        b"xhtml" => "application/xhtml+xml",
+        b"xls" => "application/vnd.ms-excel",
+        b"xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        b"xz" => "application/x-xz",
+        b"zip" => "application/zip",
+        b"zst" => "application/zstd",
+        // Image
+        b"apng" => "image/apng",
+        b"avif" => "image/avif",
+        b"bmp" => "image/bmp",
+        b"gif" => "image/gif",
+        b"heic" => "image/heic",
+        b"ico" => "image/vnd.microsoft.icon",
+        b"jpeg" | b"jpg" | b"jpe" | b"jfif" => "image/jpeg",
+        b"jxl" => "image/jxl",
+        b"png" => "image/png",
+        b"svg" | b"svgz" => "image/svg+xml",
+        b"tif" | b"tiff" => "image/tiff",
+        b"webp" => "image/webp",
+        // Audio
+        b"aac" => "audio/aac",
+        b"flac" => "audio/flac",
+        b"m4a" => "audio/mp4",
+        b"mid" | b"midi" => "audio/midi",
+        b"mp3" => "audio/mpeg",
+        b"oga" | b"ogg" => "audio/ogg",
+        b"opus" => "audio/opus",
+        b"wav" => "audio/wav",
+        b"weba" => "audio/webm",
+        // Video
+        b"3gp" => "video/3gpp",
+        b"avi" => "video/x-msvideo",
+        b"m4v" | b"mp4" => "video/mp4",
+        b"mkv" => "video/x-matroska",
+        b"mov" => "video/quicktime",
+        b"mpeg" | b"mpg" => "video/mpeg",
+        b"ogv" => "video/ogg",
+        b"webm" => "video/webm",
+        // Font
+        b"otf" => "font/otf",
+        b"ttf" => "font/ttf",
+        b"woff" => "font/woff",
+        b"woff2" => "font/woff2",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// Guess the MIME of data by its leading bytes.
+/// Return `None` if the data is empty or looks like unknown binary data.
+pub fn sniff(data: &[u8]) -> Option<&'static str> {
+    let data = &data[..data.len().min(SNIFF_LEN)];
+    if data.is_empty() {
+        return None;
+    }
+
+    // Text with byte order mark
+    if data.starts_with(b"\xef\xbb\xbf") {
+        return Some(sniff_text(&data[3..]).unwrap_or("text/plain; charset=utf-8"));
+    }
+    if data.starts_with(b"\xfe\xff") {
+        return Some("text/plain; charset=utf-16be");
+    }
+    if data.starts_with(b"\xff\xfe") {
+        return Some("text/plain; charset=utf-16le");
+    }
+
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"BM", "image/bmp"),
+        (b"\x00\x00\x01\x00", "image/vnd.microsoft.icon"),
+        (b"II*\x00", "image/tiff"),
+        (b"MM\x00*", "image/tiff"),
+        (b"\xff\x0a", "image/jxl"),
+        (b"%PDF-", "application/pdf"),
+        (b"%!PS-Adobe-", "application/postscript"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b\x08", "application/gzip"),
+        (b"BZh", "application/x-bzip2"),
+        (b"\xfd7zXZ\x00", "application/x-xz"),
+        (b"\x28\xb5\x2f\xfd", "application/zstd"),
+        (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+        (b"Rar!\x1a\x07", "application/vnd.rar"),
+        (b"\x00asm", "application/wasm"),
+        (b"{\\rtf", "application/rtf"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+        (b"OTTO", "font/otf"),
+        (b"\x00\x01\x00\x00\x00", "font/ttf"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS\x00", "application/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"MThd\x00\x00\x00\x06", "audio/midi"),
+        (b"\x1a\x45\xdf\xa3", "video/webm"),
+    ];
+    for (signature, mime) in SIGNATURES {
+        if data.starts_with(signature) {
+            return Some(mime);
+        }
+    }
+
+    // RIFF containers
+    if data.len() >= 12 && data.starts_with(b"RIFF") {
+        match &data[8..12] {
+            b"WEBP" => return Some("image/webp"),
+            b"WAVE" => return Some("audio/wav"),
+            b"AVI " => return Some("video/x-msvideo"),
+            _ => {}
+        }
+    }
+
+    // ISO base media files
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        return Some(match &data[8..12] {
+            b"avif" | b"avis" => "image/avif",
+            b"heic" | b"heix" | b"mif1" => "image/heic",
+            b"M4A " => "audio/mp4",
+            b"qt  " => "video/quicktime",
+            b"3gp4" | b"3gp5" | b"3gp6" => "video/3gpp",
+            _ => "video/mp4",
+        });
+    }
+
+    // MPEG audio frame
+    if data.len() >= 2 && data[0] == 0xff && data[1] & 0xe0 == 0xe0 {
+        return Some("audio/mpeg");
+    }
+
+    if let Some(mime) = sniff_text(data) {
+        return Some(mime);
+    }
+
+    // Data without binary bytes is treated as plain text.
+    let binary = data
+        .iter()
+        .any(|&byte| matches!(byte, 0x00..=0x08 | 0x0b | 0x0e..=0x1a | 0x1c..=0x1f));
+    if binary {
+        return None;
+    }
+    match std::str::from_utf8(data) {
+        // A multi-byte character may be cut off at the end.
+        Err(error) if error.error_len().is_some() => Some("text/plain"),
+        _ => Some("text/plain; charset=utf-8"),
+    }
+}
+
+// Recognize markup documents by their leading tag.
+fn sniff_text(data: &[u8]) -> Option<&'static str> {
+    const TAGS: &[(&[u8], &str)] = &[
+        (b"<!doctype html", "text/html; charset=utf-8"),
+        (b"<html", "text/html; charset=utf-8"),
+        (b"<head", "text/html; charset=utf-8"),
+        (b"<body", "text/html; charset=utf-8"),
+        (b"<script", "text/html; charset=utf-8"),
+        (b"<iframe", "text/html; charset=utf-8"),
+        (b"<title", "text/html; charset=utf-8"),
+        (b"<div", "text/html; charset=utf-8"),
+        (b"<p", "text/html; charset=utf-8"),
+        (b"<!--", "text/html; charset=utf-8"),
+        (b"<svg", "image/svg+xml"),
+        (b"<?xml", "text/xml; charset=utf-8"),
+    ];
+    let start = data.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    let data = &data[start..];
+    TAGS.iter().find_map(|(tag, mime)| {
+        let prefix = data.get(..tag.len())?;
+        // A tag name must be terminated by a space or `>`.
+        let terminated = tag.ends_with(b"--")
+            || tag.starts_with(b"<?")
+            || matches!(data.get(tag.len()), Some(b' ' | b'>'));
+        (prefix.eq_ignore_ascii_case(tag) && terminated).then_some(*mime)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{guess, sniff};
+
+    #[test]
+    fn extensions() {
+        assert_eq!(guess(b"png"), Some("image/png"));
+        assert_eq!(guess(b"PNG"), Some("image/png"));
+        assert_eq!(guess(b"Jpg"), Some("image/jpeg"));
+        assert_eq!(guess(b"webmanifest"), Some("application/manifest+json"));
+        assert_eq!(guess(b"ts"), None);
+        assert_eq!(guess(b"unknown"), None);
+        assert_eq!(guess(b""), None);
+        // Longer than any known extension.
+        assert_eq!(guess(b"averyveryverylongextension"), None);
+    }
+
+    #[test]
+    fn charset() {
+        assert_eq!(guess(b"HTML"), Some("text/html; charset=utf-8"));
+        assert_eq!(guess(b"txt"), Some("text/plain; charset=utf-8"));
+        assert_eq!(guess(b"js"), Some("text/javascript; charset=utf-8"));
+        assert_eq!(guess(b"json"), Some("application/json"));
+        assert_eq!(guess(b"svg"), Some("image/svg+xml"));
+    }
+
+    #[test]
+    fn magic_bytes() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("image/png"));
+        assert_eq!(sniff(b"\xff\xd8\xff\xe0"), Some("image/jpeg"));
+        assert_eq!(sniff(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WAVEfmt "), Some("audio/wav"));
+        assert_eq!(sniff(b"\0\0\0\x20ftypisom"), Some("video/mp4"));
+        assert_eq!(sniff(b"\0\0\0\x1cftypavif"), Some("image/avif"));
+        assert_eq!(sniff(b"\xff\xfb\x90\x00"), Some("audio/mpeg"));
+        assert_eq!(sniff(b""), None);
+        assert_eq!(sniff(b"\0\x01\x02\x03"), None);
+    }
+
+    #[test]
+    fn text() {
+        let html = Some("text/html; charset=utf-8");
+        assert_eq!(sniff(b"  <!DOCTYPE html><html>"), html);
+        assert_eq!(sniff(b"<HTML>"), html);
+        assert_eq!(sniff(b"<!-- comment -->"), html);
+        assert_eq!(
+            sniff(b"<svg xmlns=\"http://www.w3.org/2000/svg\">"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(
+            sniff(b"<?xml version=\"1.0\"?>"),
+            Some("text/xml; charset=utf-8")
+        );
+        // `<pre>` is not a `<p>` tag.
+        assert_eq!(sniff(b"<pre>"), Some("text/plain; charset=utf-8"));
+
+        assert_eq!(sniff(b"\xef\xbb\xbf<html>"), html);
+        assert_eq!(
+            sniff(b"\xef\xbb\xbfHello"),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(sniff(b"\xff\xfeH\0"), Some("text/plain; charset=utf-16le"));
+        assert_eq!(sniff(b"\xfe\xff\0H"), Some("text/plain; charset=utf-16be"));
+        // A character cut off at the end is still UTF-8, invalid data is not.
+        assert_eq!(
+            sniff("Hello, 世界".as_bytes()[..11].as_ref()),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(sniff(b"Hello \xe9t\xe9"), Some("text/plain"));
     }
 }
@@ -0,0 +1,971 @@
+//! Streaming `multipart/form-data` parsing and building (RFC 7578).
+//!
+//! [`Multipart`] reads fields one by one from a body without buffering it,
+//! and [`MultipartBuilder`] assembles a body for outgoing requests.
+//!
+//! # Example
+//! ```rust
+//! use http_kit::{multipart::MultipartBuilder, Request};
+//!
+//! # async_std::task::block_on(async {
+//! let form = MultipartBuilder::new()
+//!     .text("title", "Hello")
+//!     .file("upload", "hello.txt", "text/plain", "Hello,world")?;
+//! let mut request = Request::post("/upload").multipart(form);
+//!
+//! let mut multipart = request.into_multipart()?;
+//! while let Some(field) = multipart.next_field().await? {
+//!     match field.name() {
+//!         Some("title") => assert_eq!(field.into_string().await?, "Hello"),
+//!         Some("upload") => {
+//!             assert_eq!(field.file_name(), Some("hello.txt"));
+//!             assert_eq!(field.content_type(), Some("text/plain"));
+//!             assert_eq!(field.into_string().await?, "Hello,world");
+//!         }
+//!         _ => unreachable!(),
+//!     }
+//! }
+//! # http_kit::Result::Ok(())
+//! # }).unwrap();
+//! ```
+
+use std::{
+    collections::hash_map::RandomState,
+    fmt::Debug,
+    hash::{BuildHasher, Hasher},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use bytestr::ByteStr;
+use futures_lite::{future, ready, stream, AsyncBufRead, Stream, StreamExt};
+use http::{
+    header::{self, InvalidHeaderValue},
+    HeaderMap, HeaderName, HeaderValue, StatusCode,
+};
+
+use crate::{Body, BodyError, Result};
+
+type BoxStdError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+// The maximum size of the headers of a field.
+const MAX_HEADERS_SIZE: usize = 8 * 1024;
+
+impl_error!(MalformedMultipart, "Malformed multipart body");
+impl_error!(
+    MissingBoundary,
+    "Content-type is not `multipart/form-data` with a boundary"
+);
+impl_error!(FieldTooLarge, "Multipart field exceeds the size limit");
+impl_error!(MultipartTooLarge, "Multipart body exceeds the size limit");
+impl_error!(InvalidBoundary, "Invalid multipart boundary");
+
+fn malformed() -> BoxStdError {
+    MalformedMultipart::new().into()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Preamble,
+    // A delimiter was read, followed by either the headers of a field or the end.
+    Delimiter,
+    Headers,
+    Data,
+    End,
+}
+
+struct Parser {
+    reader: Pin<Box<dyn AsyncBufRead + Send + Sync + 'static>>,
+    buf: BytesMut,
+    // `CRLF--boundary`.
+    delimiter: Bytes,
+    state: State,
+    // The index of the current field.
+    field: u64,
+    field_size: u64,
+    total_size: u64,
+    max_field_size: u64,
+    max_total_size: u64,
+}
+
+impl Parser {
+    // Read more data into the buffer, reaching EOF is always an error as the closing delimiter is missing.
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), BoxStdError>> {
+        let data = ready!(self.reader.as_mut().poll_fill_buf(cx))?;
+        if data.is_empty() {
+            return Poll::Ready(Err(malformed()));
+        }
+        let len = data.len();
+        self.total_size += len as u64;
+        if self.total_size > self.max_total_size {
+            return Poll::Ready(Err(MultipartTooLarge::new().into()));
+        }
+        self.buf.extend_from_slice(data);
+        self.reader.as_mut().consume(len);
+        Poll::Ready(Ok(()))
+    }
+
+    fn take_data(&mut self, len: usize) -> std::result::Result<Bytes, BoxStdError> {
+        self.field_size += len as u64;
+        if self.field_size > self.max_field_size {
+            return Err(FieldTooLarge::new().into());
+        }
+        Ok(self.buf.split_to(len).freeze())
+    }
+
+    // Poll the data of the current field, return `None` once the next delimiter is reached.
+    fn poll_data(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Bytes, BoxStdError>>> {
+        loop {
+            if let Some(index) = find(&self.buf, &self.delimiter) {
+                if index == 0 {
+                    self.buf.advance(self.delimiter.len());
+                    self.state = State::Delimiter;
+                    return Poll::Ready(None);
+                }
+                return Poll::Ready(Some(self.take_data(index)));
+            }
+            // Keep the tail which may be the beginning of a delimiter.
+            let keep = self.delimiter.len() - 1;
+            if self.buf.len() > keep {
+                return Poll::Ready(Some(self.take_data(self.buf.len() - keep)));
+            }
+            if let Err(error) = ready!(self.poll_fill(cx)) {
+                return Poll::Ready(Some(Err(error)));
+            }
+        }
+    }
+
+    // Skip to the headers of the next field, and parse them.
+    fn poll_next_field(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::result::Result<Option<HeaderMap>, BoxStdError>> {
+        loop {
+            match self.state {
+                State::Preamble => {
+                    if let Some(index) = find(&self.buf, &self.delimiter) {
+                        self.buf.advance(index + self.delimiter.len());
+                        self.state = State::Delimiter;
+                        continue;
+                    }
+                    let keep = self.delimiter.len() - 1;
+                    if self.buf.len() > keep {
+                        let len = self.buf.len() - keep;
+                        self.buf.advance(len);
+                    }
+                }
+                // The rest of the previous field is discarded.
+                State::Data => {
+                    if let Some(Err(error)) = ready!(self.poll_data(cx)) {
+                        return Poll::Ready(Err(error));
+                    }
+                    continue;
+                }
+                State::Delimiter => {
+                    if self.buf.starts_with(b"--") {
+                        // The epilogue is ignored.
+                        self.state = State::End;
+                        continue;
+                    }
+                    if let Some(index) = find(&self.buf, b"\r\n") {
+                        // Only transport padding is allowed after the delimiter.
+                        if !self.buf[..index]
+                            .iter()
+                            .all(|&byte| byte == b' ' || byte == b'\t')
+                        {
+                            return Poll::Ready(Err(malformed()));
+                        }
+                        self.buf.advance(index + 2);
+                        self.state = State::Headers;
+                        continue;
+                    }
+                    if self.buf.len() > MAX_HEADERS_SIZE {
+                        return Poll::Ready(Err(malformed()));
+                    }
+                }
+                State::Headers => {
+                    let end = if self.buf.starts_with(b"\r\n") {
+                        Some(0)
+                    } else {
+                        find(&self.buf, b"\r\n\r\n").map(|index| index + 2)
+                    };
+                    if let Some(end) = end {
+                        let headers = parse_headers(&self.buf[..end])?;
+                        self.buf.advance(end + 2);
+                        self.state = State::Data;
+                        self.field += 1;
+                        self.field_size = 0;
+                        return Poll::Ready(Ok(Some(headers)));
+                    }
+                    if self.buf.len() > MAX_HEADERS_SIZE {
+                        return Poll::Ready(Err(malformed()));
+                    }
+                }
+                State::End => return Poll::Ready(Ok(None)),
+            }
+            ready!(self.poll_fill(cx))?;
+        }
+    }
+}
+
+fn parse_headers(block: &[u8]) -> std::result::Result<HeaderMap, BoxStdError> {
+    let mut headers = HeaderMap::new();
+    for line in block.split(|&byte| byte == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        let colon = line
+            .iter()
+            .position(|&byte| byte == b':')
+            .ok_or_else(malformed)?;
+        let name = HeaderName::from_bytes(line[..colon].trim_ascii()).map_err(|_| malformed())?;
+        let value =
+            HeaderValue::from_bytes(line[colon + 1..].trim_ascii()).map_err(|_| malformed())?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+// Iterate over `key=value` parameters following the first item of a header value, keys are lowercased.
+fn parameters(value: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    let mut rest = value.split_once(';').map_or("", |(_, rest)| rest);
+    std::iter::from_fn(move || loop {
+        rest = rest.trim_start_matches([' ', '\t', ';']);
+        if rest.is_empty() {
+            return None;
+        }
+        let (key, after) = rest.split_once('=').unwrap_or((rest, ""));
+        let key = key.trim().to_ascii_lowercase();
+        let after = after.trim_start();
+        let value = if let Some(quoted) = after.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((index, char)) = chars.next() {
+                match char {
+                    '\\' => value.extend(chars.next().map(|(_, char)| char)),
+                    '"' => {
+                        end = index + 1;
+                        break;
+                    }
+                    char => value.push(char),
+                }
+            }
+            rest = &quoted[end..];
+            value
+        } else {
+            let end = after.find(';').unwrap_or(after.len());
+            rest = &after[end..];
+            after[..end].trim().to_owned()
+        };
+        if !key.is_empty() {
+            return Some((key, value));
+        }
+    })
+}
+
+/// Extract the boundary from a `multipart/form-data` content type.
+fn boundary(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    let essence = content_type.split(';').next()?.trim();
+    if !essence.eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    parameters(content_type)
+        .find(|(key, _)| key == "boundary")
+        .map(|(_, boundary)| boundary)
+        .filter(|boundary| !boundary.is_empty() && boundary.len() <= 70)
+}
+
+/// A streaming reader of a `multipart/form-data` body.
+///
+/// Fields are yielded in order. A field which is not completely read is skipped when the next field is requested.
+/// Size limits are unlimited by default, violations are reported as `413 Payload Too Large`
+/// while malformed bodies are reported as `400 Bad Request`.
+pub struct Multipart {
+    parser: Arc<Mutex<Parser>>,
+}
+
+impl Debug for Multipart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Multipart")
+    }
+}
+
+fn into_error(error: BoxStdError) -> crate::Error {
+    let status = if error.is::<FieldTooLarge>() || error.is::<MultipartTooLarge>() {
+        StatusCode::PAYLOAD_TOO_LARGE
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    match BodyError::from(error) {
+        BodyError::TooLarge => BodyError::TooLarge.into_http_error(),
+        error => crate::Error::new(error, status),
+    }
+}
+
+impl Multipart {
+    /// Create a reader parsing `body` delimited by `boundary`.
+    pub fn new(body: Body, boundary: &str) -> Self {
+        let delimiter = Bytes::from(format!("\r\n--{boundary}"));
+        // The first delimiter may not be preceded by a line break.
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"\r\n");
+        Self {
+            parser: Arc::new(Mutex::new(Parser {
+                reader: Box::pin(body.into_reader()),
+                buf,
+                delimiter,
+                state: State::Preamble,
+                field: 0,
+                field_size: 0,
+                total_size: 0,
+                max_field_size: u64::MAX,
+                max_total_size: u64::MAX,
+            })),
+        }
+    }
+
+    /// Create a reader parsing `body`, with the boundary extracted from the `Content-Type` of `headers`.
+    /// Fail with `415 Unsupported Media Type` if the content type is not `multipart/form-data` with a boundary.
+    pub fn from_headers(headers: &HeaderMap, body: Body) -> Result<Self> {
+        let boundary = boundary(headers).ok_or_else(|| {
+            crate::Error::new(MissingBoundary::new(), StatusCode::UNSUPPORTED_MEDIA_TYPE)
+        })?;
+        Ok(Self::new(body, &boundary))
+    }
+
+    /// Set the maximum size of the data of each field.
+    pub fn max_field_size(self, size: u64) -> Self {
+        self.parser.lock().unwrap().max_field_size = size;
+        self
+    }
+
+    /// Set the maximum size of the whole body.
+    pub fn max_total_size(self, size: u64) -> Self {
+        self.parser.lock().unwrap().max_total_size = size;
+        self
+    }
+
+    /// Return the next field, or `None` if all fields have been read.
+    pub async fn next_field(&mut self) -> Result<Option<Field>> {
+        let headers = future::poll_fn(|cx| self.parser.lock().unwrap().poll_next_field(cx))
+            .await
+            .map_err(into_error)?;
+        let Some(headers) = headers else {
+            return Ok(None);
+        };
+
+        let disposition = headers
+            .get(header::CONTENT_DISPOSITION)
+            .and_then(|value| std::str::from_utf8(value.as_bytes()).ok())
+            .unwrap_or_default();
+        let mut name = None;
+        let mut file_name = None;
+        let mut extended_file_name = None;
+        for (key, value) in parameters(disposition) {
+            match key.as_str() {
+                "name" => name = Some(value),
+                "filename" => file_name = Some(value),
+                "filename*" => extended_file_name = decode_extended(&value),
+                _ => {}
+            }
+        }
+        let file_name = extended_file_name.or(file_name);
+
+        let field = self.parser.lock().unwrap().field;
+        Ok(Some(Field {
+            headers,
+            name,
+            file_name,
+            body: Body::from_stream(FieldData {
+                parser: self.parser.clone(),
+                field,
+            }),
+        }))
+    }
+}
+
+// Decode an extended parameter value as `charset'language'percent-encoded` (RFC 8187),
+// only the UTF-8 charset is supported.
+fn decode_extended(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let (charset, _language, encoded) = (parts.next()?, parts.next()?, parts.next()?);
+    charset
+        .eq_ignore_ascii_case("utf-8")
+        .then(|| crate::router::percent_decode(encoded))
+}
+
+struct FieldData {
+    parser: Arc<Mutex<Parser>>,
+    field: u64,
+}
+
+impl Stream for FieldData {
+    type Item = std::result::Result<Bytes, BoxStdError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut parser = self.parser.lock().unwrap();
+        // The field has been read or skipped.
+        if parser.field != self.field || parser.state != State::Data {
+            return Poll::Ready(None);
+        }
+        // Size limits are reported as for `Body::limit`, so that they keep their status.
+        parser.poll_data(cx).map_err(|error| {
+            if error.is::<FieldTooLarge>() || error.is::<MultipartTooLarge>() {
+                Box::new(BodyError::TooLarge) as BoxStdError
+            } else {
+                error
+            }
+        })
+    }
+}
+
+/// A field of a `multipart/form-data` body.
+pub struct Field {
+    headers: HeaderMap,
+    name: Option<String>,
+    file_name: Option<String>,
+    body: Body,
+}
+
+impl Debug for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Field")
+            .field("headers", &self.headers)
+            .field("name", &self.name)
+            .field("file_name", &self.file_name)
+            .finish()
+    }
+}
+
+impl Field {
+    /// Return the headers of the field.
+    pub const fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Return the name of the field from its `Content-Disposition`.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Return the file name of the field from its `Content-Disposition`, if the field is a file.
+    /// An extended `filename*` parameter (RFC 8187) takes precedence over `filename`.
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// Return the content type of the field.
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get(header::CONTENT_TYPE)?.to_str().ok()
+    }
+
+    /// Return the data of the field as a streaming body,
+    /// which must be read before requesting the next field.
+    pub fn into_body(self) -> Body {
+        self.body
+    }
+
+    /// Read the data of the field and return a `Bytes` object.
+    /// Fail with [`BodyError::TooLarge`] if a size limit of the reader is exceeded.
+    pub async fn into_bytes(self) -> std::result::Result<Bytes, BodyError> {
+        self.body.into_bytes().await
+    }
+
+    /// Read the data of the field as a UTF-8 string and return a `ByteStr`.
+    pub async fn into_string(self) -> std::result::Result<ByteStr, BodyError> {
+        self.body.into_string().await
+    }
+}
+
+/// A builder of `multipart/form-data` bodies.
+pub struct MultipartBuilder {
+    boundary: String,
+    parts: Vec<(HeaderMap, Body)>,
+}
+
+impl Debug for MultipartBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultipartBuilder")
+            .field("boundary", &self.boundary)
+            .field("parts", &self.parts.len())
+            .finish()
+    }
+}
+
+impl Default for MultipartBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Escape a name or a file name as in the HTML form submission algorithm,
+// other control characters are percent-encoded as well since header values can not carry them.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for char in value.chars() {
+        if char == '"' || char.is_ascii_control() {
+            escaped.push_str(&format!("%{:02X}", char as u8));
+        } else {
+            escaped.push(char);
+        }
+    }
+    escaped
+}
+
+fn disposition(name: &str, file_name: Option<&str>) -> HeaderValue {
+    let mut value = format!("form-data; name=\"{}\"", escape(name));
+    if let Some(file_name) = file_name {
+        value.push_str(&format!("; filename=\"{}\"", escape(file_name)));
+    }
+    HeaderValue::from_bytes(value.as_bytes()).expect("Control characters are escaped")
+}
+
+pub(crate) fn random_boundary() -> String {
+    let random = || RandomState::new().build_hasher().finish();
+    format!("{:016x}{:016x}", random(), random())
+}
+
+// Return true if `boundary` is 1 to 70 characters allowed by RFC 2046, not ending with a space.
+fn is_valid_boundary(boundary: &str) -> bool {
+    (1..=70).contains(&boundary.len())
+        && !boundary.ends_with(' ')
+        && boundary
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"'()+_,-./:=? ".contains(&byte))
+}
+
+// Return `true` if the delimiter appears in the headers or the buffered body of a part.
+fn contains_delimiter((headers, body): &(HeaderMap, Body), delimiter: &[u8]) -> bool {
+    let contains = |data: &[u8]| find(data, delimiter).is_some();
+    headers
+        .iter()
+        .any(|(name, value)| contains(name.as_str().as_bytes()) || contains(value.as_bytes()))
+        || body.buffered().is_some_and(|data| contains(data))
+}
+
+fn serialize_head(boundary: &str, headers: &HeaderMap) -> Vec<u8> {
+    let mut head = format!("--{boundary}\r\n").into_bytes();
+    for (name, value) in headers {
+        head.extend_from_slice(name.as_str().as_bytes());
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+    head.extend_from_slice(b"\r\n");
+    head
+}
+
+impl MultipartBuilder {
+    /// Create a builder with a random boundary.
+    pub fn new() -> Self {
+        Self {
+            boundary: random_boundary(),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Set the boundary, fail if it is not 1 to 70 characters allowed by RFC 2046.
+    ///
+    /// Like a random boundary, it is replaced if it appears in a part held in memory.
+    pub fn boundary(
+        mut self,
+        boundary: impl Into<String>,
+    ) -> std::result::Result<Self, InvalidBoundary> {
+        let boundary = boundary.into();
+        if !is_valid_boundary(&boundary) {
+            return Err(InvalidBoundary::new());
+        }
+        self.boundary = boundary;
+        self.ensure_boundary(0);
+        Ok(self)
+    }
+
+    // Generate new boundaries until none appears in a part, streaming parts can not be checked.
+    // Parts before `checked` are known not to contain the current boundary.
+    fn ensure_boundary(&mut self, checked: usize) {
+        let mut delimiter = format!("--{}", self.boundary);
+        let mut unchecked = &self.parts[checked..];
+        while unchecked
+            .iter()
+            .any(|part| contains_delimiter(part, delimiter.as_bytes()))
+        {
+            self.boundary = random_boundary();
+            delimiter = format!("--{}", self.boundary);
+            unchecked = &self.parts;
+        }
+    }
+
+    /// Add a part with arbitrary headers.
+    pub fn part(mut self, headers: HeaderMap, body: impl Into<Body>) -> Self {
+        self.parts.push((headers, body.into()));
+        self.ensure_boundary(self.parts.len() - 1);
+        self
+    }
+
+    /// Add a text field.
+    pub fn text(self, name: &str, value: impl Into<Body>) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_DISPOSITION, disposition(name, None));
+        self.part(headers, value)
+    }
+
+    /// Add a file field, fail if `content_type` is not a valid header value.
+    ///
+    /// Non-ASCII file names are sent as UTF-8 in the `filename` parameter,
+    /// as RFC 7578 forbids `filename*` in `multipart/form-data`.
+    pub fn file(
+        self,
+        name: &str,
+        file_name: &str,
+        content_type: &str,
+        body: impl Into<Body>,
+    ) -> std::result::Result<Self, InvalidHeaderValue> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            disposition(name, Some(file_name)),
+        );
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_str(content_type)?);
+        Ok(self.part(headers, body))
+    }
+
+    /// Return the `Content-Type` of the body, carrying the boundary.
+    pub fn content_type(&self) -> HeaderValue {
+        let value = if self
+            .boundary
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"'+_-.".contains(&byte))
+        {
+            format!("multipart/form-data; boundary={}", self.boundary)
+        } else {
+            format!("multipart/form-data; boundary=\"{}\"", self.boundary)
+        };
+        HeaderValue::from_str(&value).expect("Boundary is validated")
+    }
+
+    /// Build the body, return its `Content-Type` and itself.
+    pub fn build(self) -> (HeaderValue, Body) {
+        let content_type = self.content_type();
+        let mut length = Some(0);
+        let mut pieces = Vec::with_capacity(self.parts.len() * 3 + 1);
+        for (headers, body) in self.parts {
+            let head = serialize_head(&self.boundary, &headers);
+            length = length
+                .zip(body.len())
+                .map(|(length, body)| length + head.len() + body + 2);
+            pieces.push(Body::from_bytes(head));
+            pieces.push(body);
+            pieces.push(Body::from_bytes(Bytes::from_static(b"\r\n")));
+        }
+        let tail = format!("--{}--\r\n", self.boundary);
+        length = length.map(|length| length + tail.len());
+        pieces.push(Body::from_bytes(tail));
+
+        let body = Body::from_stream(stream::iter(pieces).flatten());
+        (content_type, Body::from_reader(body.into_reader(), length))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::{future::block_on, stream};
+    use http::{header, HeaderMap, HeaderValue, StatusCode};
+
+    use super::{Multipart, MultipartBuilder};
+    use crate::{Body, BodyError};
+
+    // Parse the body built by `builder`, return the name, file name and data of each field.
+    fn parse(builder: MultipartBuilder) -> Vec<(String, Option<String>, String)> {
+        let (content_type, body) = builder.build();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, content_type);
+        parse_body(&headers, body)
+    }
+
+    fn parse_body(headers: &HeaderMap, body: Body) -> Vec<(String, Option<String>, String)> {
+        block_on(async {
+            let mut multipart = Multipart::from_headers(headers, body).unwrap();
+            let mut fields = Vec::new();
+            while let Some(field) = multipart.next_field().await.unwrap() {
+                let name = field.name().unwrap().to_owned();
+                let file_name = field.file_name().map(str::to_owned);
+                fields.push((
+                    name,
+                    file_name,
+                    field.into_string().await.unwrap().to_string(),
+                ));
+            }
+            fields
+        })
+    }
+
+    async fn parse_multipart(mut multipart: Multipart) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        while let Some(field) = multipart.next_field().await.unwrap() {
+            let name = field.name().unwrap().to_owned();
+            fields.push((name, field.into_string().await.unwrap().to_string()));
+        }
+        fields
+    }
+
+    #[test]
+    fn round_trip() {
+        let builder = MultipartBuilder::new()
+            .text("title", "Hello")
+            .file("upload", "héllo.txt", "text/plain", "Hello,world")
+            .unwrap();
+        assert_eq!(
+            parse(builder),
+            [
+                ("title".to_owned(), None, "Hello".to_owned()),
+                (
+                    "upload".to_owned(),
+                    Some("héllo.txt".to_owned()),
+                    "Hello,world".to_owned()
+                ),
+            ]
+        );
+    }
+
+    fn form_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("multipart/form-data; boundary=abc"),
+        );
+        headers
+    }
+
+    const FORM: &str = "--abc\r\ncontent-disposition: form-data; name=\"a\"\r\n\r\n1234567890\r\n\
+        --abc\r\ncontent-disposition: form-data; name=\"b\"\r\n\r\n12\r\n--abc--\r\n";
+
+    #[test]
+    fn max_field_size() {
+        block_on(async {
+            let mut multipart = Multipart::from_headers(&form_headers(), Body::from(FORM))
+                .unwrap()
+                .max_field_size(5);
+            let field = multipart.next_field().await.unwrap().unwrap();
+            let error = field.into_bytes().await.unwrap_err();
+            assert!(matches!(error, BodyError::TooLarge));
+
+            // The limit is also enforced while an unread field is skipped.
+            let mut multipart = Multipart::from_headers(&form_headers(), Body::from(FORM))
+                .unwrap()
+                .max_field_size(5);
+            multipart.next_field().await.unwrap().unwrap();
+            let error = multipart.next_field().await.unwrap_err();
+            assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+            let multipart = Multipart::from_headers(&form_headers(), Body::from(FORM))
+                .unwrap()
+                .max_field_size(10);
+            assert_eq!(
+                parse_multipart(multipart).await,
+                [
+                    ("a".to_owned(), "1234567890".to_owned()),
+                    ("b".to_owned(), "12".to_owned())
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn max_total_size() {
+        block_on(async {
+            let mut multipart = Multipart::from_headers(&form_headers(), Body::from(FORM))
+                .unwrap()
+                .max_total_size(FORM.len() as u64 - 1);
+            let error = multipart.next_field().await.unwrap_err();
+            assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+            // The limit is also enforced while the data of a field is read.
+            let (head, rest) = FORM.split_at(FORM.find("1234").unwrap());
+            let body = Body::from_stream(stream::iter([Ok::<_, std::io::Error>(head), Ok(rest)]));
+            let mut multipart = Multipart::from_headers(&form_headers(), body)
+                .unwrap()
+                .max_total_size(head.len() as u64 + 5);
+            let field = multipart.next_field().await.unwrap().unwrap();
+            let error = field.into_bytes().await.unwrap_err();
+            assert!(matches!(error, BodyError::TooLarge));
+
+            let multipart = Multipart::from_headers(&form_headers(), Body::from(FORM))
+                .unwrap()
+                .max_total_size(FORM.len() as u64);
+            assert_eq!(parse_multipart(multipart).await.len(), 2);
+        });
+    }
+
+    #[test]
+    fn malformed_body() {
+        for body in [
+            // The closing delimiter is missing.
+            "--abc\r\ncontent-disposition: form-data; name=\"a\"\r\n\r\n1",
+            // Data follows the delimiter on the same line.
+            "--abc garbage\r\n\r\n1\r\n--abc--\r\n",
+            // A header line without a colon.
+            "--abc\r\ncontent-disposition\r\n\r\n1\r\n--abc--\r\n",
+            "",
+        ] {
+            let error = block_on(async {
+                let mut multipart =
+                    Multipart::from_headers(&form_headers(), Body::from(body)).unwrap();
+                loop {
+                    match multipart.next_field().await {
+                        Ok(Some(_)) => continue,
+                        Ok(None) => panic!("{body:?} is accepted"),
+                        Err(error) => break error,
+                    }
+                }
+            });
+            assert_eq!(error.status(), StatusCode::BAD_REQUEST, "{body:?}");
+        }
+    }
+
+    #[test]
+    fn missing_boundary() {
+        for content_type in [
+            Some("multipart/form-data"),
+            Some("multipart/form-data; boundary="),
+            Some("multipart/mixed; boundary=abc"),
+            Some("application/x-www-form-urlencoded"),
+            None,
+        ] {
+            let mut headers = HeaderMap::new();
+            if let Some(content_type) = content_type {
+                headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+            }
+            let error = Multipart::from_headers(&headers, Body::from(FORM)).unwrap_err();
+            assert_eq!(
+                error.status(),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "{content_type:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn unread_field_is_skipped() {
+        block_on(async {
+            let mut multipart = Multipart::from_headers(&form_headers(), Body::from(FORM)).unwrap();
+            let first = multipart.next_field().await.unwrap().unwrap();
+            assert_eq!(first.name(), Some("a"));
+            let second = multipart.next_field().await.unwrap().unwrap();
+            assert_eq!(second.name(), Some("b"));
+            assert_eq!(second.into_string().await.unwrap(), "12");
+            // The skipped field does not yield data anymore.
+            assert_eq!(first.into_bytes().await.unwrap(), "");
+            assert!(multipart.next_field().await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn control_characters_are_escaped() {
+        let builder = MultipartBuilder::new().text("a\u{1}b\r\n\"", "x");
+        assert_eq!(
+            parse(builder),
+            [("a%01b%0D%0A%22".to_owned(), None, "x".to_owned())]
+        );
+
+        let builder = MultipartBuilder::new()
+            .file("a\tb", "c\u{7f}d\0.txt", "text/plain", "x")
+            .unwrap();
+        assert_eq!(
+            parse(builder),
+            [(
+                "a%09b".to_owned(),
+                Some("c%7Fd%00.txt".to_owned()),
+                "x".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn invalid_content_type() {
+        assert!(MultipartBuilder::new()
+            .file("upload", "a.txt", "text/plain\r\n", "")
+            .is_err());
+    }
+
+    #[test]
+    fn invalid_boundary() {
+        assert!(MultipartBuilder::new().boundary("").is_err());
+        assert!(MultipartBuilder::new().boundary("a\"b").is_err());
+        assert!(MultipartBuilder::new().boundary("a".repeat(71)).is_err());
+    }
+
+    #[test]
+    fn quoted_boundary() {
+        let builder = MultipartBuilder::new()
+            .boundary("a b")
+            .unwrap()
+            .text("a", "1");
+        assert_eq!(
+            builder.content_type(),
+            "multipart/form-data; boundary=\"a b\""
+        );
+        assert_eq!(parse(builder), [("a".to_owned(), None, "1".to_owned())]);
+    }
+
+    #[test]
+    fn colliding_boundary_is_replaced() {
+        let builder = MultipartBuilder::new()
+            .boundary("abc")
+            .unwrap()
+            .text("a", "x\r\n--abc--\r\n");
+        assert_ne!(builder.boundary, "abc");
+        assert_eq!(
+            parse(builder),
+            [("a".to_owned(), None, "x\r\n--abc--\r\n".to_owned())]
+        );
+
+        let builder = MultipartBuilder::new().text("a", "--abc");
+        let builder = builder.boundary("abc").unwrap();
+        assert_ne!(builder.boundary, "abc");
+
+        let builder = MultipartBuilder::new()
+            .boundary("abc")
+            .unwrap()
+            .text("a", "--ab")
+            .text("b", "-abc");
+        assert_eq!(builder.boundary, "abc");
+        let builder = builder.text("c", "--abcd");
+        assert_ne!(builder.boundary, "abc");
+        assert_eq!(parse(builder).len(), 3);
+    }
+
+    #[test]
+    fn extended_file_name() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("multipart/form-data; boundary=abc"),
+        );
+        let body = Body::from(
+            "--abc\r\ncontent-disposition: form-data; name=\"a\"; filename=\"euro.txt\"; \
+             filename*=UTF-8''%E2%82%AC.txt\r\n\r\n1\r\n--abc\r\n\
+             content-disposition: form-data; name=\"b\"; filename*=unknown''x.txt\r\n\r\n2\r\n--abc--\r\n",
+        );
+        assert_eq!(
+            parse_body(&headers, body),
+            [
+                ("a".to_owned(), Some("€.txt".to_owned()), "1".to_owned()),
+                ("b".to_owned(), None, "2".to_owned()),
+            ]
+        );
+    }
+}
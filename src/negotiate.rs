@@ -0,0 +1,369 @@
+//! Content negotiation with `Accept`, `Accept-Language`, `Accept-Charset` and `Accept-Encoding`.
+//!
+//! Each function chooses the best of the `available` values according to q-values and wildcards,
+//! earlier values win ties. `None` is returned if no value is acceptable.
+//! # Example
+//! ```rust
+//! use http_kit::{header, negotiate, Request, Response};
+//!
+//! let request = Request::get("/").header(header::ACCEPT, "text/*;q=0.5, application/json");
+//! let content_type = negotiate::media_type(request.headers(), &["text/plain", "application/json"]);
+//! assert_eq!(content_type, Some("application/json"));
+//!
+//! let response = Response::new(200, "{}").header(header::CONTENT_TYPE, content_type.unwrap());
+//! ```
+
+use http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+
+use crate::content_type::essence;
+
+impl_error!(NotAcceptable, "No acceptable representation is available");
+
+// Add `name` to `Vary`, unless it is already listed or `Vary` is `*`.
+pub(crate) fn append_vary(headers: &mut HeaderMap, name: &'static str) {
+    let varies = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|item| {
+            let item = item.trim();
+            item == "*" || item.eq_ignore_ascii_case(name)
+        });
+    if !varies {
+        headers.append(header::VARY, HeaderValue::from_static(name));
+    }
+}
+
+// An item of an `Accept-*` header, the weight is in thousandths.
+struct Weighted<'a> {
+    value: &'a str,
+    params: Vec<(&'a str, &'a str)>,
+    weight: u16,
+}
+
+fn parse_weight(value: &str) -> Option<u16> {
+    let (integer, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if fraction.len() > 3 || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let fraction = format!("{fraction:0<3}").parse::<u16>().ok()?;
+    match integer {
+        "0" => Some(fraction),
+        "1" if fraction == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+// Parse all values of the header, return `None` if it is absent.
+fn parse(headers: &HeaderMap, name: HeaderName) -> Option<Vec<Weighted<'_>>> {
+    let mut values = headers.get_all(name).iter().peekable();
+    values.peek()?;
+    let items = values
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let value = parts.next()?.trim();
+            if value.is_empty() {
+                return None;
+            }
+            let mut params = Vec::new();
+            let mut weight = 1000;
+            for param in parts {
+                let (key, param) = param.split_once('=').unwrap_or((param, ""));
+                let (key, param) = (key.trim(), param.trim().trim_matches('"'));
+                if key.is_empty() {
+                    continue;
+                }
+                if key.eq_ignore_ascii_case("q") {
+                    // Parameters after the weight are extensions, which are ignored.
+                    weight = parse_weight(param)?;
+                    break;
+                }
+                params.push((key, param));
+            }
+            Some(Weighted {
+                value,
+                params,
+                weight,
+            })
+        })
+        .collect();
+    Some(items)
+}
+
+// Choose the available value with the highest weight.
+// `weight` returns the weight of the most specific matched item.
+fn choose<'a>(available: &[&'a str], weight: impl Fn(&str) -> u16) -> Option<&'a str> {
+    let mut best: Option<(&'a str, u16)> = None;
+    for value in available {
+        let weight = weight(value);
+        if weight > 0 && best.is_none_or(|(_, best)| weight > best) {
+            best = Some((value, weight));
+        }
+    }
+    best.map(|(value, _)| value)
+}
+
+// Find the weight of the matched item with the highest specificity.
+fn most_specific(
+    items: &[Weighted<'_>],
+    specificity: impl Fn(&Weighted<'_>) -> Option<usize>,
+) -> Option<u16> {
+    items
+        .iter()
+        .filter_map(|item| Some((specificity(item)?, item.weight)))
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, weight)| weight)
+}
+
+/// Choose a media type by `Accept`, like `text/html` or `application/json`.
+///
+/// Parameters of a media range must be present in the media type, all media types are acceptable if the header is absent.
+pub fn media_type<'a>(headers: &HeaderMap, available: &[&'a str]) -> Option<&'a str> {
+    let Some(items) = parse(headers, header::ACCEPT) else {
+        return available.first().copied();
+    };
+    choose(available, |media_type| {
+        let Some((ty, subtype)) = essence(media_type) else {
+            return 0;
+        };
+        let params: Vec<_> = media_type
+            .split(';')
+            .skip(1)
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim().trim_matches('"')))
+            .collect();
+        most_specific(&items, |item| {
+            let (range_ty, range_subtype) = item.value.split_once('/')?;
+            let specificity = match (range_ty, range_subtype) {
+                ("*", "*") => 0,
+                (range_ty, "*") if range_ty.eq_ignore_ascii_case(ty) => 1,
+                (range_ty, range_subtype)
+                    if range_ty.eq_ignore_ascii_case(ty)
+                        && range_subtype.eq_ignore_ascii_case(subtype) =>
+                {
+                    2
+                }
+                _ => return None,
+            };
+            let params_matched = item.params.iter().all(|(key, value)| {
+                params.iter().any(|(name, param)| {
+                    name.eq_ignore_ascii_case(key) && param.eq_ignore_ascii_case(value)
+                })
+            });
+            params_matched.then_some(specificity + item.params.len())
+        })
+        .unwrap_or(0)
+    })
+}
+
+/// Choose a language tag by `Accept-Language`, the range `en` matches `en` and `en-US`.
+///
+/// All languages are acceptable if the header is absent.
+pub fn language<'a>(headers: &HeaderMap, available: &[&'a str]) -> Option<&'a str> {
+    let Some(items) = parse(headers, header::ACCEPT_LANGUAGE) else {
+        return available.first().copied();
+    };
+    choose(available, |tag| {
+        most_specific(&items, |item| {
+            let range = item.value;
+            if range == "*" {
+                return Some(0);
+            }
+            let matched = tag.eq_ignore_ascii_case(range)
+                || tag
+                    .get(..range.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(range))
+                    && tag.as_bytes().get(range.len()) == Some(&b'-');
+            matched.then_some(range.len())
+        })
+        .unwrap_or(0)
+    })
+}
+
+/// Choose a charset by `Accept-Charset`.
+///
+/// All charsets are acceptable if the header is absent.
+pub fn charset<'a>(headers: &HeaderMap, available: &[&'a str]) -> Option<&'a str> {
+    let Some(items) = parse(headers, header::ACCEPT_CHARSET) else {
+        return available.first().copied();
+    };
+    choose(available, |charset| {
+        most_specific(&items, |item| match item.value {
+            "*" => Some(0),
+            value => value.eq_ignore_ascii_case(charset).then_some(1),
+        })
+        .unwrap_or(0)
+    })
+}
+
+/// Choose a content coding by `Accept-Encoding`, `x-gzip` is treated as `gzip`.
+///
+/// `identity` is acceptable unless it is excluded explicitly,
+/// and it is the only acceptable coding if the header is absent.
+pub fn encoding<'a>(headers: &HeaderMap, available: &[&'a str]) -> Option<&'a str> {
+    let items = parse(headers, header::ACCEPT_ENCODING).unwrap_or_default();
+    let normalize = |coding: &str| {
+        let coding = coding.trim().to_ascii_lowercase();
+        match coding.as_str() {
+            "x-gzip" => "gzip".to_owned(),
+            "x-compress" => "compress".to_owned(),
+            _ => coding,
+        }
+    };
+    choose(available, |coding| {
+        let coding = normalize(coding);
+        let weight = most_specific(&items, |item| match item.value {
+            "*" => Some(0),
+            value => (normalize(value) == coding).then_some(1),
+        });
+        match weight {
+            Some(weight) => weight,
+            None if coding == "identity" => 1000,
+            None => 0,
+        }
+    })
+}
+
+pub(crate) fn not_acceptable() -> crate::Error {
+    crate::Error::new(NotAcceptable::new(), StatusCode::NOT_ACCEPTABLE)
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+
+    use super::{
+        append_vary, charset, encoding, language, media_type, not_acceptable, NotAcceptable,
+    };
+    use crate::Request;
+
+    fn headers(name: HeaderName, value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn quality_values() {
+        let available = ["text/html", "application/json", "text/plain"];
+        let accept = |value| media_type(&headers(header::ACCEPT, value), &available);
+        assert_eq!(accept("application/json"), Some("application/json"));
+        assert_eq!(
+            accept("text/html;q=0.5, application/json"),
+            Some("application/json")
+        );
+        assert_eq!(
+            accept("text/html;q=0.9, text/plain;q=0.95"),
+            Some("text/plain")
+        );
+        // Earlier available values win ties.
+        assert_eq!(accept("text/plain, text/html"), Some("text/html"));
+        assert_eq!(accept("text/html;q=0.001, image/png"), Some("text/html"));
+        // Invalid weights drop the item.
+        assert_eq!(accept("text/html;q=2, text/plain;q=0.1234"), None);
+        assert_eq!(media_type(&HeaderMap::new(), &available), Some("text/html"));
+
+        let available = ["en", "fr"];
+        let accept = |value| language(&headers(header::ACCEPT_LANGUAGE, value), &available);
+        assert_eq!(accept("fr-CH, fr;q=0.9, en;q=0.8"), Some("fr"));
+        assert_eq!(accept("de, en;q=0.5"), Some("en"));
+
+        let available = ["utf-8", "iso-8859-1"];
+        let accept = |value| charset(&headers(header::ACCEPT_CHARSET, value), &available);
+        assert_eq!(accept("iso-8859-1, UTF-8;q=0.7"), Some("iso-8859-1"));
+    }
+
+    #[test]
+    fn zero_weight_excludes() {
+        let available = ["text/html", "application/json"];
+        let accept = |value| media_type(&headers(header::ACCEPT, value), &available);
+        assert_eq!(accept("text/html;q=0, */*"), Some("application/json"));
+        assert_eq!(accept("*/*;q=0"), None);
+        assert_eq!(accept("text/html;q=0.000"), None);
+
+        let available = ["gzip", "identity"];
+        let accept = |value| encoding(&headers(header::ACCEPT_ENCODING, value), &available);
+        assert_eq!(accept("gzip;q=0"), Some("identity"));
+        assert_eq!(accept("x-gzip"), Some("gzip"));
+        assert_eq!(accept("gzip;q=0, identity;q=0"), None);
+        assert_eq!(accept("*;q=0"), None);
+        assert_eq!(encoding(&HeaderMap::new(), &available), Some("identity"));
+        assert_eq!(encoding(&HeaderMap::new(), &["gzip"]), None);
+    }
+
+    #[test]
+    fn wildcard_specificity() {
+        let available = ["text/html", "text/plain", "image/png"];
+        let accept = |value| media_type(&headers(header::ACCEPT, value), &available);
+        // The most specific range applies, regardless of the order of the header.
+        assert_eq!(accept("*/*;q=0.9, text/*;q=0.1"), Some("image/png"));
+        assert_eq!(
+            accept("text/*;q=0.1, */*;q=0.9, text/plain"),
+            Some("text/plain")
+        );
+        assert_eq!(accept("text/*, text/html;q=0"), Some("text/plain"));
+        assert_eq!(accept("image/*;q=0.5, */*;q=0.1"), Some("image/png"));
+
+        // Parameters of a range must be present in the media type.
+        let available = ["text/plain; charset=utf-8", "text/plain"];
+        let accept = |value| media_type(&headers(header::ACCEPT, value), &available);
+        assert_eq!(
+            accept("text/plain;charset=UTF-8;q=0.1, text/plain"),
+            Some("text/plain")
+        );
+        assert_eq!(
+            accept("text/plain;q=0.1, text/plain;charset=utf-8"),
+            Some("text/plain; charset=utf-8")
+        );
+
+        let available = ["en-US", "en-GB"];
+        let accept = |value| language(&headers(header::ACCEPT_LANGUAGE, value), &available);
+        assert_eq!(accept("en;q=0.5, en-GB"), Some("en-GB"));
+        assert_eq!(accept("*;q=0.5, en-US;q=0.1"), Some("en-GB"));
+        assert_eq!(accept("enx"), None);
+    }
+
+    #[test]
+    fn vary() {
+        let mut headers = HeaderMap::new();
+        append_vary(&mut headers, "accept");
+        append_vary(&mut headers, "accept-encoding");
+        append_vary(&mut headers, "accept");
+        let vary: Vec<_> = headers.get_all(header::VARY).iter().collect();
+        assert_eq!(vary, ["accept", "accept-encoding"]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::VARY, HeaderValue::from_static("Origin, Accept"));
+        append_vary(&mut headers, "accept");
+        append_vary(&mut headers, "accept-language");
+        let vary: Vec<_> = headers.get_all(header::VARY).iter().collect();
+        assert_eq!(vary, ["Origin, Accept", "accept-language"]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::VARY, HeaderValue::from_static("*"));
+        append_vary(&mut headers, "accept");
+        assert_eq!(headers.get_all(header::VARY).iter().count(), 1);
+    }
+
+    #[test]
+    fn not_acceptable_status() {
+        let error = not_acceptable();
+        assert_eq!(error.status(), StatusCode::NOT_ACCEPTABLE);
+        assert!(error.downcast_ref::<NotAcceptable>().is_some());
+
+        let request = Request::get("/")
+            .header(header::ACCEPT, "application/json")
+            .header(header::ACCEPT_ENCODING, "identity;q=0");
+        let error = request.negotiate(&["text/html"]).unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_ACCEPTABLE);
+        let error = request.negotiate_encoding(&["identity"]).unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(
+            request.negotiate(&["application/json"]).unwrap(),
+            "application/json"
+        );
+    }
+}
@@ -50,7 +50,6 @@ impl Request {
         Self::new(Method::GET, uri)
     }
     /// Create a POST `Request`.
-
     pub fn post<U>(uri: U) -> Self
     where
         U: TryInto<Uri>,
@@ -59,7 +58,6 @@ impl Request {
         Self::new(Method::POST, uri)
     }
     /// Create a PUT `Request`.
-
     pub fn put<U>(uri: U) -> Self
     where
         U: TryInto<Uri>,
@@ -68,7 +66,6 @@ impl Request {
         Self::new(Method::PUT, uri)
     }
     /// Create a DELETE `Request`.
-
     pub fn delete<U>(uri: U) -> Self
     where
         U: TryInto<Uri>,
@@ -81,13 +78,11 @@ impl Request {
         &self.parts
     }
     /// Return the mutable reference of request parts.
-
     pub fn parts_mut(&mut self) -> &mut RequestParts {
         &mut self.parts
     }
 
     /// Return the reference of request method.
-
     pub const fn method(&self) -> &Method {
         &self.parts.method
     }
@@ -107,7 +102,6 @@ impl Request {
         &self.parts.uri
     }
     /// Return the mutable reference of URI.
-
     pub fn uri_mut(&mut self) -> &mut Uri {
         &mut self.parts.uri
     }
@@ -116,17 +110,79 @@ impl Request {
     pub fn set_uri(&mut self, uri: Uri) {
         *self.uri_mut() = uri;
     }
+
+    /// Deserialize the query string of the URI, fail with `400 Bad Request` if it is malformed.
+    /// Repeated keys are deserialized into sequences like `Vec<T>`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use http_kit::Request;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Search {
+    ///     tag: Vec<String>,
+    ///     page: u32,
+    /// }
+    ///
+    /// let mut request = Request::get("http://example.com/search?page=1");
+    /// request
+    ///     .set_query(&Search { tag: vec!["a b".into(), "c".into()], page: 2 })
+    ///     .unwrap();
+    /// assert_eq!(request.uri(), "http://example.com/search?tag=a+b&tag=c&page=2");
+    /// let search: Search = request.query().unwrap();
+    /// assert_eq!(search.tag, ["a b", "c"]);
+    /// ```
+    #[cfg(feature = "form")]
+    pub fn query<'a, T>(&'a self) -> Result<T, crate::Error>
+    where
+        T: serde::Deserialize<'a>,
+    {
+        use crate::ResultExt;
+
+        serde_html_form::from_str(self.uri().query().unwrap_or_default())
+            .status(http::StatusCode::BAD_REQUEST)
+    }
+
+    /// Serialize `query` into the query string of the URI, the path is kept.
+    /// Sequences are serialized as repeated keys.
+    #[cfg(feature = "form")]
+    pub fn set_query<T: serde::Serialize + ?Sized>(
+        &mut self,
+        query: &T,
+    ) -> Result<(), crate::Error> {
+        use crate::ResultExt;
+
+        let query =
+            serde_html_form::to_string(query).status(http::StatusCode::INTERNAL_SERVER_ERROR)?;
+        let mut parts = self.uri().clone().into_parts();
+        let path = parts
+            .path_and_query
+            .as_ref()
+            .map_or("/", |path_and_query| path_and_query.path());
+        let path_and_query = if query.is_empty() {
+            path.to_owned()
+        } else {
+            format!("{path}?{query}")
+        };
+        parts.path_and_query = Some(
+            path_and_query
+                .parse()
+                .status(http::StatusCode::BAD_REQUEST)?,
+        );
+        self.set_uri(Uri::from_parts(parts).status(http::StatusCode::BAD_REQUEST)?);
+        Ok(())
+    }
+
     /// Return the HTTP version.
     pub const fn version(&self) -> Version {
         self.parts.version
     }
     /// Return the mutable reference of the HTTP version.
-
     pub fn version_mut(&mut self) -> &mut Version {
         &mut self.parts.version
     }
     /// Set the HTTP version by `version`.
-
     pub fn set_version(&mut self, version: Version) {
         *self.version_mut() = version;
     }
@@ -142,12 +198,10 @@ impl Request {
     }
 
     /// Return the reference of the HTTP header.
-
     pub const fn headers(&self) -> &HeaderMap {
         &self.parts.headers
     }
     /// Return the mutable reference of the HTTP header.
-
     pub fn headers_mut(&mut self) -> &mut HeaderMap {
         &mut self.parts.headers
     }
@@ -167,14 +221,52 @@ impl Request {
         self.headers_mut().insert(name, value)
     }
 
-    /// Return the reference of the extension.
+    /// Decode the typed header `H`, return `None` if it is missing,
+    /// fail with `400 Bad Request` if it is malformed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use http_kit::{header, typed_header::Range, Request};
+    ///
+    /// let request = Request::get("/").header(header::RANGE, "bytes=0-99");
+    /// let range = request.typed_header::<Range>().unwrap().unwrap();
+    /// assert_eq!(range.satisfiable(50), [(0, 49)]);
+    ///
+    /// let request = Request::get("/").header(header::RANGE, "bytes=99-0");
+    /// assert_eq!(request.typed_header::<Range>().unwrap_err().status(), 400);
+    /// ```
+    pub fn typed_header<H: crate::typed_header::TypedHeader>(
+        &self,
+    ) -> Result<Option<H>, crate::Error> {
+        crate::typed_header::decode(self.headers())
+    }
+
+    /// Encode the typed header `H`, the previous values of the header will be removed.
+    pub fn set_typed_header<H: crate::typed_header::TypedHeader>(&mut self, header: H) {
+        self.insert_header(H::name(), header.encode());
+    }
+
+    /// Parse the `Cookie` headers into a jar, malformed cookies are skipped.
+    #[cfg(feature = "cookie")]
+    pub fn cookies(&self) -> crate::cookie::CookieJar {
+        let mut jar = crate::cookie::CookieJar::new();
+        for value in self.headers().get_all(http::header::COOKIE) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            for cookie in crate::cookie::Cookie::split_parse_encoded(value).flatten() {
+                jar.add_original(cookie.into_owned());
+            }
+        }
+        jar
+    }
 
+    /// Return the reference of the extension.
     pub const fn extensions(&self) -> &Extensions {
         &self.parts.extensions
     }
 
     /// Return the mutable reference of the extension.
-
     pub fn extensions_mut(&mut self) -> &mut Extensions {
         &mut self.parts.extensions
     }
@@ -188,12 +280,10 @@ impl Request {
     pub fn get_mut_extension<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
         self.extensions_mut().get_mut()
     }
-
     /// Remove a type from extensions.
     pub fn remove_extension<T: Send + Sync + 'static>(&mut self) -> Option<T> {
         self.extensions_mut().remove()
     }
-
     /// Insert a type into extensions,if the type already exists,the old value will be returned.
     pub fn insert_extension<T: Send + Sync + 'static>(&mut self, extension: T) -> Option<T> {
         self.extensions_mut().insert(extension)
@@ -214,6 +304,58 @@ impl Request {
         self.body.swap(body)
     }
 
+    /// Set or validate the framing headers according to the body.
+    ///
+    /// `Transfer-Encoding` takes precedence and removes `Content-Length`, and `chunked` must be its final coding.
+    /// Otherwise `Content-Length` is set to the length of the body, or validated against it.
+    /// A declared length is enforced while reading a body of unknown length,
+    /// and `Transfer-Encoding: chunked` is set for HTTP/1.1 if the length is unknown or trailers are attached.
+    ///
+    /// Fail with `400 Bad Request` if the headers are invalid or do not match the body.
+    /// # Example
+    /// ```rust
+    /// use http_kit::{header, Request};
+    ///
+    /// let mut request = Request::post("/").map_body(|_| "Hello,world".into());
+    /// request.finalize_headers().unwrap();
+    /// assert_eq!(request.get_header(header::CONTENT_LENGTH).unwrap(), "11");
+    ///
+    /// let mut request = request.header(header::CONTENT_LENGTH, "5");
+    /// assert!(request.finalize_headers().is_err());
+    /// ```
+    pub fn finalize_headers(&mut self) -> Result<(), crate::Error> {
+        impl_error!(
+            NotChunked,
+            "The final transfer coding of a request must be `chunked`"
+        );
+
+        if self.headers().contains_key(http::header::TRANSFER_ENCODING) {
+            let not_chunked =
+                || crate::Error::new(NotChunked::new(), http::StatusCode::BAD_REQUEST);
+            // The codings of all values in order, `chunked` must be the final one and is not repeated.
+            let mut codings = Vec::new();
+            for value in self.headers().get_all(http::header::TRANSFER_ENCODING) {
+                let value = value.to_str().map_err(|_| not_chunked())?;
+                codings.extend(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|coding| !coding.is_empty()),
+                );
+            }
+            let is_chunked = |coding: &&str| coding.eq_ignore_ascii_case("chunked");
+            match codings.split_last() {
+                Some((last, rest)) if is_chunked(last) && !rest.iter().any(is_chunked) => {}
+                _ => return Err(not_chunked()),
+            }
+        }
+        let version = self.version();
+        let method = self.parts.method.clone();
+        self.body
+            .finalize_headers(&mut self.parts.headers, version, Some(&method))
+            .map_err(|error| crate::Error::new(error, http::StatusCode::BAD_REQUEST))
+    }
+
     /// Map the body to a different value.
     pub fn map_body<F>(mut self, f: F) -> Self
     where
@@ -223,6 +365,32 @@ impl Request {
         self
     }
 
+    /// Try to clone the request, which is only possible if the body is in memory.
+    /// Extensions are not cloned.
+    ///
+    /// A streaming body can be prepared for cloning by [`Body::into_replayable`].
+    /// # Example
+    /// ```rust
+    /// # async_std::task::block_on(async {
+    /// use http_kit::Request;
+    ///
+    /// let mut request = Request::post("/").map_body(|_| "Hello,world".into());
+    /// let body = request.take_body().unwrap().into_replayable(1024).await.unwrap();
+    /// request.replace_body(body);
+    /// let mut cloned = request.try_clone().unwrap();
+    /// assert_eq!(cloned.into_string().await.unwrap(), "Hello,world");
+    /// # })
+    /// ```
+    pub fn try_clone(&self) -> Option<Self> {
+        let body = self.body.try_clone()?;
+        let mut request = http::Request::new(body);
+        *request.method_mut() = self.method().clone();
+        *request.uri_mut() = self.uri().clone();
+        *request.version_mut() = self.version();
+        *request.headers_mut() = self.headers().clone();
+        Some(request.into())
+    }
+
     #[cfg(feature = "json")]
     /// Set the body from a JSON.
     /// This method will set `Content-type` header automatically.
@@ -254,6 +422,15 @@ impl Request {
         Ok(self)
     }
 
+    /// Set the body from a `multipart/form-data` builder.
+    /// This method will set `Content-type` header automatically.
+    pub fn multipart(mut self, form: crate::multipart::MultipartBuilder) -> Self {
+        let (content_type, body) = form.build();
+        self.insert_header(http::header::CONTENT_TYPE, content_type);
+        self.replace_body(body);
+        self
+    }
+
     /// Set the body from a file.
     /// This method will try to guess MIME by the extension of file.
     #[cfg(feature = "fs")]
@@ -268,6 +445,16 @@ impl Request {
         Ok(self)
     }
 
+    /// Serialize the request as an HTTP/1.1 message into `writer`, the body will be taken.
+    /// The `Host` header is derived from the URI if it is missing.
+    #[cfg(feature = "h1")]
+    pub async fn write_to<W>(&mut self, writer: &mut W) -> std::io::Result<()>
+    where
+        W: futures_lite::AsyncWrite + Unpin,
+    {
+        crate::h1::write_request(writer, self).await
+    }
+
     /// Consume and read request body as a chunk of bytes.
     pub async fn into_bytes(&mut self) -> Result<Bytes, BodyError> {
         self.take_body()?.into_bytes().await
@@ -280,33 +467,75 @@ impl Request {
 
     /// Prepare data in the inner representation,then try to read the body as JSON.
     /// This method allows you to deserialize data with zero copy.
-    /// Tip: This method will fail if `content-type` header is mismatched.
+    /// Tip: This method will fail if `content-type` header is not `application/json` or a `+json` media type.
     #[cfg(feature = "json")]
     pub async fn into_json<'a, T>(&'a mut self) -> Result<T, crate::Error>
     where
         T: serde::Deserialize<'a>,
     {
-        use crate::ResultExt;
+        self.into_json_with(&crate::content_type::ContentTypePolicy::json())
+            .await
+    }
 
-        assert_content_type!("application/json", self.headers());
+    /// Prepare data in the inner representation,then try to read the body as JSON.
+    /// Fail with `415 Unsupported Media Type` if `content-type` header is not accepted by `policy`.
+    #[cfg(feature = "json")]
+    pub async fn into_json_with<'a, T>(
+        &'a mut self,
+        policy: &crate::content_type::ContentTypePolicy,
+    ) -> Result<T, crate::Error>
+    where
+        T: serde::Deserialize<'a>,
+    {
+        use crate::ResultExt;
 
-        serde_json::from_slice(self.body.as_bytes().await?).status(crate::StatusCode::BAD_REQUEST)
+        policy.check(self.headers())?;
+        let data = self
+            .body
+            .as_bytes()
+            .await
+            .map_err(BodyError::into_http_error)?;
+        serde_json::from_slice(data).status(crate::StatusCode::BAD_REQUEST)
     }
 
     /// Prepare data in the inner representation,then try to read the body as a form.
     /// This method allows you to deserialize data with zero copy.
-    /// Tip: This method will fail if `content-type` header is mismatched.
+    /// Tip: This method will fail if `content-type` header is not `application/x-www-form-urlencoded`.
     #[cfg(feature = "form")]
     pub async fn into_form<'a, T>(&'a mut self) -> Result<T, crate::Error>
+    where
+        T: serde::Deserialize<'a>,
+    {
+        self.into_form_with(&crate::content_type::ContentTypePolicy::form())
+            .await
+    }
+
+    /// Prepare data in the inner representation,then try to read the body as a form.
+    /// Fail with `415 Unsupported Media Type` if `content-type` header is not accepted by `policy`.
+    #[cfg(feature = "form")]
+    pub async fn into_form_with<'a, T>(
+        &'a mut self,
+        policy: &crate::content_type::ContentTypePolicy,
+    ) -> Result<T, crate::Error>
     where
         T: serde::Deserialize<'a>,
     {
         use crate::ResultExt;
 
-        assert_content_type!("application/x-www-form-urlencoded", self.headers());
+        policy.check(self.headers())?;
+        let data = self
+            .body
+            .as_bytes()
+            .await
+            .map_err(BodyError::into_http_error)?;
+        serde_urlencoded::from_bytes(data).status(crate::StatusCode::BAD_REQUEST)
+    }
 
-        serde_urlencoded::from_bytes(self.body.as_bytes().await?)
-            .status(crate::StatusCode::BAD_REQUEST)
+    /// Take the body and read it as `multipart/form-data` with the boundary in `Content-Type`.
+    /// Tip: This method will fail if `content-type` header is mismatched.
+    pub fn into_multipart(&mut self) -> Result<crate::multipart::Multipart, crate::Error> {
+        let body = self.take_body()?;
+        crate::multipart::Multipart::from_headers(self.headers(), body)
     }
     /// Set the MIME.
     #[cfg(feature = "mime")]
@@ -321,11 +550,139 @@ impl Request {
     /// Try to parse the header and return a `Mime` instance.
     #[cfg(feature = "mime")]
     pub fn get_mime(&self) -> Option<mime::Mime> {
-        Some(
-            std::str::from_utf8(self.get_header(http::header::CONTENT_TYPE)?.as_bytes())
-                .ok()?
-                .parse()
-                .ok()?,
-        )
+        std::str::from_utf8(self.get_header(http::header::CONTENT_TYPE)?.as_bytes())
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// Choose the best of `available` media types by the `Accept` header,
+    /// fail with `406 Not Acceptable` if none of them is acceptable.
+    ///
+    /// # Example
+    /// ```rust
+    /// use http_kit::{header, Request};
+    ///
+    /// let request = Request::get("/").header(header::ACCEPT, "application/json, */*;q=0.1");
+    /// let mime = request.negotiate(&["text/plain", "application/json"]).unwrap();
+    /// assert_eq!(mime, "application/json");
+    /// ```
+    pub fn negotiate<'a>(&self, available: &[&'a str]) -> Result<&'a str, crate::Error> {
+        crate::negotiate::media_type(self.headers(), available)
+            .ok_or_else(crate::negotiate::not_acceptable)
+    }
+
+    /// Choose the best of `available` languages by the `Accept-Language` header,
+    /// fail with `406 Not Acceptable` if none of them is acceptable.
+    pub fn negotiate_language<'a>(&self, available: &[&'a str]) -> Result<&'a str, crate::Error> {
+        crate::negotiate::language(self.headers(), available)
+            .ok_or_else(crate::negotiate::not_acceptable)
+    }
+
+    /// Choose the best of `available` charsets by the `Accept-Charset` header,
+    /// fail with `406 Not Acceptable` if none of them is acceptable.
+    pub fn negotiate_charset<'a>(&self, available: &[&'a str]) -> Result<&'a str, crate::Error> {
+        crate::negotiate::charset(self.headers(), available)
+            .ok_or_else(crate::negotiate::not_acceptable)
+    }
+
+    /// Choose the best of `available` content codings by the `Accept-Encoding` header,
+    /// fail with `406 Not Acceptable` if none of them is acceptable.
+    pub fn negotiate_encoding<'a>(&self, available: &[&'a str]) -> Result<&'a str, crate::Error> {
+        crate::negotiate::encoding(self.headers(), available)
+            .ok_or_else(crate::negotiate::not_acceptable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header;
+
+    use crate::Request;
+
+    fn finalize(values: &[&str]) -> Result<(), crate::Error> {
+        let mut request = Request::post("/");
+        for value in values {
+            request.append_header(header::TRANSFER_ENCODING, value.parse().unwrap());
+        }
+        request.finalize_headers()
+    }
+
+    #[test]
+    fn finalize_inspects_every_transfer_encoding() {
+        for values in [&["chunked"][..], &["gzip", "chunked"], &["gzip, chunked"]] {
+            assert!(finalize(values).is_ok(), "{values:?}");
+        }
+        for values in [
+            &["chunked", "gzip"][..],
+            &["chunked", "chunked"],
+            &["chunked, gzip"],
+            &[""],
+        ] {
+            assert_eq!(finalize(values).unwrap_err().status(), 400, "{values:?}");
+        }
+    }
+
+    #[cfg(feature = "form")]
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Search {
+        q: String,
+        #[serde(default)]
+        tag: Vec<String>,
+        page: Option<u32>,
+    }
+
+    #[cfg(feature = "form")]
+    #[test]
+    fn query_repeated_keys() {
+        let request = Request::get("/search?q=rust&tag=a&tag=b+c&tag=%26");
+        let search: Search = request.query().unwrap();
+        assert_eq!(search.tag, ["a", "b c", "&"]);
+        assert_eq!(search.page, None);
+
+        let request = Request::get("/search?tag=a&q=rust&page=2");
+        let search: Search = request.query().unwrap();
+        assert_eq!((search.tag, search.page), (vec!["a".to_owned()], Some(2)));
+
+        let request = Request::get("/search?q=rust");
+        assert!(request.query::<Search>().unwrap().tag.is_empty());
+    }
+
+    #[cfg(feature = "form")]
+    #[test]
+    fn query_round_trip() {
+        let search = Search {
+            q: "a&b=c é".to_owned(),
+            tag: vec!["x".to_owned(), "y z".to_owned()],
+            page: Some(3),
+        };
+        let mut request = Request::get("http://example.com/search?old=1");
+        request.set_query(&search).unwrap();
+        assert_eq!(request.uri().host(), Some("example.com"));
+        assert_eq!(request.uri().path(), "/search");
+        assert_eq!(request.query::<Search>().unwrap(), search);
+
+        // An empty query removes the query string.
+        let mut request = Request::get("/search?old=1");
+        request.set_query(&Vec::<(String, String)>::new()).unwrap();
+        assert_eq!(request.uri(), "/search");
+        request.set_query(&[("page", 1)]).unwrap();
+        assert_eq!(request.uri(), "/search?page=1");
+    }
+
+    #[cfg(feature = "form")]
+    #[test]
+    fn query_errors() {
+        for uri in [
+            "/search",
+            "/search?q=rust&page=two",
+            "/search?q=rust&page=-1",
+        ] {
+            let error = Request::get(uri).query::<Search>().unwrap_err();
+            assert_eq!(error.status(), 400, "{uri}");
+        }
+        // Only maps and sequences of pairs can be serialized into a query string.
+        let error = Request::get("/").set_query(&5).unwrap_err();
+        assert_eq!(error.status(), 500);
     }
 }
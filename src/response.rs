@@ -43,6 +43,118 @@ macro_rules! impl_response_from {
 
 impl_response_from![ByteStr, String, Vec<u8>, Bytes, &str, &[u8]];
 
+/// Types that can be converted into a response, which allows handlers to return them directly.
+pub trait IntoResponse {
+    /// Convert `self` into a response.
+    fn into_response(self) -> Response;
+
+    /// Convert `self` into a result.
+    /// Errors are kept as `Err`, so that middlewares can still observe them.
+    fn into_result(self) -> crate::Result<Response>
+    where
+        Self: Sized,
+    {
+        Ok(self.into_response())
+    }
+}
+
+macro_rules! impl_into_response {
+    ($($ty:ty),*) => {
+        $(
+            impl IntoResponse for $ty {
+                fn into_response(self) -> Response {
+                    self.into()
+                }
+            }
+        )*
+    };
+}
+
+impl_into_response![ByteStr, String, Vec<u8>, Bytes, &'static str, &'static [u8]];
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+impl IntoResponse for Body {
+    fn into_response(self) -> Response {
+        Response::new(StatusCode::OK, self)
+    }
+}
+
+impl IntoResponse for () {
+    fn into_response(self) -> Response {
+        Response::empty()
+    }
+}
+
+impl IntoResponse for StatusCode {
+    fn into_response(self) -> Response {
+        Response::new(self, Body::empty())
+    }
+}
+
+/// The message of a server error may expose internals such as paths or queries,
+/// so only the reason phrase is sent. The error is kept as an extension of the response,
+/// and with the `log` feature, it is also logged at the error level.
+impl IntoResponse for crate::Error {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            #[cfg(feature = "log")]
+            log::error!("{status}: {self:#}");
+            let mut response = Response::new(status, status.canonical_reason().unwrap_or_default());
+            response.insert_extension(self);
+            return response;
+        }
+        Response::new(status, self.to_string())
+    }
+
+    fn into_result(self) -> crate::Result<Response> {
+        Err(self)
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for (StatusCode, T) {
+    fn into_response(self) -> Response {
+        let (status, value) = self;
+        let mut response = value.into_response();
+        response.set_status(status);
+        response
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for (HeaderMap, T) {
+    fn into_response(self) -> Response {
+        let (headers, value) = self;
+        let mut response = value.into_response();
+        response.headers_mut().extend(headers);
+        response
+    }
+}
+
+impl<T, E> IntoResponse for Result<T, E>
+where
+    T: IntoResponse,
+    E: Into<crate::Error>,
+{
+    fn into_response(self) -> Response {
+        match self {
+            Ok(value) => value.into_response(),
+            Err(error) => error.into().into_response(),
+        }
+    }
+
+    fn into_result(self) -> crate::Result<Response> {
+        match self {
+            Ok(value) => value.into_result(),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
 impl Response {
     /// Create a new `Response` with a body.
     pub fn new<S>(status: S, body: impl Into<Body>) -> Self
@@ -65,7 +177,6 @@ impl Response {
         self.parts.status
     }
     /// Return the mutable reference of status code.
-
     pub fn status_mut(&mut self) -> &mut StatusCode {
         &mut self.parts.status
     }
@@ -74,42 +185,34 @@ impl Response {
         *self.status_mut() = status;
     }
     /// Return the HTTP version.
-
     pub const fn version(&self) -> Version {
         self.parts.version
     }
     /// Return the mutable reference of HTTP version.
-
     pub fn version_mut(&mut self) -> &mut Version {
         &mut self.parts.version
     }
     /// Set the HTTP version by `version`
-
     pub fn set_version(&mut self, version: Version) {
         *self.version_mut() = version;
     }
     /// Return the reference of the HTTP header.
-
     pub const fn headers(&self) -> &HeaderMap {
         &self.parts.headers
     }
     /// Return the mutable reference of the HTTP header.
-
     pub fn headers_mut(&mut self) -> &mut HeaderMap {
         &mut self.parts.headers
     }
     /// Acquire the first value of header by header name.
-
     pub fn get_header(&self, name: HeaderName) -> Option<&HeaderValue> {
         self.headers().get(name)
     }
     /// Append a header,the previous header (if exists) wouldn't be removed.
-
     pub fn append_header(&mut self, name: HeaderName, value: HeaderValue) {
         self.headers_mut().append(name, value);
     }
     /// Insert a header,if the header already exists,the previous header will be removed.
-
     pub fn insert_header(&mut self, name: HeaderName, value: HeaderValue) -> Option<HeaderValue> {
         self.headers_mut().insert(name, value)
     }
@@ -124,18 +227,69 @@ impl Response {
         self
     }
 
-    /// Return the reference of the extension.
+    /// Decode the typed header `H`, return `None` if it is missing,
+    /// fail with `400 Bad Request` if it is malformed.
+    pub fn typed_header<H: crate::typed_header::TypedHeader>(
+        &self,
+    ) -> Result<Option<H>, crate::Error> {
+        crate::typed_header::decode(self.headers())
+    }
+
+    /// Encode the typed header `H`, the previous values of the header will be removed.
+    pub fn set_typed_header<H: crate::typed_header::TypedHeader>(&mut self, header: H) {
+        self.insert_header(H::name(), header.encode());
+    }
+
+    /// Append a `Set-Cookie` header for `cookie`,
+    /// fail if the domain or the path of `cookie` contains characters not allowed in a header.
+    #[cfg(feature = "cookie")]
+    pub fn set_cookie<'c>(
+        &mut self,
+        cookie: impl Into<crate::cookie::Cookie<'c>>,
+    ) -> Result<(), http::header::InvalidHeaderValue> {
+        let value = HeaderValue::from_str(&cookie.into().encoded().to_string())?;
+        self.append_header(http::header::SET_COOKIE, value);
+        Ok(())
+    }
+
+    /// Append `Set-Cookie` headers for the cookies added to or removed from `jar`,
+    /// fail without appending any if the domain or the path of a cookie contains characters not allowed in a header.
+    #[cfg(feature = "cookie")]
+    pub fn set_cookies(
+        &mut self,
+        jar: &crate::cookie::CookieJar,
+    ) -> Result<(), http::header::InvalidHeaderValue> {
+        let values = jar
+            .delta()
+            .map(|cookie| HeaderValue::from_str(&cookie.encoded().to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        for value in values {
+            self.append_header(http::header::SET_COOKIE, value);
+        }
+        Ok(())
+    }
+
+    /// Append a `Set-Cookie` header removing the cookie from the client.
+    /// The domain and the path of `cookie` should match the cookie to remove.
+    #[cfg(feature = "cookie")]
+    pub fn remove_cookie<'c>(
+        &mut self,
+        cookie: impl Into<crate::cookie::Cookie<'c>>,
+    ) -> Result<(), http::header::InvalidHeaderValue> {
+        let mut cookie = cookie.into();
+        cookie.make_removal();
+        self.set_cookie(cookie)
+    }
 
+    /// Return the reference of the extension.
     pub const fn extensions(&self) -> &Extensions {
         &self.parts.extensions
     }
     /// Return the mutable reference of the extension.
-
     pub fn extensions_mut(&mut self) -> &mut Extensions {
         &mut self.parts.extensions
     }
     /// Returns a refernece of associated extension.
-
     pub fn get_extension<T: Send + Sync + 'static>(&self) -> Option<&T> {
         self.extensions().get()
     }
@@ -145,12 +299,10 @@ impl Response {
         self.extensions_mut().get_mut()
     }
     /// Remove a type from extensions.
-
     pub fn remove_extension<T: Send + Sync + 'static>(&mut self) -> Option<T> {
         self.extensions_mut().remove()
     }
     /// Insert a type into extensions,if the type already exists,the old value will be returned.
-
     pub fn insert_extension<T: Send + Sync + 'static>(&mut self, extension: T) -> Option<T> {
         self.extensions_mut().insert(extension)
     }
@@ -160,7 +312,6 @@ impl Response {
         self.body.take()
     }
     /// Replace the value of the response body and return the old body.
-
     pub fn replace_body(&mut self, body: impl Into<Body>) -> Body {
         self.body.replace(body.into())
     }
@@ -170,6 +321,33 @@ impl Response {
         self.body.swap(body)
     }
 
+    /// Set or validate the framing headers according to the body.
+    ///
+    /// `Transfer-Encoding` takes precedence and removes `Content-Length`.
+    /// Otherwise `Content-Length` is set to the length of the body, or validated against it.
+    /// A declared length is enforced while reading a body of unknown length,
+    /// and `Transfer-Encoding: chunked` is set for HTTP/1.1 if the length is unknown or trailers are attached.
+    /// Responses without a body, which are informational or `204 No Content`, have both headers removed,
+    /// and `304 Not Modified` responses are left untouched.
+    ///
+    /// Fail with `500 Internal Server Error` if the headers are invalid or do not match the body.
+    pub fn finalize_headers(&mut self) -> Result<(), crate::Error> {
+        let status = self.status();
+        if status.is_informational() || status == StatusCode::NO_CONTENT {
+            let headers = self.headers_mut();
+            headers.remove(http::header::CONTENT_LENGTH);
+            headers.remove(http::header::TRANSFER_ENCODING);
+            return Ok(());
+        }
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(());
+        }
+        let version = self.version();
+        self.body
+            .finalize_headers(&mut self.parts.headers, version, None)
+            .map_err(|error| crate::Error::new(error, StatusCode::INTERNAL_SERVER_ERROR))
+    }
+
     /// Map the body to a different value.
     pub fn map_body<F>(mut self, f: F) -> Self
     where
@@ -179,6 +357,57 @@ impl Response {
         self
     }
 
+    /// Try to clone the response, which is only possible if the body is in memory.
+    /// Extensions are not cloned.
+    ///
+    /// A streaming body can be prepared for cloning by [`Body::into_replayable`].
+    pub fn try_clone(&self) -> Option<Self> {
+        let body = self.body.try_clone()?;
+        let mut response = http::Response::new(body);
+        *response.status_mut() = self.status();
+        *response.version_mut() = self.version();
+        *response.headers_mut() = self.headers().clone();
+        Some(response.into())
+    }
+
+    /// Set the body to the representation chosen from `available` media types by the `Accept` header of `request`.
+    ///
+    /// `body` produces the body of the chosen media type, which is set as `Content-Type`, and `accept` is added to `Vary`.
+    /// Fail with `406 Not Acceptable` if none of them is acceptable.
+    ///
+    /// # Example
+    /// ```rust
+    /// use http_kit::{header, Body, Request, Response};
+    ///
+    /// let request = Request::get("/").header(header::ACCEPT, "text/plain");
+    /// let response = Response::empty()
+    ///     .negotiated(&request, &["application/json", "text/plain"], |media_type| {
+    ///         Ok(match media_type {
+    ///             "application/json" => Body::from("{\"name\":\"alice\"}"),
+    ///             _ => Body::from("alice"),
+    ///         })
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(response.headers()[header::CONTENT_TYPE], "text/plain");
+    /// assert_eq!(response.headers()[header::VARY], "accept");
+    /// ```
+    pub fn negotiated<'a>(
+        mut self,
+        request: &crate::Request,
+        available: &[&'a str],
+        body: impl FnOnce(&'a str) -> Result<Body, crate::Error>,
+    ) -> Result<Self, crate::Error> {
+        use crate::ResultExt;
+
+        let media_type = request.negotiate(available)?;
+        let content_type =
+            HeaderValue::from_str(media_type).status(StatusCode::INTERNAL_SERVER_ERROR)?;
+        self.replace_body(body(media_type)?);
+        self.insert_header(http::header::CONTENT_TYPE, content_type);
+        crate::negotiate::append_vary(self.headers_mut(), "accept");
+        Ok(self)
+    }
+
     /// Set the body from a JSON.
     /// This method will set `Content-type` header automatically.
     #[cfg(feature = "json")]
@@ -224,6 +453,23 @@ impl Response {
         Ok(self)
     }
 
+    /// Serialize the response as an HTTP/1.1 message into `writer`, the body will be taken.
+    #[cfg(feature = "h1")]
+    pub async fn write_to<W>(&mut self, writer: &mut W) -> std::io::Result<()>
+    where
+        W: futures_lite::AsyncWrite + Unpin,
+    {
+        crate::h1::write_response(
+            writer,
+            self,
+            &http::Method::GET,
+            http::Version::HTTP_11,
+            true,
+        )
+        .await?;
+        Ok(())
+    }
+
     /// Consume and read response body as a chunk of bytes.
     pub async fn into_bytes(&mut self) -> Result<Bytes, BodyError> {
         self.take_body()?.into_bytes().await
@@ -236,31 +482,68 @@ impl Response {
 
     /// Prepare data in the inner representation,then try to read the body as JSON.
     /// This method allows you to deserialize data with zero copy.
-    /// Tip: This method will fail if `content-type` header is mismatched.
+    /// Tip: This method will fail if `content-type` header is not `application/json` or a `+json` media type.
     #[cfg(feature = "json")]
     pub async fn into_json<'a, T>(&'a mut self) -> Result<T, crate::Error>
+    where
+        T: serde::Deserialize<'a>,
+    {
+        self.into_json_with(&crate::content_type::ContentTypePolicy::json())
+            .await
+    }
+
+    /// Prepare data in the inner representation,then try to read the body as JSON.
+    /// Fail with `415 Unsupported Media Type` if `content-type` header is not accepted by `policy`.
+    #[cfg(feature = "json")]
+    pub async fn into_json_with<'a, T>(
+        &'a mut self,
+        policy: &crate::content_type::ContentTypePolicy,
+    ) -> Result<T, crate::Error>
     where
         T: serde::Deserialize<'a>,
     {
         use crate::ResultExt;
 
-        assert_content_type!("application/json", self.headers());
-        serde_json::from_slice(self.body.as_bytes().await?).status(crate::StatusCode::BAD_REQUEST)
+        policy.check(self.headers())?;
+        let data = self
+            .body
+            .as_bytes()
+            .await
+            .map_err(BodyError::into_http_error)?;
+        serde_json::from_slice(data).status(crate::StatusCode::BAD_REQUEST)
     }
 
     /// Prepare data in the inner representation,then try to read the body as a form.
     /// This method allows you to deserialize data with zero copy.
-    /// Tip: This method will fail if `content-type` header is mismatched.
+    /// Tip: This method will fail if `content-type` header is not `application/x-www-form-urlencoded`.
     #[cfg(feature = "form")]
     pub async fn into_form<'a, T>(&'a mut self) -> Result<T, crate::Error>
+    where
+        T: serde::Deserialize<'a>,
+    {
+        self.into_form_with(&crate::content_type::ContentTypePolicy::form())
+            .await
+    }
+
+    /// Prepare data in the inner representation,then try to read the body as a form.
+    /// Fail with `415 Unsupported Media Type` if `content-type` header is not accepted by `policy`.
+    #[cfg(feature = "form")]
+    pub async fn into_form_with<'a, T>(
+        &'a mut self,
+        policy: &crate::content_type::ContentTypePolicy,
+    ) -> Result<T, crate::Error>
     where
         T: serde::Deserialize<'a>,
     {
         use crate::ResultExt;
 
-        assert_content_type!("application/x-www-form-urlencoded", self.headers());
-        serde_urlencoded::from_bytes(self.body.as_bytes().await?)
-            .status(crate::StatusCode::BAD_REQUEST)
+        policy.check(self.headers())?;
+        let data = self
+            .body
+            .as_bytes()
+            .await
+            .map_err(BodyError::into_http_error)?;
+        serde_urlencoded::from_bytes(data).status(crate::StatusCode::BAD_REQUEST)
     }
 
     /// Set the MIME.
@@ -276,11 +559,81 @@ impl Response {
     /// Try to parse the header and return a `Mime` instance.
     #[cfg(feature = "mime")]
     pub fn get_mime(&self) -> Option<mime::Mime> {
-        Some(
-            std::str::from_utf8(self.get_header(http::header::CONTENT_TYPE)?.as_bytes())
-                .ok()?
-                .parse()
-                .ok()?,
-        )
+        std::str::from_utf8(self.get_header(http::header::CONTENT_TYPE)?.as_bytes())
+            .ok()?
+            .parse()
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header, StatusCode};
+
+    use super::{IntoResponse, Response};
+    use crate::{Body, Request};
+
+    #[test]
+    fn server_error_hides_message() {
+        let error = crate::Error::msg("/srv/data/users.db: permission denied");
+        let mut response = error.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = futures_lite::future::block_on(response.into_string()).unwrap();
+        assert_eq!(body, "Service Unavailable");
+        let error = response.get_extension::<crate::Error>().unwrap();
+        assert_eq!(error.to_string(), "/srv/data/users.db: permission denied");
+    }
+
+    #[test]
+    fn client_error_keeps_message() {
+        let error = crate::Error::msg("missing field `name`").set_status(StatusCode::BAD_REQUEST);
+        let mut response = error.into_response();
+        let body = futures_lite::future::block_on(response.into_string()).unwrap();
+        assert_eq!(body, "missing field `name`");
+    }
+
+    #[test]
+    fn negotiated() {
+        let request = Request::get("/").header(header::ACCEPT, "application/json, */*;q=0.1");
+        let mut response = Response::empty()
+            .header(header::VARY, "Accept")
+            .negotiated(
+                &request,
+                &["text/plain", "application/json"],
+                |media_type| Ok(Body::from(media_type)),
+            )
+            .unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(response.headers().get_all(header::VARY).iter().count(), 1);
+        let body = futures_lite::future::block_on(response.into_string()).unwrap();
+        assert_eq!(body, "application/json");
+    }
+
+    #[test]
+    fn not_acceptable() {
+        let request = Request::get("/").header(header::ACCEPT, "image/png");
+        let error = Response::empty()
+            .negotiated(&request, &["text/plain"], |_| unreachable!())
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+
+    #[cfg(feature = "cookie")]
+    #[test]
+    fn invalid_cookie_is_rejected() {
+        use crate::cookie::{Cookie, CookieJar};
+
+        let mut response = Response::empty();
+        assert!(response
+            .set_cookie(Cookie::build(("a", "1")).path("/\n"))
+            .is_err());
+        let mut jar = CookieJar::new();
+        jar.add(("b", "2"));
+        jar.add(Cookie::build(("c", "3")).domain("\0"));
+        assert!(response.set_cookies(&jar).is_err());
+        assert!(response.headers().get(header::SET_COOKIE).is_none());
+
+        response.set_cookie(("a", "1")).unwrap();
+        assert_eq!(response.headers()[header::SET_COOKIE], "a=1");
     }
 }
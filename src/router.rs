@@ -0,0 +1,577 @@
+//! Router dispatches requests to endpoints by method and path pattern.
+//!
+//! A pattern is made of segments separated by `/`. A segment can be static (`users`),
+//! a named parameter (`:id`) matching exactly one segment, or a wildcard (`*rest`) matching the remaining path.
+//! Captured values are stored in request extensions as [`Params`].
+//! Parameters are percent-decoded, while wildcards keep the remaining path encoded,
+//! so that an encoded `/` (`%2F`) is never taken as a separator.
+//!
+//! # Example
+//! ```rust
+//! use async_trait::async_trait;
+//! use http_kit::{router::Params, Endpoint, Request, Response, Router};
+//!
+//! struct User;
+//!
+//! #[async_trait]
+//! impl Endpoint for User {
+//!     async fn call_endpoint(&self, request: &mut Request) -> http_kit::Result<Response> {
+//!         let id = request.get_extension::<Params>().unwrap().get("id").unwrap();
+//!         Ok(Response::from(format!("user {id}")))
+//!     }
+//! }
+//!
+//! let api = Router::new().get("/users/:id", User);
+//! let router = Router::new().nest("/api", api);
+//!
+//! # async_std::task::block_on(async {
+//! let mut request = Request::get("/api/users/42");
+//! let mut response = router.call_endpoint(&mut request).await?;
+//! assert_eq!(response.into_string().await?, "user 42");
+//! # http_kit::Result::Ok(())
+//! # }).unwrap();
+//! ```
+
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use async_trait::async_trait;
+use http::{header, HeaderValue, Method, StatusCode};
+
+use crate::{Body, Endpoint, Request, Response, Result};
+
+/// Parameters captured from the request path.
+#[derive(Debug, Clone, Default)]
+pub struct Params {
+    params: Vec<(Arc<str>, String)>,
+}
+
+impl Params {
+    /// Return the value of the parameter named `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.as_ref() == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Return an iterator over all captured parameters in pattern order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|(key, value)| (key.as_ref(), value.as_str()))
+    }
+
+    /// Return the number of captured parameters.
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Returns true if no parameter was captured.
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Static(Box<str>),
+    Param(Arc<str>),
+    Wildcard(Arc<str>),
+}
+
+impl Segment {
+    // Lower rank is more specific.
+    const fn rank(&self) -> u8 {
+        match self {
+            Segment::Static(_) => 0,
+            Segment::Param(_) => 1,
+            Segment::Wildcard(_) => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Self {
+        let segments: Vec<Segment> = split_path(pattern)
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix(':') {
+                    Segment::Param(name.into())
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    Segment::Wildcard(name.into())
+                } else {
+                    Segment::Static(segment.into())
+                }
+            })
+            .collect();
+
+        if let Some(position) = segments
+            .iter()
+            .position(|segment| matches!(segment, Segment::Wildcard(_)))
+        {
+            assert!(
+                position + 1 == segments.len(),
+                "Wildcard segment must be the last segment of pattern `{pattern}`"
+            );
+        }
+
+        Self { segments }
+    }
+
+    fn join(&self, other: &Pattern) -> Self {
+        assert!(
+            !matches!(self.segments.last(), Some(Segment::Wildcard(_))),
+            "Can not nest a router under a wildcard pattern"
+        );
+        let mut segments = self.segments.clone();
+        segments.extend_from_slice(&other.segments);
+        Self { segments }
+    }
+
+    fn matches(&self, path: &str) -> Option<Params> {
+        let mut params = Vec::new();
+        let mut remain = path.trim_start_matches('/');
+        for segment in &self.segments {
+            if let Segment::Wildcard(name) = segment {
+                params.push((name.clone(), remain.trim_end_matches('/').to_owned()));
+                return Some(Params { params });
+            }
+            let (current, rest) = next_segment(remain)?;
+            match segment {
+                Segment::Static(expected) => {
+                    if current != expected.as_ref() {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => params.push((name.clone(), percent_decode(current))),
+                Segment::Wildcard(_) => unreachable!(),
+            }
+            remain = rest;
+        }
+
+        if next_segment(remain).is_some() {
+            None
+        } else {
+            Some(Params { params })
+        }
+    }
+
+    fn ranks(&self) -> impl Iterator<Item = u8> + '_ {
+        self.segments.iter().map(Segment::rank)
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+// Return the next non-empty segment and the remaining path.
+fn next_segment(path: &str) -> Option<(&str, &str)> {
+    let path = path.trim_start_matches('/');
+    if path.is_empty() {
+        return None;
+    }
+    Some(path.split_once('/').unwrap_or((path, "")))
+}
+
+pub(crate) fn percent_decode(input: &str) -> String {
+    fn hex(byte: u8) -> Option<u8> {
+        match byte {
+            b'0'..=b'9' => Some(byte - b'0'),
+            b'a'..=b'f' => Some(byte - b'a' + 10),
+            b'A'..=b'F' => Some(byte - b'A' + 10),
+            _ => None,
+        }
+    }
+
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            if let (Some(high), Some(low)) = (
+                bytes.get(index + 1).copied().and_then(hex),
+                bytes.get(index + 2).copied().and_then(hex),
+            ) {
+                decoded.push(high << 4 | low);
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| input.to_owned())
+}
+
+type SharedEndpoint = Arc<dyn Endpoint>;
+
+struct Route {
+    pattern: Pattern,
+    endpoints: HashMap<Method, SharedEndpoint>,
+}
+
+impl Debug for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Route")
+            .field("pattern", &self.pattern)
+            .field("methods", &self.endpoints.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// An endpoint dispatching requests to other endpoints by method and path pattern.
+///
+/// If no pattern matches the path, a `404 Not Found` response is returned.
+/// If a pattern matches but no endpoint is registered for the method,
+/// a `405 Method Not Allowed` response with an `Allow` header is returned.
+/// `HEAD` requests fall back to the `GET` endpoint if no `HEAD` endpoint is registered.
+#[derive(Debug, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+macro_rules! impl_router_method {
+    ($(($name:ident,$method:ident)),*) => {
+        $(
+            #[doc = concat!("Register an endpoint handling `", stringify!($method), "` requests on `pattern`.")]
+            pub fn $name(self, pattern: &str, endpoint: impl Endpoint + 'static) -> Self {
+                self.route(Method::$method, pattern, endpoint)
+            }
+        )*
+    };
+}
+
+impl Router {
+    /// Create an empty router.
+    pub const fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    /// Register a shared endpoint handling `method` requests on `pattern`.
+    /// # Panics
+    /// Panic if a wildcard segment is not the last segment of `pattern`.
+    pub fn add_route(&mut self, method: Method, pattern: &str, endpoint: Arc<dyn Endpoint>) {
+        self.insert(method, Pattern::parse(pattern), endpoint);
+    }
+
+    /// Register an endpoint handling `method` requests on `pattern`.
+    /// # Panics
+    /// Panic if a wildcard segment is not the last segment of `pattern`.
+    pub fn route(
+        mut self,
+        method: Method,
+        pattern: &str,
+        endpoint: impl Endpoint + 'static,
+    ) -> Self {
+        self.add_route(method, pattern, Arc::new(endpoint));
+        self
+    }
+
+    impl_router_method![
+        (get, GET),
+        (post, POST),
+        (put, PUT),
+        (delete, DELETE),
+        (patch, PATCH),
+        (head, HEAD),
+        (options, OPTIONS)
+    ];
+
+    /// Mount all routes of `router` under `prefix`.
+    /// Parameters captured by `prefix` are visible to the endpoints of the nested router.
+    /// # Panics
+    /// Panic if `prefix` ends with a wildcard segment.
+    pub fn nest(mut self, prefix: &str, router: Router) -> Self {
+        let prefix = Pattern::parse(prefix);
+        for route in router.routes {
+            let pattern = prefix.join(&route.pattern);
+            for (method, endpoint) in route.endpoints {
+                self.insert(method, pattern.clone(), endpoint);
+            }
+        }
+        self
+    }
+
+    fn insert(&mut self, method: Method, pattern: Pattern, endpoint: SharedEndpoint) {
+        if let Some(route) = self
+            .routes
+            .iter_mut()
+            .find(|route| route.pattern == pattern)
+        {
+            route.endpoints.insert(method, endpoint);
+        } else {
+            self.routes.push(Route {
+                pattern,
+                endpoints: HashMap::from([(method, endpoint)]),
+            });
+        }
+    }
+
+    // Return all routes matching the path, the most specific route comes first.
+    fn matches(&self, path: &str) -> Vec<(&Route, Params)> {
+        let mut matches: Vec<_> = self
+            .routes
+            .iter()
+            .filter_map(|route| Some((route, route.pattern.matches(path)?)))
+            .collect();
+        matches.sort_by(|(a, _), (b, _)| a.pattern.ranks().cmp(b.pattern.ranks()));
+        matches
+    }
+}
+
+impl Route {
+    fn endpoint(&self, method: &Method) -> Option<&SharedEndpoint> {
+        self.endpoints.get(method).or_else(|| {
+            (method == Method::HEAD)
+                .then(|| self.endpoints.get(&Method::GET))
+                .flatten()
+        })
+    }
+}
+
+fn allow_header<'a>(routes: impl Iterator<Item = &'a Route>) -> HeaderValue {
+    let mut methods = Vec::new();
+    for route in routes {
+        methods.extend(route.endpoints.keys().map(Method::as_str));
+        if route.endpoints.contains_key(&Method::GET) {
+            methods.push(Method::HEAD.as_str());
+        }
+    }
+    methods.sort_unstable();
+    methods.dedup();
+    HeaderValue::from_str(&methods.join(", ")).expect("Method names are valid header values")
+}
+
+#[async_trait]
+impl Endpoint for Router {
+    async fn call_endpoint(&self, request: &mut Request) -> Result<Response> {
+        let matches = self.matches(request.uri().path());
+        if matches.is_empty() {
+            return Ok(Response::new(StatusCode::NOT_FOUND, Body::empty()));
+        }
+
+        let found = matches
+            .iter()
+            .find_map(|(route, params)| Some((route.endpoint(request.method())?, params)));
+
+        if let Some((endpoint, params)) = found {
+            if let Some(captured) = request.get_mut_extension::<Params>() {
+                captured.params.extend(params.params.iter().cloned());
+            } else {
+                request.insert_extension(params.clone());
+            }
+            endpoint.call_endpoint(request).await
+        } else {
+            Ok(
+                Response::new(StatusCode::METHOD_NOT_ALLOWED, Body::empty()).header(
+                    header::ALLOW,
+                    allow_header(matches.iter().map(|(route, _)| *route)),
+                ),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use futures_lite::future::block_on;
+    use http::{header, Method, StatusCode};
+
+    use super::{Params, Pattern, Router};
+    use crate::{Endpoint, Request, Response, Result};
+
+    #[test]
+    fn param_is_decoded() {
+        let params = Pattern::parse("/users/:name")
+            .matches("/users/a%20b")
+            .unwrap();
+        assert_eq!(params.get("name"), Some("a b"));
+    }
+
+    #[test]
+    fn wildcard_keeps_encoded_separator() {
+        let params = Pattern::parse("/files/*path")
+            .matches("/files/a%2Fb/c.txt/")
+            .unwrap();
+        assert_eq!(params.get("path"), Some("a%2Fb/c.txt"));
+    }
+
+    #[test]
+    fn empty_wildcard() {
+        let params = Pattern::parse("/files/*path").matches("/files").unwrap();
+        assert_eq!(params.get("path"), Some(""));
+    }
+
+    // An endpoint answering with its name and the captured parameters.
+    struct Named(&'static str);
+
+    #[async_trait]
+    impl Endpoint for Named {
+        async fn call_endpoint(&self, request: &mut Request) -> Result<Response> {
+            let mut body = self.0.to_owned();
+            if let Some(params) = request.get_extension::<Params>() {
+                for (name, value) in params.iter() {
+                    body.push_str(&format!(" {name}={value}"));
+                }
+            }
+            Ok(Response::from(body))
+        }
+    }
+
+    fn call(router: &Router, method: Method, uri: &str) -> (StatusCode, String) {
+        let mut request = Request::new(method, uri);
+        let mut response = block_on(router.call_endpoint(&mut request)).unwrap();
+        let body = block_on(response.into_string()).unwrap();
+        (response.status(), body.to_string())
+    }
+
+    fn allow(router: &Router, method: Method, uri: &str) -> Option<String> {
+        let mut request = Request::new(method, uri);
+        let response = block_on(router.call_endpoint(&mut request)).unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let allow = response.get_header(header::ALLOW)?;
+        Some(allow.to_str().unwrap().to_owned())
+    }
+
+    #[test]
+    fn method_dispatch() {
+        let router = Router::new()
+            .get("/users", Named("list"))
+            .post("/users", Named("create"))
+            .route(
+                Method::from_bytes(b"PURGE").unwrap(),
+                "/users",
+                Named("purge"),
+            );
+        assert_eq!(
+            call(&router, Method::GET, "/users"),
+            (StatusCode::OK, "list".into())
+        );
+        assert_eq!(
+            call(&router, Method::POST, "/users/"),
+            (StatusCode::OK, "create".into())
+        );
+        assert_eq!(
+            call(&router, Method::from_bytes(b"PURGE").unwrap(), "/users"),
+            (StatusCode::OK, "purge".into())
+        );
+        assert_eq!(
+            call(&router, Method::GET, "/posts").0,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            call(&router, Method::GET, "/users/1").0,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn method_not_allowed() {
+        let router = Router::new()
+            .get("/users", Named("list"))
+            .post("/users", Named("create"))
+            .put("/users/me", Named("update"))
+            .get("/users/:id", Named("show"));
+        assert_eq!(
+            allow(&router, Method::DELETE, "/users").as_deref(),
+            Some("GET, HEAD, POST")
+        );
+        // Methods of every matching route are allowed.
+        assert_eq!(
+            allow(&router, Method::DELETE, "/users/me").as_deref(),
+            Some("GET, HEAD, PUT")
+        );
+        assert_eq!(
+            allow(&router, Method::PUT, "/users/1").as_deref(),
+            Some("GET, HEAD")
+        );
+    }
+
+    #[test]
+    fn head_falls_back_to_get() {
+        let router = Router::new()
+            .get("/users", Named("list"))
+            .get("/posts", Named("posts"))
+            .head("/posts", Named("head"))
+            .post("/login", Named("login"));
+        assert_eq!(
+            call(&router, Method::HEAD, "/users"),
+            (StatusCode::OK, "list".into())
+        );
+        assert_eq!(
+            call(&router, Method::HEAD, "/posts"),
+            (StatusCode::OK, "head".into())
+        );
+        assert_eq!(
+            allow(&router, Method::HEAD, "/login").as_deref(),
+            Some("POST")
+        );
+    }
+
+    #[test]
+    fn nested_routers() {
+        let users = Router::new()
+            .get("/", Named("list"))
+            .get("/:id", Named("show"))
+            .delete("/:id", Named("delete"));
+        let router = Router::new()
+            .get("/", Named("index"))
+            .nest("/api/:version/users", users);
+        assert_eq!(
+            call(&router, Method::GET, "/"),
+            (StatusCode::OK, "index".into())
+        );
+        assert_eq!(
+            call(&router, Method::GET, "/api/v1/users"),
+            (StatusCode::OK, "list version=v1".into())
+        );
+        assert_eq!(
+            call(&router, Method::DELETE, "/api/v2/users/7"),
+            (StatusCode::OK, "delete version=v2 id=7".into())
+        );
+        assert_eq!(
+            allow(&router, Method::POST, "/api/v1/users/7").as_deref(),
+            Some("DELETE, GET, HEAD")
+        );
+    }
+
+    #[test]
+    fn specificity() {
+        // Registered from the least to the most specific.
+        let router = Router::new()
+            .get("/files/*path", Named("wildcard"))
+            .get("/files/:name", Named("param"))
+            .get("/files/index", Named("static"))
+            .post("/files/upload", Named("upload"));
+        assert_eq!(call(&router, Method::GET, "/files/index").1, "static");
+        assert_eq!(
+            call(&router, Method::GET, "/files/a.txt").1,
+            "param name=a.txt"
+        );
+        assert_eq!(
+            call(&router, Method::GET, "/files/a/b.txt").1,
+            "wildcard path=a/b.txt"
+        );
+        // A less specific route is used if the most specific one does not handle the method.
+        assert_eq!(
+            call(&router, Method::GET, "/files/upload").1,
+            "param name=upload"
+        );
+        assert_eq!(call(&router, Method::POST, "/files/upload").1, "upload");
+
+        let router = Router::new()
+            .get("/:a/static", Named("second"))
+            .get("/static/:b", Named("first"));
+        assert_eq!(
+            call(&router, Method::GET, "/static/static").1,
+            "first b=static"
+        );
+    }
+}
@@ -0,0 +1,688 @@
+//! Serving static files from a directory.
+//!
+//! [`ServeDir`] maps the request path to a file under its root, and supports conditional requests
+//! by `If-None-Match` and `If-Modified-Since`, byte ranges and precompressed files.
+//!
+//! # Example
+//! ```rust
+//! use http_kit::{serve_dir::ServeDir, Router};
+//!
+//! let router = Router::new().get("/assets/*path", ServeDir::new("assets").param("path").precompressed());
+//! ```
+
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    fmt::Write,
+    io::{self, SeekFrom},
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_fs::File;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_lite::{stream, AsyncReadExt, AsyncSeekExt};
+use http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+
+use crate::{
+    router::Params,
+    typed_header::{
+        ContentRange, ETag, EntityTag, IfModifiedSince, IfNoneMatch, IfRange, LastModified, Range,
+        TypedHeader,
+    },
+    Body, Endpoint, Request, Response, Result,
+};
+
+// The maximum number of ranges served in a multipart response, more ranges are ignored.
+const MAX_RANGES: usize = 16;
+
+// The size of chunks read from a range of a file.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// An endpoint serving files under a root directory.
+///
+/// The path of the request is mapped to a file under the root, and segments such as `..` are rejected.
+/// A directory is served by its `index.html`, and a request for a directory without a trailing slash is redirected.
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    root: PathBuf,
+    param: Option<String>,
+    precompressed: bool,
+}
+
+impl ServeDir {
+    /// Create an endpoint serving files under `root`, mapped from the whole path of the request.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            param: None,
+            precompressed: false,
+        }
+    }
+
+    /// Map files from the path parameter named `name` captured by [`Router`](crate::Router),
+    /// which is usually a wildcard such as `*path`.
+    pub fn param(mut self, name: impl Into<String>) -> Self {
+        self.param = Some(name.into());
+        self
+    }
+
+    /// Serve a `.br` or `.gz` sibling of the requested file instead, if it exists and the client accepts the coding.
+    pub fn precompressed(mut self) -> Self {
+        self.precompressed = true;
+        self
+    }
+
+    // Map the request to a path under the root, return `None` if the path is unsafe.
+    fn resolve(&self, request: &Request) -> Option<PathBuf> {
+        // Both the wildcard and the path are percent-encoded.
+        let relative = match &self.param {
+            Some(name) => request.get_extension::<Params>()?.get(name)?,
+            None => request.uri().path(),
+        };
+
+        let mut path = self.root.clone();
+        for segment in relative.split('/').map(crate::router::percent_decode) {
+            if segment.is_empty() || segment == "." {
+                continue;
+            }
+            // An encoded separator would change the segmentation.
+            if segment.contains(['/', '\\', '\0']) {
+                return None;
+            }
+            // Reject `..`, and prefixes or roots on Windows.
+            match Path::new(&segment).components().next() {
+                Some(Component::Normal(_)) => path.push(&segment),
+                _ => return None,
+            }
+        }
+        Some(path)
+    }
+}
+
+fn not_found() -> Response {
+    Response::new(StatusCode::NOT_FOUND, Body::empty())
+}
+
+fn io_error(error: io::Error) -> crate::Error {
+    let status = match error.kind() {
+        io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    crate::Error::new(error, status)
+}
+
+async fn file_metadata(path: &Path) -> Option<std::fs::Metadata> {
+    async_fs::metadata(path)
+        .await
+        .ok()
+        .filter(|metadata| metadata.is_file())
+}
+
+#[async_trait]
+impl Endpoint for ServeDir {
+    async fn call_endpoint(&self, request: &mut Request) -> Result<Response> {
+        let method = request.method();
+        if method != Method::GET && method != Method::HEAD {
+            return Ok(Response::new(StatusCode::METHOD_NOT_ALLOWED, Body::empty())
+                .header(header::ALLOW, HeaderValue::from_static("GET, HEAD")));
+        }
+
+        let Some(mut path) = self.resolve(request) else {
+            return Ok(not_found());
+        };
+        let Ok(metadata) = async_fs::metadata(&path).await else {
+            return Ok(not_found());
+        };
+        if metadata.is_dir() {
+            let uri = request.uri();
+            if !uri.path().ends_with('/') {
+                // Relative links in the index are resolved against the directory.
+                // Leading slashes are collapsed, as `//host/` would redirect to another host.
+                let mut location = format!("/{}/", uri.path().trim_start_matches(['/', '\\']));
+                if let Some(query) = uri.query() {
+                    let _ = write!(location, "?{query}");
+                }
+                return Ok(Response::new(StatusCode::MOVED_PERMANENTLY, Body::empty())
+                    .header(header::LOCATION, location));
+            }
+            path.push("index.html");
+        }
+        let Some(mut metadata) = file_metadata(&path).await else {
+            return Ok(not_found());
+        };
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        let mime =
+            crate::mime_guess::guess(extension.as_bytes()).unwrap_or("application/octet-stream");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(mime));
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+        let mut encoding = None;
+        if self.precompressed {
+            headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+            for (coding, suffix) in [("br", ".br"), ("gzip", ".gz")] {
+                if crate::negotiate::encoding(request.headers(), &[coding]).is_none() {
+                    continue;
+                }
+                let mut sibling = OsString::from(path.as_os_str());
+                sibling.push(suffix);
+                let sibling = PathBuf::from(sibling);
+                if let Some(sibling_metadata) = file_metadata(&sibling).await {
+                    path = sibling;
+                    metadata = sibling_metadata;
+                    encoding = Some(coding);
+                    headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(coding));
+                    break;
+                }
+            }
+        }
+
+        let len = metadata.len();
+        // Truncated to whole seconds, the precision of HTTP dates.
+        let secs = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs());
+        let modified = secs.map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        let etag = EntityTag::strong(format!(
+            "{:x}-{:x}{}",
+            secs.unwrap_or_default(),
+            len,
+            encoding
+                .map(|coding| format!("-{coding}"))
+                .unwrap_or_default()
+        ));
+        headers.insert(ETag::name(), ETag(etag.clone()).encode());
+        if let Some(modified) = modified {
+            headers.insert(LastModified::name(), LastModified(modified).encode());
+        }
+
+        if is_not_modified(request, &etag, modified) {
+            headers.remove(header::CONTENT_TYPE);
+            headers.remove(header::CONTENT_ENCODING);
+            let mut response = Response::new(StatusCode::NOT_MODIFIED, Body::empty());
+            *response.headers_mut() = headers;
+            return Ok(response);
+        }
+
+        // Malformed ranges are ignored.
+        let ranges = request
+            .typed_header::<Range>()
+            .ok()
+            .flatten()
+            .filter(|_| request.method() == Method::GET)
+            .filter(|_| if_range_matches(request, &etag, modified))
+            .and_then(|range| select_ranges(&range, len));
+
+        let mut response = match ranges {
+            None => {
+                let body = Body::from_file(&path).await.map_err(io_error)?;
+                Response::new(StatusCode::OK, body)
+            }
+            Some(Err(Unsatisfiable)) => {
+                headers.remove(header::CONTENT_ENCODING);
+                headers.insert(
+                    ContentRange::name(),
+                    ContentRange::unsatisfied(len).encode(),
+                );
+                Response::new(StatusCode::RANGE_NOT_SATISFIABLE, Body::empty())
+            }
+            Some(Ok(ranges)) => {
+                let file = File::open(&path).await.map_err(io_error)?;
+                let mut pieces = VecDeque::new();
+                let mut length = 0;
+                if let [(start, end)] = ranges[..] {
+                    headers.insert(
+                        ContentRange::name(),
+                        ContentRange::bytes(start, end, Some(len)).encode(),
+                    );
+                    pieces.push_back(Piece::Range(start, end - start + 1));
+                    length = end - start + 1;
+                } else {
+                    let boundary = crate::multipart::random_boundary();
+                    let content_type = headers.remove(header::CONTENT_TYPE).unwrap();
+                    for (start, end) in ranges {
+                        let head = format!(
+                            "\r\n--{boundary}\r\ncontent-type: {}\r\ncontent-range: bytes {start}-{end}/{len}\r\n\r\n",
+                            content_type.to_str().unwrap()
+                        );
+                        length += head.len() as u64 + end - start + 1;
+                        pieces.push_back(Piece::Data(head.into()));
+                        pieces.push_back(Piece::Range(start, end - start + 1));
+                    }
+                    let tail = format!("\r\n--{boundary}--\r\n");
+                    length += tail.len() as u64;
+                    pieces.push_back(Piece::Data(tail.into()));
+                    headers.insert(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_str(&format!(
+                            "multipart/byteranges; boundary={boundary}"
+                        ))
+                        .unwrap(),
+                    );
+                }
+                let body = Body::from_stream(stream::unfold((file, pieces), next_piece));
+                let body = match usize::try_from(length) {
+                    Ok(length) => body.with_length(length),
+                    Err(_) => body,
+                };
+                Response::new(StatusCode::PARTIAL_CONTENT, body)
+            }
+        };
+        response.headers_mut().extend(headers);
+        Ok(response)
+    }
+}
+
+enum Piece {
+    Data(Bytes),
+    // The start and the length of a range of the file.
+    Range(u64, u64),
+}
+
+async fn next_piece(
+    (mut file, mut pieces): (File, VecDeque<Piece>),
+) -> Option<(io::Result<Bytes>, (File, VecDeque<Piece>))> {
+    let result = match pieces.pop_front()? {
+        Piece::Data(data) => Ok(data),
+        Piece::Range(start, len) => match read_chunk(&mut file, start, len).await {
+            Ok(data) => {
+                let read = data.len() as u64;
+                if read < len {
+                    pieces.push_front(Piece::Range(start + read, len - read));
+                }
+                Ok(data)
+            }
+            Err(error) => {
+                pieces.clear();
+                Err(error)
+            }
+        },
+    };
+    Some((result, (file, pieces)))
+}
+
+async fn read_chunk(file: &mut File, start: u64, len: u64) -> io::Result<Bytes> {
+    file.seek(SeekFrom::Start(start)).await?;
+    let mut buf = vec![0; len.min(CHUNK_SIZE) as usize];
+    let read = file.read(&mut buf).await?;
+    if read == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    buf.truncate(read);
+    Ok(buf.into())
+}
+
+// Evaluate `If-None-Match`, or `If-Modified-Since` if the former is absent, malformed values never match.
+fn is_not_modified(request: &Request, etag: &EntityTag, modified: Option<SystemTime>) -> bool {
+    if request.headers().contains_key(IfNoneMatch::name()) {
+        return request
+            .typed_header::<IfNoneMatch>()
+            .ok()
+            .flatten()
+            .is_some_and(|if_none_match| if_none_match.matches(etag));
+    }
+    let since = request.typed_header::<IfModifiedSince>().ok().flatten();
+    matches!((modified, since), (Some(modified), Some(IfModifiedSince(since))) if modified <= since)
+}
+
+// Return true if the range should be served according to `If-Range`, a malformed value never matches.
+fn if_range_matches(request: &Request, etag: &EntityTag, modified: Option<SystemTime>) -> bool {
+    match request.typed_header::<IfRange>() {
+        Ok(None) => true,
+        Ok(Some(if_range)) => if_range.matches(Some(etag), modified),
+        Err(_) => false,
+    }
+}
+
+struct Unsatisfiable;
+
+// Resolve the satisfiable ranges, return `None` if there are too many ranges to be served.
+fn select_ranges(
+    range: &Range,
+    len: u64,
+) -> Option<std::result::Result<Vec<(u64, u64)>, Unsatisfiable>> {
+    let ranges = range.satisfiable(len);
+    if ranges.len() > MAX_RANGES {
+        return None;
+    }
+    if ranges.is_empty() {
+        return Some(Err(Unsatisfiable));
+    }
+    Some(Ok(ranges))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use http::{header, HeaderValue, Method, StatusCode};
+
+    use super::ServeDir;
+    use crate::{Endpoint, Request, Response, Router};
+
+    // Create a temporary directory with `files`, which is removed by `remove_dir_all`.
+    fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("http-kit-serve-dir-{name}-{}", std::process::id()));
+        for (path, content) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn call(serve_dir: &ServeDir, mut request: Request) -> Response {
+        async_std::task::block_on(serve_dir.call_endpoint(&mut request)).unwrap()
+    }
+
+    fn body(response: &mut Response) -> String {
+        async_std::task::block_on(response.into_string())
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn resolve_decodes_segments() {
+        let request = Request::get("/a%20b/c.txt");
+        let path = ServeDir::new("root").resolve(&request).unwrap();
+        assert_eq!(path, PathBuf::from("root/a b/c.txt"));
+    }
+
+    #[test]
+    fn resolve_rejects_encoded_separator() {
+        let request = Request::get("/a%2F..%2F..%2Fsecret");
+        assert!(ServeDir::new("root").resolve(&request).is_none());
+    }
+
+    #[test]
+    fn resolve_rejects_parent() {
+        let request = Request::get("/a/../../secret");
+        assert!(ServeDir::new("root").resolve(&request).is_none());
+    }
+
+    #[test]
+    fn wildcard_can_not_escape_root() {
+        let dir = temp_dir(
+            "wildcard",
+            &[("root/public.txt", "public"), ("secret.txt", "secret")],
+        );
+        let router = Router::new().get(
+            "/assets/*path",
+            ServeDir::new(dir.join("root")).param("path"),
+        );
+
+        async_std::task::block_on(async {
+            let mut request = Request::get("/assets/public.txt");
+            let response = router.call_endpoint(&mut request).await.unwrap();
+            assert_eq!(response.status(), 200);
+
+            let mut request = Request::get("/assets/..%2Fsecret.txt");
+            let response = router.call_endpoint(&mut request).await.unwrap();
+            assert_eq!(response.status(), 404);
+        });
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn conditional_requests() {
+        let dir = temp_dir("conditional", &[("file.txt", "0123456789")]);
+        let serve_dir = ServeDir::new(&dir);
+
+        async_std::task::block_on(async {
+            let mut request = Request::get("/file.txt");
+            let response = serve_dir.call_endpoint(&mut request).await.unwrap();
+            let etag = response.get_header(header::ETAG).unwrap().clone();
+            let modified = response.get_header(header::LAST_MODIFIED).unwrap().clone();
+            let weak = HeaderValue::from_str(&format!("W/{}", etag.to_str().unwrap())).unwrap();
+
+            let conditional =
+                |name, value: &HeaderValue| Request::get("/file.txt").header(name, value.clone());
+            let status = |mut request: Request| {
+                let serve_dir = &serve_dir;
+                async move {
+                    serve_dir
+                        .call_endpoint(&mut request)
+                        .await
+                        .unwrap()
+                        .status()
+                }
+            };
+
+            assert_eq!(status(conditional(header::IF_NONE_MATCH, &weak)).await, 304);
+            assert_eq!(
+                status(conditional(header::IF_MODIFIED_SINCE, &modified)).await,
+                304
+            );
+            // `If-None-Match` takes precedence, even if it is malformed.
+            let request = conditional(
+                header::IF_NONE_MATCH,
+                &HeaderValue::from_static("\"other\""),
+            )
+            .header(header::IF_MODIFIED_SINCE, modified.clone());
+            assert_eq!(status(request).await, 200);
+            let request = conditional(
+                header::IF_NONE_MATCH,
+                &HeaderValue::from_static("malformed"),
+            )
+            .header(header::IF_MODIFIED_SINCE, modified.clone());
+            assert_eq!(status(request).await, 200);
+
+            let range = |if_range: &HeaderValue| {
+                conditional(header::IF_RANGE, if_range).header(header::RANGE, "bytes=0-1")
+            };
+            assert_eq!(status(range(&etag)).await, 206);
+            assert_eq!(status(range(&modified)).await, 206);
+            assert_eq!(status(range(&weak)).await, 200);
+            assert_eq!(
+                status(range(&HeaderValue::from_static("malformed"))).await,
+                200
+            );
+        });
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn multiple_ranges() {
+        let dir = temp_dir("ranges", &[("file.txt", "0123456789")]);
+        let serve_dir = ServeDir::new(&dir);
+
+        let request = Request::get("/file.txt").header(header::RANGE, "bytes=0-1,5-6,-2");
+        let mut response = call(&serve_dir, request);
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert!(response.get_header(header::CONTENT_RANGE).is_none());
+        let content_type = response
+            .get_header(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+
+        let part = |range: &str, data: &str| {
+            format!(
+                "\r\n--{boundary}\r\ncontent-type: text/plain; charset=utf-8\r\n\
+                 content-range: bytes {range}/10\r\n\r\n{data}"
+            )
+        };
+        let expected = format!(
+            "{}{}{}\r\n--{boundary}--\r\n",
+            part("0-1", "01"),
+            part("5-6", "56"),
+            part("8-9", "89")
+        );
+        // The body is declared with its exact length, which is checked while it is read.
+        let data = response.take_body().unwrap();
+        assert_eq!(data.len(), Some(expected.len()));
+        assert_eq!(
+            async_std::task::block_on(data.into_string()).unwrap(),
+            expected
+        );
+
+        // A single range is served without multipart.
+        let request = Request::get("/file.txt").header(header::RANGE, "bytes=2-4");
+        let mut response = call(&serve_dir, request);
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.get_header(header::CONTENT_RANGE).unwrap(),
+            "bytes 2-4/10"
+        );
+        assert_eq!(body(&mut response), "234");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unsatisfiable_range() {
+        let dir = temp_dir("unsatisfiable", &[("file.txt", "0123456789")]);
+        let serve_dir = ServeDir::new(&dir);
+        let request = Request::get("/file.txt").header(header::RANGE, "bytes=20-30");
+        let mut response = call(&serve_dir, request);
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            response.get_header(header::CONTENT_RANGE).unwrap(),
+            "bytes */10"
+        );
+        assert_eq!(body(&mut response), "");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn methods() {
+        let dir = temp_dir("methods", &[("file.txt", "0123456789")]);
+        let serve_dir = ServeDir::new(&dir);
+
+        let response = call(&serve_dir, Request::post("/file.txt"));
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.get_header(header::ALLOW).unwrap(), "GET, HEAD");
+
+        // Ranges are only served for GET.
+        let mut request = Request::get("/file.txt").header(header::RANGE, "bytes=0-1");
+        *request.method_mut() = Method::HEAD;
+        let response = call(&serve_dir, request);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.get_header(header::ETAG).is_some());
+        assert!(response.get_header(header::CONTENT_RANGE).is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn precompressed() {
+        let dir = temp_dir(
+            "precompressed",
+            &[
+                ("file.txt", "plain"),
+                ("file.txt.br", "brotli"),
+                ("file.txt.gz", "gzip"),
+                ("other.txt", "plain"),
+                ("other.txt.gz", "gzip"),
+            ],
+        );
+        let serve_dir = ServeDir::new(&dir).precompressed();
+        let get = |path: &str, accept_encoding: Option<&'static str>| {
+            let mut request = Request::get(path);
+            if let Some(accept_encoding) = accept_encoding {
+                request = request.header(header::ACCEPT_ENCODING, accept_encoding);
+            }
+            call(&serve_dir, request)
+        };
+        let etag = |response: &Response| {
+            response
+                .get_header(header::ETAG)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_owned()
+        };
+
+        let mut plain = get("/file.txt", None);
+        assert_eq!(plain.get_header(header::VARY).unwrap(), "accept-encoding");
+        assert!(plain.get_header(header::CONTENT_ENCODING).is_none());
+        assert_eq!(body(&mut plain), "plain");
+
+        for (accept_encoding, coding, data) in [
+            ("gzip", "gzip", "gzip"),
+            ("gzip, br", "br", "brotli"),
+            ("br;q=0, gzip", "gzip", "gzip"),
+        ] {
+            let mut response = get("/file.txt", Some(accept_encoding));
+            assert_eq!(
+                response.get_header(header::CONTENT_ENCODING).unwrap(),
+                coding
+            );
+            assert_eq!(
+                response.get_header(header::VARY).unwrap(),
+                "accept-encoding"
+            );
+            assert_eq!(
+                response.get_header(header::CONTENT_TYPE).unwrap(),
+                "text/plain; charset=utf-8"
+            );
+            // Each representation has its own validator.
+            assert!(etag(&response).ends_with(&format!("-{coding}\"")));
+            assert_ne!(etag(&response), etag(&plain));
+            assert_eq!(body(&mut response), data);
+        }
+
+        // A missing sibling falls back to the next accepted coding, then to the file itself.
+        let mut response = get("/other.txt", Some("br, gzip"));
+        assert_eq!(
+            response.get_header(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+        assert_eq!(body(&mut response), "gzip");
+        let mut response = get("/other.txt", Some("br"));
+        assert!(response.get_header(header::CONTENT_ENCODING).is_none());
+        assert_eq!(body(&mut response), "plain");
+
+        // Siblings are ignored unless enabled.
+        let mut response = call(
+            &ServeDir::new(&dir),
+            Request::get("/file.txt").header(header::ACCEPT_ENCODING, "gzip"),
+        );
+        assert!(response.get_header(header::VARY).is_none());
+        assert!(response.get_header(header::CONTENT_ENCODING).is_none());
+        assert_eq!(body(&mut response), "plain");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn directory_index() {
+        let dir = temp_dir("index", &[("sub/index.html", "<p>index</p>")]);
+        let serve_dir = ServeDir::new(&dir);
+
+        let response = call(&serve_dir, Request::get("/sub"));
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(response.get_header(header::LOCATION).unwrap(), "/sub/");
+        let response = call(&serve_dir, Request::get("/sub?a=1"));
+        assert_eq!(response.get_header(header::LOCATION).unwrap(), "/sub/?a=1");
+        // A protocol-relative location would redirect to another host.
+        let response = call(&serve_dir, Request::get("//sub"));
+        assert_eq!(response.get_header(header::LOCATION).unwrap(), "/sub/");
+
+        let mut response = call(&serve_dir, Request::get("/sub/"));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.get_header(header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(body(&mut response), "<p>index</p>");
+
+        // A directory without an index is not listed.
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        let response = call(&serve_dir, Request::get("/empty/"));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,587 @@
+//! A small HTTP/1.1 server driving an [`App`].
+//!
+//! The server supports keep-alive, chunked transfer encoding and graceful shutdown.
+//! Connections are served concurrently on the task calling [`Server::serve`].
+//!
+//! # Example
+//! ```rust,no_run
+//! use http_kit::{endpoint_fn, server::Server, App, Response};
+//!
+//! # async_std::task::block_on(async {
+//! let app = App::new(endpoint_fn(async |_request| {
+//!     Ok(Response::from("Hello,world"))
+//! }));
+//! Server::new(app).listen("127.0.0.1:8080").await?;
+//! # std::io::Result::Ok(())
+//! # }).unwrap();
+//! ```
+
+use std::{
+    fmt::Debug,
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_executor::LocalExecutor;
+use async_io::Timer;
+use async_net::{AsyncToSocketAddrs, TcpListener, TcpStream};
+use async_trait::async_trait;
+use event_listener::Event;
+use futures_lite::{
+    future,
+    io::{BufReader, BufWriter},
+    ready, AsyncBufRead, AsyncRead, AsyncWrite,
+};
+use http::{header, StatusCode, Version};
+
+use crate::{
+    h1::{self, BodyDecoder, Framing, Parser},
+    App, Body, Endpoint, IntoResponse, Request, Response,
+};
+
+/// A source of incoming connections.
+#[async_trait]
+pub trait Listener: Send + Sync {
+    /// The connection type, whose clones must refer to the same connection.
+    type Stream: AsyncRead + AsyncWrite + Clone + Unpin + Send + Sync + 'static;
+
+    /// Accept a new incoming connection.
+    async fn accept(&self) -> io::Result<Self::Stream>;
+}
+
+#[async_trait]
+impl Listener for TcpListener {
+    type Stream = TcpStream;
+
+    async fn accept(&self) -> io::Result<Self::Stream> {
+        let (stream, _) = TcpListener::accept(self).await?;
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+}
+
+#[cfg(unix)]
+#[async_trait]
+impl Listener for async_net::unix::UnixListener {
+    type Stream = async_net::unix::UnixStream;
+
+    async fn accept(&self) -> io::Result<Self::Stream> {
+        Ok(async_net::unix::UnixListener::accept(self).await?.0)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ShutdownInner {
+    triggered: AtomicBool,
+    event: Event,
+}
+
+/// A handle triggering the graceful shutdown of a server.
+///
+/// After shutdown, the server stops accepting connections, finishes in-flight requests and closes idle connections.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownHandle {
+    inner: Arc<ShutdownInner>,
+}
+
+impl ShutdownHandle {
+    /// Trigger the graceful shutdown.
+    pub fn shutdown(&self) {
+        self.inner.triggered.store(true, Ordering::SeqCst);
+        self.inner.event.notify(usize::MAX);
+    }
+
+    /// Return `true` if the shutdown has been triggered.
+    pub fn is_shutdown(&self) -> bool {
+        self.inner.triggered.load(Ordering::SeqCst)
+    }
+
+    async fn wait(&self) {
+        loop {
+            if self.is_shutdown() {
+                return;
+            }
+            let listener = self.inner.event.listen();
+            if self.is_shutdown() {
+                return;
+            }
+            listener.await;
+        }
+    }
+}
+
+/// An HTTP/1.1 server serving an [`App`].
+pub struct Server<E: Endpoint> {
+    app: App<E>,
+    shutdown: ShutdownHandle,
+    keep_alive_timeout: Option<Duration>,
+    parser: Parser,
+    max_drain_size: u64,
+}
+
+impl<E: Endpoint> Debug for Server<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field("app", &self.app)
+            .field("keep_alive_timeout", &self.keep_alive_timeout)
+            .field("parser", &self.parser)
+            .finish()
+    }
+}
+
+impl<E: Endpoint> Server<E> {
+    /// Create a server serving `app`.
+    pub fn new(app: App<E>) -> Self {
+        Self {
+            app,
+            shutdown: ShutdownHandle::default(),
+            keep_alive_timeout: Some(Duration::from_secs(5)),
+            parser: Parser::new(),
+            max_drain_size: 64 * 1024,
+        }
+    }
+
+    /// Set how long an idle connection is kept open waiting for the next request, `None` means forever.
+    /// Default to 5 seconds.
+    pub fn keep_alive_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.keep_alive_timeout = timeout.into();
+        self
+    }
+
+    /// Set the parser reading requests, whose limits are enforced on every request head.
+    /// Oversized heads are rejected with `431 Request Header Fields Too Large`.
+    pub fn parser(mut self, parser: Parser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Return a handle triggering the graceful shutdown of this server.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Bind a TCP listener on `addr` and serve connections from it.
+    pub async fn listen(&self, addr: impl AsyncToSocketAddrs) -> io::Result<()> {
+        self.serve(TcpListener::bind(addr).await?).await
+    }
+
+    /// Serve connections accepted from `listener` until the shutdown is triggered,
+    /// then wait for in-flight connections to finish.
+    pub async fn serve<L: Listener>(&self, listener: L) -> io::Result<()> {
+        let executor = LocalExecutor::new();
+        executor
+            .run(async {
+                let mut tasks = Vec::new();
+                loop {
+                    let accepted = future::or(async { Some(listener.accept().await) }, async {
+                        self.shutdown.wait().await;
+                        None
+                    })
+                    .await;
+
+                    match accepted {
+                        Some(Ok(stream)) => {
+                            tasks.retain(|task: &async_executor::Task<()>| !task.is_finished());
+                            tasks.push(executor.spawn(async move {
+                                // I/O errors only affect the current connection.
+                                let _ = self.serve_connection(stream).await;
+                            }));
+                        }
+                        Some(Err(error)) => match error.kind() {
+                            io::ErrorKind::ConnectionAborted
+                            | io::ErrorKind::ConnectionReset
+                            | io::ErrorKind::Interrupted => {}
+                            // Such as running out of file descriptors, wait for a while before accepting again.
+                            _ => {
+                                Timer::after(Duration::from_millis(100)).await;
+                            }
+                        },
+                        None => break,
+                    }
+                }
+
+                for task in tasks {
+                    task.await;
+                }
+            })
+            .await;
+        Ok(())
+    }
+
+    async fn read_request_head<R>(&self, reader: &mut R) -> Option<io::Result<(Request, Framing)>>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let read = async { self.parser.read_request_head(reader).await.transpose() };
+        let idle = async {
+            match self.keep_alive_timeout {
+                Some(timeout) => {
+                    future::or(
+                        async {
+                            Timer::after(timeout).await;
+                        },
+                        self.shutdown.wait(),
+                    )
+                    .await
+                }
+                None => self.shutdown.wait().await,
+            }
+            None
+        };
+        future::or(read, idle).await
+    }
+
+    async fn serve_connection<S>(&self, stream: S) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Clone + Unpin + Send + Sync + 'static,
+    {
+        let mut reader = BufReader::new(stream.clone());
+        let mut writer = BufWriter::new(stream);
+
+        loop {
+            let (mut request, framing) = match self.read_request_head(&mut reader).await {
+                Some(Ok(head)) => head,
+                Some(Err(error)) if error.kind() == io::ErrorKind::InvalidData => {
+                    let status = if error
+                        .get_ref()
+                        .is_some_and(|error| error.is::<h1::HeadTooLarge>())
+                    {
+                        StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+                    } else {
+                        StatusCode::BAD_REQUEST
+                    };
+                    return reject(&mut writer, status).await;
+                }
+                Some(Err(error)) => return Err(error),
+                None => return Ok(()),
+            };
+
+            let method = request.method().clone();
+            let version = request.version();
+            let keep_alive = h1::wants_keep_alive(version, request.headers());
+
+            // `100 Continue` is only sent once the body is read, so that a request rejected
+            // by its head is answered before the client uploads the body.
+            let expect = Arc::new(Mutex::new(None));
+            if framing != Framing::Length(0)
+                && version == Version::HTTP_11
+                && request
+                    .get_header(header::EXPECT)
+                    .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"100-continue"))
+            {
+                *expect.lock().unwrap() = Some(writer.get_ref().clone());
+            }
+
+            let slot = Arc::new(Mutex::new(None));
+            let decoder = BodyDecoder::new(reader, framing);
+            if decoder.is_done() {
+                *slot.lock().unwrap() = Some(decoder);
+            } else {
+                request.replace_body(decoder.into_body(|decoder| RequestBody {
+                    decoder: Some(decoder),
+                    slot: slot.clone(),
+                    expect: Some(Expect::Pending(expect.clone())),
+                }));
+            }
+
+            let mut response = self
+                .app
+                .run(request)
+                .await
+                .unwrap_or_else(IntoResponse::into_response);
+
+            *response.version_mut() = version;
+            if let Err(error) = response.finalize_headers() {
+                response = error.into_response();
+            }
+
+            // The client is still waiting for `100 Continue`, whose body may or may not follow.
+            let unread = expect.lock().unwrap().take().is_some();
+            let keep_alive = keep_alive && !unread && !self.shutdown.is_shutdown();
+            let keep_alive =
+                h1::write_response(&mut writer, &mut response, &method, version, keep_alive)
+                    .await?;
+            drop(response);
+
+            if !keep_alive {
+                return Ok(());
+            }
+
+            // The request body may still be alive if it was moved out of the handling chain.
+            let Some(mut decoder) = slot.lock().unwrap().take() else {
+                return Ok(());
+            };
+            if !decoder.is_done() && !decoder.drain(self.max_drain_size).await? {
+                return Ok(());
+            }
+            reader = decoder.into_inner();
+        }
+    }
+}
+
+async fn reject<W: AsyncWrite + Unpin>(writer: &mut W, status: StatusCode) -> io::Result<()> {
+    let mut response = Response::new(status, Body::empty());
+    h1::write_response(
+        writer,
+        &mut response,
+        &http::Method::GET,
+        Version::HTTP_11,
+        false,
+    )
+    .await?;
+    Ok(())
+}
+
+// The state of an interim `100 Continue` response.
+enum Expect<S> {
+    // The connection to write it on, taken by the server once the response is ready.
+    Pending(Arc<Mutex<Option<S>>>),
+    // The connection and the number of bytes written.
+    Writing(S, usize),
+}
+
+// The request body, which gives the connection back to the server when it is dropped.
+struct RequestBody<S> {
+    decoder: Option<BodyDecoder<BufReader<S>>>,
+    slot: Arc<Mutex<Option<BodyDecoder<BufReader<S>>>>>,
+    expect: Option<Expect<S>>,
+}
+
+impl<S: AsyncWrite + Unpin> RequestBody<S> {
+    fn decoder(self: Pin<&mut Self>) -> Pin<&mut BodyDecoder<BufReader<S>>> {
+        Pin::new(
+            self.get_mut()
+                .decoder
+                .as_mut()
+                .expect("Decoder only be taken on drop"),
+        )
+    }
+
+    // Send `100 Continue` on the first read if the client expects it.
+    fn poll_continue(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            match self.expect.take() {
+                None => return Poll::Ready(Ok(())),
+                Some(Expect::Pending(stream)) => match stream.lock().unwrap().take() {
+                    Some(stream) => self.expect = Some(Expect::Writing(stream, 0)),
+                    // The response has been sent already.
+                    None => return Poll::Ready(Ok(())),
+                },
+                Some(Expect::Writing(mut stream, written)) => {
+                    if written == h1::CONTINUE.len() {
+                        let poll = Pin::new(&mut stream).poll_flush(cx);
+                        if poll.is_pending() {
+                            self.expect = Some(Expect::Writing(stream, written));
+                        }
+                        return poll;
+                    }
+                    match Pin::new(&mut stream).poll_write(cx, &h1::CONTINUE[written..]) {
+                        Poll::Ready(Ok(0)) => {
+                            return Poll::Ready(Err(io::ErrorKind::WriteZero.into()))
+                        }
+                        Poll::Ready(Ok(n)) => {
+                            self.expect = Some(Expect::Writing(stream, written + n))
+                        }
+                        Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                        Poll::Pending => {
+                            self.expect = Some(Expect::Writing(stream, written));
+                            return Poll::Pending;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S> Drop for RequestBody<S> {
+    fn drop(&mut self) {
+        if let Some(decoder) = self.decoder.take() {
+            if let Ok(mut slot) = self.slot.lock() {
+                *slot = Some(decoder);
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for RequestBody<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_continue(cx))?;
+        self.decoder().poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncBufRead for RequestBody<S> {
+    fn poll_fill_buf(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        ready!(self.poll_continue(cx))?;
+        self.decoder().poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.decoder().consume(amt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_net::{TcpListener, TcpStream};
+    use futures_lite::{future, AsyncReadExt, AsyncWriteExt};
+
+    use super::Server;
+    use crate::{endpoint_fn, App, Endpoint, Request, Response};
+
+    fn app() -> App<impl Endpoint> {
+        App::new(endpoint_fn(async |request: &mut Request| {
+            Ok(Response::from(request.uri().path().to_owned()))
+        }))
+    }
+
+    // Write `input` on a new connection and read the output until the server closes it.
+    fn exchange(server: Server<impl Endpoint>, input: &[u8]) -> String {
+        future::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let shutdown = server.shutdown_handle();
+            let client = async {
+                let mut stream = TcpStream::connect(addr).await.unwrap();
+                stream.write_all(input).await.unwrap();
+                let mut output = String::new();
+                stream.read_to_string(&mut output).await.unwrap();
+                shutdown.shutdown();
+                output
+            };
+            let (served, output) = future::zip(server.serve(listener), client).await;
+            served.unwrap();
+            output
+        })
+    }
+
+    #[test]
+    fn pipelining() {
+        let output = exchange(
+            Server::new(app()),
+            b"GET /a HTTP/1.1\r\nhost: x\r\n\r\nGET /b HTTP/1.1\r\nhost: x\r\nconnection: close\r\n\r\n",
+        );
+        assert_eq!(
+            output,
+            "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n/a\
+             HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n/b"
+        );
+    }
+
+    #[test]
+    fn unread_body_is_drained() {
+        let output = exchange(
+            Server::new(app()),
+            b"POST /a HTTP/1.1\r\ncontent-length: 5\r\n\r\nhello\
+              POST /b HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n\
+              GET /c HTTP/1.1\r\nconnection: close\r\n\r\n",
+        );
+        assert_eq!(
+            output,
+            "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n/a\
+             HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n/b\
+             HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n/c"
+        );
+    }
+
+    #[test]
+    fn http10_closes_by_default() {
+        let output = exchange(
+            Server::new(app()),
+            b"GET /a HTTP/1.0\r\n\r\nGET /b HTTP/1.0\r\n\r\n",
+        );
+        assert_eq!(
+            output,
+            "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n/a"
+        );
+    }
+
+    #[test]
+    fn http10_keep_alive() {
+        let output = exchange(
+            Server::new(app()),
+            b"GET /a HTTP/1.0\r\nconnection: keep-alive\r\n\r\nGET /b HTTP/1.0\r\n\r\n",
+        );
+        assert_eq!(
+            output,
+            "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: keep-alive\r\n\r\n/a\
+             HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n/b"
+        );
+    }
+
+    #[test]
+    fn idle_connection_times_out() {
+        let server = Server::new(app()).keep_alive_timeout(Duration::from_millis(50));
+        let output = exchange(server, b"GET /a HTTP/1.1\r\n\r\n");
+        assert_eq!(output, "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n/a");
+    }
+
+    #[test]
+    fn continue_is_sent_when_the_body_is_read() {
+        let app = App::new(endpoint_fn(async |request: &mut Request| {
+            Ok(Response::from(request.into_string().await?))
+        }));
+        let server = Server::new(app);
+        future::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let shutdown = server.shutdown_handle();
+            let client = async {
+                let mut stream = TcpStream::connect(addr).await.unwrap();
+                stream
+                    .write_all(b"POST /a HTTP/1.1\r\nexpect: 100-continue\r\ncontent-length: 5\r\nconnection: close\r\n\r\n")
+                    .await
+                    .unwrap();
+                let mut interim = [0; 25];
+                stream.read_exact(&mut interim).await.unwrap();
+                assert_eq!(&interim, b"HTTP/1.1 100 Continue\r\n\r\n");
+                stream.write_all(b"hello").await.unwrap();
+                let mut output = String::new();
+                stream.read_to_string(&mut output).await.unwrap();
+                shutdown.shutdown();
+                output
+            };
+            let (served, output) = future::zip(server.serve(listener), client).await;
+            served.unwrap();
+            assert_eq!(
+                output,
+                "HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\nhello"
+            );
+        });
+    }
+
+    #[test]
+    fn continue_is_skipped_when_the_body_is_not_read() {
+        // The body is never sent, the connection is closed instead of waiting for it.
+        let output = exchange(
+            Server::new(app()),
+            b"POST /a HTTP/1.1\r\nexpect: 100-continue\r\ncontent-length: 5\r\n\r\n",
+        );
+        assert_eq!(
+            output,
+            "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n/a"
+        );
+    }
+
+    #[test]
+    fn invalid_request() {
+        let output = exchange(
+            Server::new(app()),
+            b"GET /a HTTP/1.1\r\ntransfer-encoding: chunked\r\ncontent-length: 5\r\n\r\n",
+        );
+        assert!(output.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+}
@@ -0,0 +1,549 @@
+//! Sessions identified by a cookie, with pluggable storage.
+//!
+//! The [`Sessions`] middleware loads the session of every request before calling the next middleware,
+//! and saves it after the response if it was changed.
+//! Handlers reach the session with `Request::get_extension::<Session>()` or the [`Session`] extractor.
+//!
+//! # Example
+//! ```rust
+//! use http_kit::{
+//!     extract::handler,
+//!     session::{MemoryStore, Session, Sessions},
+//!     App, Result,
+//! };
+//!
+//! async fn visit(session: Session) -> Result<String> {
+//!     let visits = session.get::<u32>("visits").unwrap_or_default() + 1;
+//!     session.insert("visits", &visits)?;
+//!     Ok(format!("{visits} visits"))
+//! }
+//!
+//! let app = App::new(handler(visit)).middleware(Sessions::new(MemoryStore::new()));
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use http::StatusCode;
+use rand::RngCore;
+
+use crate::{
+    cookie::{Cookie, CookieBuilder, SameSite},
+    middleware::Next,
+    FromRequest, Middleware, Request, Response, Result, ResultExt,
+};
+
+mod store;
+#[cfg(feature = "fs")]
+pub use store::FileStore;
+pub use store::{CookieSessionStore, MemoryStore};
+
+impl_error!(
+    SessionMissing,
+    "Session is missing from request extensions, you may forget to add the `Sessions` middleware"
+);
+
+/// A storage of sessions.
+///
+/// The session cookie carries an opaque value returned by [`SessionStore::save`],
+/// which is an identifier for server-side stores, or the data itself for client-side stores.
+#[async_trait]
+pub trait SessionStore: Send + Sync + 'static {
+    /// Load the session by the value of the session cookie, return `None` if it is unknown or expired.
+    async fn load(&self, cookie: &str) -> Result<Option<Session>>;
+
+    /// Save the session, return the value of the session cookie.
+    async fn save(&self, session: &Session) -> Result<String>;
+
+    /// Remove the session by the value of the session cookie.
+    async fn destroy(&self, cookie: &str) -> Result<()>;
+}
+
+#[derive(Debug, Default)]
+struct SessionInner {
+    id: String,
+    data: HashMap<String, String>,
+    expires: Option<SystemTime>,
+    changed: bool,
+    regenerate: bool,
+    destroyed: bool,
+}
+
+/// A session, clones of it share the same data.
+///
+/// Values are stored as JSON.
+#[derive(Debug, Clone)]
+pub struct Session {
+    inner: Arc<Mutex<SessionInner>>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Generate a random identifier of 256 bits.
+fn generate_id() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+impl Session {
+    /// Create an empty session with a random identifier.
+    pub fn new() -> Self {
+        Self::from_inner(SessionInner {
+            id: generate_id(),
+            ..Default::default()
+        })
+    }
+
+    fn from_inner(inner: SessionInner) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SessionInner> {
+        self.inner.lock().unwrap()
+    }
+
+    /// Return the identifier of the session.
+    pub fn id(&self) -> String {
+        self.lock().id.clone()
+    }
+
+    /// Deserialize the value of `key`, return `None` if it is missing or malformed.
+    pub fn get<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        serde_json::from_str(self.lock().data.get(key)?).ok()
+    }
+
+    /// Serialize `value` as the value of `key`.
+    pub fn insert<T: serde::Serialize + ?Sized>(
+        &self,
+        key: impl Into<String>,
+        value: &T,
+    ) -> std::result::Result<(), serde_json::Error> {
+        let key = key.into();
+        let value = serde_json::to_string(value)?;
+        let mut inner = self.lock();
+        if inner.data.get(&key) != Some(&value) {
+            inner.data.insert(key, value);
+            inner.changed = true;
+        }
+        Ok(())
+    }
+
+    /// Remove the value of `key`.
+    pub fn remove(&self, key: &str) {
+        let mut inner = self.lock();
+        if inner.data.remove(key).is_some() {
+            inner.changed = true;
+        }
+    }
+
+    /// Remove all values.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        if !inner.data.is_empty() {
+            inner.data.clear();
+            inner.changed = true;
+        }
+    }
+
+    /// Return `true` if the session has no value.
+    pub fn is_empty(&self) -> bool {
+        self.lock().data.is_empty()
+    }
+
+    /// Give the session a new identifier once the response is sent, keeping the data.
+    ///
+    /// This should be called on privilege changes like logging in,
+    /// so that an identifier planted by an attacker becomes useless.
+    pub fn regenerate(&self) {
+        self.lock().regenerate = true;
+    }
+
+    /// Remove the session from the store and the client once the response is sent.
+    pub fn destroy(&self) {
+        self.lock().destroyed = true;
+    }
+
+    /// Return `true` if the session was changed and should be saved.
+    pub fn is_changed(&self) -> bool {
+        let inner = self.lock();
+        inner.changed || inner.regenerate
+    }
+
+    /// Return `true` if the session is going to be destroyed.
+    pub fn is_destroyed(&self) -> bool {
+        self.lock().destroyed
+    }
+
+    /// Return the time the session expires.
+    pub fn expires(&self) -> Option<SystemTime> {
+        self.lock().expires
+    }
+
+    /// Set the time the session expires.
+    pub fn set_expires(&self, expires: SystemTime) {
+        self.lock().expires = Some(expires);
+    }
+
+    /// Return `true` if the session has expired.
+    pub fn is_expired(&self) -> bool {
+        self.expires()
+            .is_some_and(|expires| expires <= SystemTime::now())
+    }
+
+    /// Serialize the identifier, the expiration time and the data of the session as JSON.
+    pub fn to_json(&self) -> String {
+        let inner = self.lock();
+        let expires = inner
+            .expires
+            .and_then(|expires| expires.duration_since(UNIX_EPOCH).ok())
+            .map(|expires| expires.as_secs());
+        serde_json::to_string(&(&inner.id, expires, &inner.data)).unwrap()
+    }
+
+    /// Deserialize a session serialized by [`Session::to_json`].
+    pub fn from_json(json: &str) -> Option<Self> {
+        let (id, expires, data): (String, Option<u64>, HashMap<String, String>) =
+            serde_json::from_str(json).ok()?;
+        Some(Self::from_inner(SessionInner {
+            id,
+            data,
+            expires: expires.map(|expires| UNIX_EPOCH + Duration::from_secs(expires)),
+            ..Default::default()
+        }))
+    }
+}
+
+#[async_trait]
+impl FromRequest for Session {
+    async fn from_request(request: &mut Request) -> Result<Self> {
+        request.get_extension::<Session>().cloned().ok_or_else(|| {
+            crate::Error::new(SessionMissing::new(), StatusCode::INTERNAL_SERVER_ERROR)
+        })
+    }
+}
+
+/// A middleware loading sessions from a [`SessionStore`] by a cookie, and saving the changed ones.
+///
+/// The cookie is `HttpOnly`, `Secure` and `SameSite=Lax` with the path `/` by default.
+/// Sessions expire one day after they were last saved by default, expired sessions are removed from the store when loaded.
+///
+/// If the next middleware fails, the error is returned without saving the session,
+/// while a session destroyed or regenerated by it is still removed from the store.
+#[derive(Debug)]
+pub struct Sessions<S> {
+    store: S,
+    cookie_name: Cow<'static, str>,
+    path: Cow<'static, str>,
+    domain: Option<Cow<'static, str>>,
+    secure: bool,
+    same_site: SameSite,
+    ttl: Duration,
+}
+
+impl<S: SessionStore> Sessions<S> {
+    /// Create a middleware storing sessions in `store`.
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            cookie_name: Cow::Borrowed("id"),
+            path: Cow::Borrowed("/"),
+            domain: None,
+            secure: true,
+            same_site: SameSite::Lax,
+            ttl: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    /// Set the name of the session cookie.
+    pub fn cookie_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.cookie_name = name.into();
+        self
+    }
+
+    /// Set the path of the session cookie.
+    pub fn path(mut self, path: impl Into<Cow<'static, str>>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Set the domain of the session cookie.
+    pub fn domain(mut self, domain: impl Into<Cow<'static, str>>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Set whether the session cookie is only sent over HTTPS, disable it for local development only.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Set the `SameSite` attribute of the session cookie.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Set how long a session lives after it was last saved.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn cookie(&self, value: String) -> CookieBuilder<'static> {
+        let mut cookie = Cookie::build((self.cookie_name.clone(), value))
+            .path(self.path.clone())
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(self.same_site);
+        if let Some(domain) = &self.domain {
+            cookie = cookie.domain(domain.clone());
+        }
+        cookie
+    }
+}
+
+#[async_trait]
+impl<S: SessionStore> Middleware for Sessions<S> {
+    async fn call_middleware(&self, request: &mut Request, next: Next<'_>) -> Result<Response> {
+        let incoming = request
+            .cookies()
+            .get(&self.cookie_name)
+            .map(|cookie| cookie.value().to_owned());
+        let session = match &incoming {
+            Some(cookie) => match self.store.load(cookie).await? {
+                Some(session) if session.is_expired() => {
+                    self.store.destroy(cookie).await?;
+                    None
+                }
+                session => session,
+            },
+            None => None,
+        }
+        .unwrap_or_default();
+        request.insert_extension(session.clone());
+
+        let result = next.run(request).await;
+
+        let (destroyed, regenerate, changed) = {
+            let inner = session.lock();
+            (inner.destroyed, inner.regenerate, inner.changed)
+        };
+        let mut response = match result {
+            Ok(response) => response,
+            Err(error) => {
+                // Changes are not saved for a failed request, but a session to be destroyed or regenerated
+                // is still removed from the store, so that its identifier does not stay valid.
+                if destroyed || regenerate {
+                    if let Some(cookie) = &incoming {
+                        self.store.destroy(cookie).await?;
+                    }
+                }
+                return Err(error);
+            }
+        };
+        if destroyed {
+            if let Some(cookie) = &incoming {
+                self.store.destroy(cookie).await?;
+            }
+            response
+                .remove_cookie(self.cookie(String::new()))
+                .status(StatusCode::INTERNAL_SERVER_ERROR)?;
+        } else if changed || regenerate {
+            if regenerate {
+                if let Some(cookie) = &incoming {
+                    self.store.destroy(cookie).await?;
+                }
+                session.lock().id = generate_id();
+            }
+            session.set_expires(SystemTime::now() + self.ttl);
+            let value = self.store.save(&session).await?;
+            let max_age = self
+                .ttl
+                .try_into()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)?;
+            response
+                .set_cookie(self.cookie(value).max_age(max_age))
+                .status(StatusCode::INTERNAL_SERVER_ERROR)?;
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use futures_lite::future::block_on;
+    use http::header;
+
+    use super::{CookieSessionStore, MemoryStore, Session, SessionStore, Sessions};
+    use crate::{
+        cookie::{Cookie, Keyring},
+        endpoint_fn, App, Endpoint, Request, Response,
+    };
+
+    fn app(store: impl SessionStore) -> App<impl Endpoint> {
+        App::new(endpoint_fn(async |request: &mut Request| {
+            let session = request.get_extension::<Session>().unwrap().clone();
+            match request.uri().path() {
+                "/set" => session.insert("user", "alice")?,
+                "/large" => session.insert("user", &"a".repeat(4096))?,
+                "/regenerate" => session.regenerate(),
+                "/destroy" => session.destroy(),
+                "/fail/regenerate" => {
+                    session.regenerate();
+                    return Err(crate::Error::msg("failed"));
+                }
+                "/fail/destroy" => {
+                    session.destroy();
+                    return Err(crate::Error::msg("failed"));
+                }
+                _ => {}
+            }
+            Ok(Response::from(
+                session.get::<String>("user").unwrap_or_default(),
+            ))
+        }))
+        .middleware(Sessions::new(store))
+    }
+
+    // Send a request with the session cookie, return the `Set-Cookie` header and the body.
+    fn send(
+        app: &App<impl Endpoint>,
+        path: &str,
+        cookie: Option<&str>,
+    ) -> (Option<Cookie<'static>>, String) {
+        let mut request = Request::get(path);
+        if let Some(cookie) = cookie {
+            request = request.header(header::COOKIE, format!("id={cookie}"));
+        }
+        let mut response = block_on(app.run(request)).unwrap();
+        let set_cookie = response
+            .get_header(header::SET_COOKIE)
+            .map(|value| Cookie::parse(value.to_str().unwrap().to_owned()).unwrap());
+        let body = block_on(response.into_string()).unwrap().to_string();
+        (set_cookie, body)
+    }
+
+    #[test]
+    fn unchanged_session_is_not_saved() {
+        let store = MemoryStore::new();
+        let app = app(store.clone());
+        let (cookie, _) = send(&app, "/", None);
+        assert!(cookie.is_none());
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn session_is_loaded() {
+        let store = MemoryStore::new();
+        let app = app(store.clone());
+        let (cookie, _) = send(&app, "/set", None);
+        let cookie = cookie.unwrap();
+        assert!(cookie.http_only().unwrap());
+        let (_, body) = send(&app, "/", Some(cookie.value()));
+        assert_eq!(body, "alice");
+        let (_, body) = send(&app, "/", Some("unknown"));
+        assert_eq!(body, "");
+    }
+
+    #[test]
+    fn regenerate() {
+        let store = MemoryStore::new();
+        let app = app(store.clone());
+        let (old, _) = send(&app, "/set", None);
+        let old = old.unwrap();
+        let (new, body) = send(&app, "/regenerate", Some(old.value()));
+        let new = new.unwrap();
+        assert_eq!(body, "alice");
+        assert_ne!(new.value(), old.value());
+        assert_eq!(store.len(), 1);
+        assert_eq!(send(&app, "/", Some(old.value())).1, "");
+        assert_eq!(send(&app, "/", Some(new.value())).1, "alice");
+    }
+
+    #[test]
+    fn destroy() {
+        let store = MemoryStore::new();
+        let app = app(store.clone());
+        let (cookie, _) = send(&app, "/set", None);
+        let cookie = cookie.unwrap();
+        let (removal, _) = send(&app, "/destroy", Some(cookie.value()));
+        let removal = removal.unwrap();
+        assert_eq!(removal.value(), "");
+        assert_eq!(removal.max_age(), Some(cookie::time::Duration::ZERO));
+        assert!(store.is_empty());
+        assert_eq!(send(&app, "/", Some(cookie.value())).1, "");
+    }
+
+    #[test]
+    fn failed_request() {
+        let store = MemoryStore::new();
+        let app = app(store.clone());
+        for path in ["/fail/regenerate", "/fail/destroy"] {
+            let (cookie, _) = send(&app, "/set", None);
+            let cookie = cookie.unwrap();
+            let request =
+                Request::get(path).header(header::COOKIE, format!("id={}", cookie.value()));
+            assert!(block_on(app.run(request)).is_err());
+            assert!(store.is_empty(), "{path}");
+            assert_eq!(send(&app, "/", Some(cookie.value())).1, "", "{path}");
+        }
+    }
+
+    #[test]
+    fn expired_session_is_removed() {
+        let store = MemoryStore::new();
+        let session = Session::new();
+        session.insert("user", "alice").unwrap();
+        session.set_expires(SystemTime::now() - Duration::from_secs(1));
+        let id = block_on(store.save(&session)).unwrap();
+        assert_eq!(store.len(), 1);
+
+        let app = app(store.clone());
+        assert_eq!(send(&app, "/", Some(&id)), (None, String::new()));
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn cookie_session_store() {
+        let app = app(CookieSessionStore::new(Keyring::new(&[1; 32])));
+        let (cookie, _) = send(&app, "/set", None);
+        let cookie = cookie.unwrap();
+        assert_eq!(send(&app, "/", Some(cookie.value())).1, "alice");
+
+        // Tampered or foreign values load as a fresh session.
+        let mut tampered = cookie.value().to_owned().into_bytes();
+        let middle = tampered.len() / 2;
+        tampered[middle] = if tampered[middle] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert_eq!(send(&app, "/", Some(&tampered)), (None, String::new()));
+        assert_eq!(send(&app, "/", Some("garbage")), (None, String::new()));
+        let other = self::app(CookieSessionStore::new(Keyring::new(&[2; 32])));
+        assert_eq!(send(&other, "/", Some(cookie.value())).1, "");
+    }
+
+    #[test]
+    fn cookie_session_store_size_limit() {
+        let app = app(CookieSessionStore::new(Keyring::new(&[1; 32])));
+        let error = block_on(app.run(Request::get("/large"))).unwrap_err();
+        assert_eq!(error.status(), 500);
+        assert!(error
+            .downcast_ref::<super::store::SessionTooLarge>()
+            .is_some());
+    }
+}
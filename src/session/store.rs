@@ -0,0 +1,364 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use async_trait::async_trait;
+use http::StatusCode;
+
+use super::{Session, SessionStore};
+use crate::{
+    cookie::{Cookie, CookieJar, Keyring},
+    Result,
+};
+
+// Serialized sessions with their expiration time, by identifier.
+type Entries = HashMap<String, (String, Option<SystemTime>)>;
+
+/// A store keeping sessions in memory, they are lost once the process exits.
+/// Clones of the store share the same sessions.
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    sessions: Arc<Mutex<Entries>>,
+}
+
+impl Debug for MemoryStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryStore")
+            .field("sessions", &self.sessions.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the number of stored sessions, including the expired ones not removed yet.
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    /// Return `true` if no session is stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn load(&self, cookie: &str) -> Result<Option<Session>> {
+        let sessions = self.sessions.lock().unwrap();
+        Ok(sessions
+            .get(cookie)
+            .and_then(|(json, _)| Session::from_json(json)))
+    }
+
+    async fn save(&self, session: &Session) -> Result<String> {
+        let now = SystemTime::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, (_, expires)| expires.is_none_or(|expires| expires > now));
+        let id = session.id();
+        sessions.insert(id.clone(), (session.to_json(), session.expires()));
+        Ok(id)
+    }
+
+    async fn destroy(&self, cookie: &str) -> Result<()> {
+        self.sessions.lock().unwrap().remove(cookie);
+        Ok(())
+    }
+}
+
+/// A store keeping each session in a JSON file of a directory, only readable by the current user.
+///
+/// Expired sessions are removed when they are loaded.
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    dir: std::path::PathBuf,
+}
+
+#[cfg(feature = "fs")]
+impl FileStore {
+    /// Create a store in `dir`, which is created on the first save if it does not exist.
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    // Return the path of the session, `None` if the identifier was not generated by `Session`.
+    fn path(&self, id: &str) -> Option<std::path::PathBuf> {
+        let valid = id.len() == 64 && id.bytes().all(|byte| byte.is_ascii_hexdigit());
+        valid.then(|| self.dir.join(format!("{id}.json")))
+    }
+}
+
+#[cfg(feature = "fs")]
+#[async_trait]
+impl SessionStore for FileStore {
+    async fn load(&self, cookie: &str) -> Result<Option<Session>> {
+        let Some(path) = self.path(cookie) else {
+            return Ok(None);
+        };
+        let json = match async_fs::read_to_string(&path).await {
+            Ok(json) => json,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let session = Session::from_json(&json);
+        if session.as_ref().is_none_or(Session::is_expired) {
+            let _ = async_fs::remove_file(&path).await;
+            return Ok(None);
+        }
+        Ok(session)
+    }
+
+    async fn save(&self, session: &Session) -> Result<String> {
+        let id = session.id();
+        let path = self.path(&id).ok_or_else(|| {
+            crate::Error::new(InvalidSessionId::new(), StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+        async_fs::create_dir_all(&self.dir).await?;
+        // Write to a temporary file first, so that a session is never read half-written.
+        // Its name is unique to this write, as the same session may be saved concurrently.
+        let temp = path.with_extension(format!("{:016x}.tmp", rand::random::<u64>()));
+        let written = async {
+            use futures_lite::AsyncWriteExt;
+
+            let mut options = async_fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use async_fs::unix::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            let mut file = options.open(&temp).await?;
+            file.write_all(session.to_json().as_bytes()).await?;
+            file.flush().await?;
+            async_fs::rename(&temp, &path).await
+        }
+        .await;
+        if let Err(error) = written {
+            let _ = async_fs::remove_file(&temp).await;
+            return Err(error.into());
+        }
+        Ok(id)
+    }
+
+    async fn destroy(&self, cookie: &str) -> Result<()> {
+        if let Some(path) = self.path(cookie) {
+            match async_fs::remove_file(path).await {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(error.into())
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "fs")]
+impl_error!(InvalidSessionId, "Session identifier is malformed");
+
+impl_error!(
+    SessionTooLarge,
+    "Session is too large to be stored in a cookie"
+);
+
+// The name used as the associated data when encrypting sessions.
+const COOKIE_NAME: &str = "session";
+
+// The maximum size of a cookie value accepted by browsers, with some room for the attributes.
+const MAX_COOKIE_SIZE: usize = 4000;
+
+/// A store keeping sessions in the session cookie itself, encrypted with the keys of a [`Keyring`].
+///
+/// Nothing is stored on the server, so a destroyed session can be restored by a client keeping the old cookie until it expires.
+/// Sessions larger than about 4 KB can not be stored.
+#[derive(Debug, Clone)]
+pub struct CookieSessionStore {
+    keys: Keyring,
+}
+
+impl CookieSessionStore {
+    /// Create a store encrypting sessions with `keys`.
+    pub fn new(keys: Keyring) -> Self {
+        Self { keys }
+    }
+}
+
+#[async_trait]
+impl SessionStore for CookieSessionStore {
+    async fn load(&self, cookie: &str) -> Result<Option<Session>> {
+        let cookie = Cookie::new(COOKIE_NAME, cookie.to_owned());
+        Ok(self
+            .keys
+            .private(CookieJar::new())
+            .decrypt(cookie)
+            .and_then(|cookie| Session::from_json(cookie.value())))
+    }
+
+    async fn save(&self, session: &Session) -> Result<String> {
+        let mut jar = CookieJar::new();
+        self.keys
+            .private(&mut jar)
+            .add(Cookie::new(COOKIE_NAME, session.to_json()));
+        let value = jar.get(COOKIE_NAME).unwrap().value().to_owned();
+        if value.len() > MAX_COOKIE_SIZE {
+            return Err(crate::Error::new(
+                SessionTooLarge::new(),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+        Ok(value)
+    }
+
+    async fn destroy(&self, _cookie: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::MemoryStore;
+    use crate::session::{Session, SessionStore};
+
+    #[test]
+    fn memory_store() {
+        block_on(async {
+            let store = MemoryStore::new();
+            let session = Session::new();
+            session.insert("user", "alice").unwrap();
+            let id = store.save(&session).await.unwrap();
+            let loaded = store.load(&id).await.unwrap().unwrap();
+            assert_eq!(loaded.get::<String>("user").unwrap(), "alice");
+            store.destroy(&id).await.unwrap();
+            assert!(store.load(&id).await.unwrap().is_none());
+        });
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn file_store_concurrent_saves() {
+        use super::FileStore;
+
+        let dir =
+            std::env::temp_dir().join(format!("http-kit-sessions-{:016x}", rand::random::<u64>()));
+        let store = FileStore::new(&dir);
+        let session = Session::new();
+        session.insert("user", "alice").unwrap();
+        let tasks: Vec<_> = (0..16)
+            .map(|_| {
+                let (store, session) = (store.clone(), session.clone());
+                async_std::task::spawn(async move { store.save(&session).await.unwrap() })
+            })
+            .collect();
+        let id = block_on(async {
+            let mut id = String::new();
+            for task in tasks {
+                id = task.await;
+            }
+            id
+        });
+
+        let loaded = block_on(store.load(&id)).unwrap().unwrap();
+        assert_eq!(loaded.get::<String>("user").unwrap(), "alice");
+        let files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files, [dir.join(format!("{id}.json"))]);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&files[0]).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "fs")]
+    fn file_store() -> (super::FileStore, std::path::PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("http-kit-sessions-{:016x}", rand::random::<u64>()));
+        (super::FileStore::new(&dir), dir)
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn file_store_load() {
+        let (store, dir) = file_store();
+        block_on(async {
+            let session = Session::new();
+            assert!(store.load(&session.id()).await.unwrap().is_none());
+            session.insert("user", "alice").unwrap();
+            let id = store.save(&session).await.unwrap();
+            assert_eq!(id, session.id());
+            let loaded = store.load(&id).await.unwrap().unwrap();
+            assert_eq!(loaded.get::<String>("user").unwrap(), "alice");
+
+            // A corrupt file is removed and loads nothing.
+            let path = dir.join(format!("{id}.json"));
+            std::fs::write(&path, "{").unwrap();
+            assert!(store.load(&id).await.unwrap().is_none());
+            assert!(!path.exists());
+
+            store.destroy(&id).await.unwrap();
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn file_store_expiry() {
+        use std::time::{Duration, SystemTime};
+
+        let (store, dir) = file_store();
+        block_on(async {
+            let session = Session::new();
+            session.insert("user", "alice").unwrap();
+            session.set_expires(SystemTime::now() - Duration::from_secs(60));
+            let id = store.save(&session).await.unwrap();
+            let path = dir.join(format!("{id}.json"));
+            assert!(path.exists());
+            assert!(store.load(&id).await.unwrap().is_none());
+            assert!(!path.exists());
+
+            session.set_expires(SystemTime::now() + Duration::from_secs(60));
+            let id = store.save(&session).await.unwrap();
+            assert!(store.load(&id).await.unwrap().is_some());
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn file_store_rejects_malformed_ids() {
+        let (store, dir) = file_store();
+        std::fs::create_dir_all(&dir).unwrap();
+        let outside = dir.with_extension("json");
+        std::fs::write(&outside, Session::new().to_json()).unwrap();
+        let name = dir.file_name().unwrap().to_str().unwrap();
+        block_on(async {
+            for id in [
+                "",
+                "abc",
+                &format!("../{name}"),
+                &"g".repeat(64),
+                &format!("{}/", "a".repeat(63)),
+            ] {
+                assert!(store.load(id).await.unwrap().is_none(), "{id}");
+                store.destroy(id).await.unwrap();
+            }
+        });
+        assert!(outside.exists());
+        std::fs::remove_file(&outside).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
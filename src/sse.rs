@@ -0,0 +1,544 @@
+//! Server-Sent Events (`text/event-stream`).
+//!
+//! [`Sse`] turns a stream of [`Event`]s into a response, and [`EventStream`] parses a body back into events.
+//!
+//! # Example
+//! ```rust
+//! use futures_lite::{stream, StreamExt};
+//! use http_kit::{
+//!     sse::{Event, EventStream, Sse},
+//!     IntoResponse,
+//! };
+//! use std::time::Duration;
+//!
+//! # async_std::task::block_on(async {
+//! let events = stream::iter([
+//!     Event::new().event("greeting").data("Hello,\nworld"),
+//!     Event::new().id("2").data("Bye"),
+//! ]);
+//! let mut response = Sse::new(events)
+//!     .keep_alive(Duration::from_secs(15))
+//!     .into_response();
+//!
+//! let mut events = EventStream::new(response.take_body().unwrap());
+//! let event = events.try_next().await.unwrap().unwrap();
+//! assert_eq!(event.get_event(), Some("greeting"));
+//! assert_eq!(event.get_data(), "Hello,\nworld");
+//! let event = events.try_next().await.unwrap().unwrap();
+//! assert_eq!(event.get_id(), Some("2"));
+//! assert!(events.try_next().await.unwrap().is_none());
+//! # })
+//! ```
+
+use std::{
+    fmt::{Debug, Write},
+    mem::take,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_io::Timer;
+use bytes::Bytes;
+use futures_lite::{ready, AsyncBufRead, Stream};
+use http::{header, HeaderValue, StatusCode};
+
+use crate::{Body, BodyError, IntoResponse, Response};
+
+/// An event of a `text/event-stream`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    data: String,
+    retry: Option<Duration>,
+    comment: Option<String>,
+}
+
+fn assert_single_line(value: &str, field: &str) {
+    assert!(
+        !value.contains(['\r', '\n']),
+        "The {field} of an event must not contain line breaks"
+    );
+}
+
+impl Event {
+    /// Create an empty event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the data of the event, which may contain multiple lines.
+    pub fn data(mut self, data: impl Into<String>) -> Self {
+        self.data = data.into();
+        self
+    }
+
+    /// Set the data of the event by serializing a object into JSON.
+    #[cfg(feature = "json")]
+    pub fn json_data<T: serde::Serialize>(self, value: T) -> Result<Self, serde_json::Error> {
+        Ok(self.data(serde_json::to_string(&value)?))
+    }
+
+    /// Set the id of the event.
+    ///
+    /// # Panics
+    /// Panic if `id` contains a line break.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        let id = id.into();
+        assert_single_line(&id, "id");
+        self.id = Some(id);
+        self
+    }
+
+    /// Set the type of the event.
+    ///
+    /// # Panics
+    /// Panic if `event` contains a line break.
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        let event = event.into();
+        assert_single_line(&event, "type");
+        self.event = Some(event);
+        self
+    }
+
+    /// Set the reconnection time of the client.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Set a comment, which is ignored by clients.
+    ///
+    /// # Panics
+    /// Panic if `comment` contains a line break.
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        let comment = comment.into();
+        assert_single_line(&comment, "comment");
+        self.comment = Some(comment);
+        self
+    }
+
+    /// Return the data of the event.
+    pub fn get_data(&self) -> &str {
+        &self.data
+    }
+
+    /// Return the id of the event.
+    pub fn get_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Return the type of the event.
+    pub fn get_event(&self) -> Option<&str> {
+        self.event.as_deref()
+    }
+
+    /// Return the reconnection time of the client.
+    pub const fn get_retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Try to deserialize the data of the event as JSON.
+    #[cfg(feature = "json")]
+    pub fn into_json<'a, T>(&'a self) -> Result<T, serde_json::Error>
+    where
+        T: serde::Deserialize<'a>,
+    {
+        serde_json::from_str(&self.data)
+    }
+
+    /// Encode the event in the `text/event-stream` format.
+    pub fn encode(&self) -> Bytes {
+        let mut buf = String::new();
+        if let Some(comment) = &self.comment {
+            let _ = writeln!(buf, ":{comment}");
+        }
+        if let Some(event) = &self.event {
+            let _ = writeln!(buf, "event: {event}");
+        }
+        if let Some(id) = &self.id {
+            let _ = writeln!(buf, "id: {id}");
+        }
+        if let Some(retry) = self.retry {
+            let _ = writeln!(buf, "retry: {}", retry.as_millis());
+        }
+        if !self.data.is_empty() {
+            for line in lines(&self.data) {
+                let _ = writeln!(buf, "data: {line}");
+            }
+        }
+        buf.push('\n');
+        buf.into()
+    }
+}
+
+// Split by CRLF, LF or CR.
+fn lines(data: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(data);
+    std::iter::from_fn(move || {
+        let data = rest?;
+        match data.find(['\r', '\n']) {
+            Some(index) => {
+                let skip = if data[index..].starts_with("\r\n") {
+                    2
+                } else {
+                    1
+                };
+                rest = Some(&data[index + skip..]);
+                Some(&data[..index])
+            }
+            None => {
+                rest = None;
+                Some(data)
+            }
+        }
+    })
+}
+
+/// A response streaming Server-Sent Events.
+pub struct Sse<S> {
+    events: S,
+    keep_alive: Option<Duration>,
+}
+
+impl<S> Debug for Sse<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sse")
+            .field("keep_alive", &self.keep_alive)
+            .finish()
+    }
+}
+
+impl<S> Sse<S>
+where
+    S: Stream<Item = Event> + Send + Sync + 'static,
+{
+    /// Create a response streaming `events`.
+    pub const fn new(events: S) -> Self {
+        Self {
+            events,
+            keep_alive: None,
+        }
+    }
+
+    /// Send a comment if no event is sent for `interval`, keeping the connection alive through proxies.
+    pub const fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Return the body streaming the encoded events.
+    pub fn into_body(self) -> Body {
+        Body::from_stream(Encoder {
+            events: Box::pin(self.events),
+            keep_alive: self
+                .keep_alive
+                .map(|interval| (Timer::interval(interval), interval)),
+        })
+    }
+}
+
+impl<S> IntoResponse for Sse<S>
+where
+    S: Stream<Item = Event> + Send + Sync + 'static,
+{
+    fn into_response(self) -> Response {
+        let mut response = Response::new(StatusCode::OK, self.into_body());
+        response.insert_header(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/event-stream"),
+        );
+        response.insert_header(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        response
+    }
+}
+
+struct Encoder<S> {
+    events: Pin<Box<S>>,
+    keep_alive: Option<(Timer, Duration)>,
+}
+
+impl<S: Stream<Item = Event>> Stream for Encoder<S> {
+    type Item = Result<Bytes, std::convert::Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Poll::Ready(event) = self.events.as_mut().poll_next(cx) {
+            let Some(event) = event else {
+                return Poll::Ready(None);
+            };
+            // Restart the keep-alive interval.
+            if let Some((timer, interval)) = &mut self.keep_alive {
+                timer.set_interval(*interval);
+            }
+            return Poll::Ready(Some(Ok(event.encode())));
+        }
+        if let Some((timer, _)) = &mut self.keep_alive {
+            if Pin::new(timer).poll_next(cx).is_ready() {
+                return Poll::Ready(Some(Ok(Bytes::from_static(b":\n\n"))));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+// The default maximum size of an event received by `EventStream`.
+const DEFAULT_MAX_EVENT_SIZE: usize = 1024 * 1024;
+
+/// A stream parsing a `text/event-stream` body into events.
+///
+/// As specified by the HTML standard, blocks without data are not dispatched,
+/// and the id of an event is the last id received in the stream.
+/// The reconnection time is available from [`EventStream::retry`] as soon as it is received,
+/// even if its block is not dispatched.
+/// A line or an event larger than [`EventStream::max_event_size`] fails with [`BodyError::TooLarge`].
+pub struct EventStream {
+    reader: Pin<Box<dyn AsyncBufRead + Send + Sync + 'static>>,
+    line: Vec<u8>,
+    max_event_size: usize,
+    // A CR was the last line break, so that a following LF is skipped.
+    after_cr: bool,
+    started: bool,
+    last_id: Option<String>,
+    retry: Option<Duration>,
+    pending: Event,
+}
+
+impl Debug for EventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventStream")
+            .field("last_id", &self.last_id)
+            .field("retry", &self.retry)
+            .finish()
+    }
+}
+
+impl EventStream {
+    /// Create a stream parsing `body`.
+    pub fn new(body: Body) -> Self {
+        Self {
+            reader: Box::pin(body.into_reader()),
+            line: Vec::new(),
+            max_event_size: DEFAULT_MAX_EVENT_SIZE,
+            after_cr: false,
+            started: false,
+            last_id: None,
+            retry: None,
+            pending: Event::new(),
+        }
+    }
+
+    /// Set the maximum size of an event, including the line being read, 1 MiB by default.
+    pub const fn max_event_size(mut self, size: usize) -> Self {
+        self.max_event_size = size;
+        self
+    }
+
+    /// Return the id of the last event, which should be sent as `Last-Event-ID` when reconnecting.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_id.as_deref()
+    }
+
+    /// Return the reconnection time last received, which the client should wait before reconnecting.
+    pub const fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    // Read a line without the line break, return `false` at EOF.
+    fn poll_line(&mut self, cx: &mut Context<'_>) -> Poll<Result<bool, BodyError>> {
+        loop {
+            let buf = ready!(self.reader.as_mut().poll_fill_buf(cx))?;
+            if buf.is_empty() {
+                return Poll::Ready(Ok(!self.line.is_empty()));
+            }
+            let mut buf = buf;
+            let mut skipped = 0;
+            if take(&mut self.after_cr) && buf[0] == b'\n' {
+                buf = &buf[1..];
+                skipped = 1;
+            }
+            let end = buf.iter().position(|&byte| byte == b'\r' || byte == b'\n');
+            // Nothing is consumed, so that the error is returned again if the stream is polled further.
+            let pending = self.pending.data.len() + self.line.len();
+            if pending + end.unwrap_or(buf.len()) > self.max_event_size {
+                self.after_cr = skipped == 1;
+                return Poll::Ready(Err(BodyError::TooLarge));
+            }
+            match end {
+                Some(index) => {
+                    self.line.extend_from_slice(&buf[..index]);
+                    self.after_cr = buf[index] == b'\r';
+                    self.reader.as_mut().consume(skipped + index + 1);
+                    return Poll::Ready(Ok(true));
+                }
+                None => {
+                    self.line.extend_from_slice(buf);
+                    let len = skipped + buf.len();
+                    self.reader.as_mut().consume(len);
+                }
+            }
+        }
+    }
+
+    fn process_line(&mut self) -> Option<Event> {
+        let mut line = take(&mut self.line);
+        if !self.started {
+            self.started = true;
+            if line.starts_with("\u{feff}".as_bytes()) {
+                line.drain(..3);
+            }
+        }
+        let line = String::from_utf8_lossy(&line);
+        if line.is_empty() {
+            let mut event = take(&mut self.pending);
+            if event.data.is_empty() {
+                return None;
+            }
+            event.data.pop();
+            event.id.clone_from(&self.last_id);
+            return Some(event);
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_ref(), ""),
+        };
+        match field {
+            "data" => {
+                self.pending.data.push_str(value);
+                self.pending.data.push('\n');
+            }
+            "event" => self.pending.event = Some(value.to_owned()),
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_owned()),
+            "retry" if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) => {
+                if let Ok(retry) = value.parse().map(Duration::from_millis) {
+                    self.retry = Some(retry);
+                    self.pending.retry = Some(retry);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Result<Event, BodyError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            // An incomplete event at EOF is discarded.
+            if !ready!(self.poll_line(cx))? {
+                return Poll::Ready(None);
+            }
+            if let Some(event) = self.process_line() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use futures_lite::{future::block_on, stream, StreamExt};
+
+    use super::{Event, EventStream, Sse};
+    use crate::{Body, BodyError};
+
+    fn parse(input: &'static str) -> (Vec<Event>, EventStream) {
+        let mut stream = EventStream::new(Body::from(input));
+        let events = block_on(async {
+            let mut events = Vec::new();
+            while let Some(event) = stream.try_next().await.unwrap() {
+                events.push(event);
+            }
+            events
+        });
+        (events, stream)
+    }
+
+    #[test]
+    fn retry_only_block() {
+        let (events, stream) = parse("retry: 3000\n\ndata: a\n\n");
+        assert_eq!(events, [Event::new().data("a")]);
+        assert_eq!(stream.retry(), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn invalid_retry_is_ignored() {
+        let (_, stream) = parse("retry: 1000\n\nretry: 2s\n\nretry:\n\n");
+        assert_eq!(stream.retry(), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn line_breaks() {
+        let (events, _) = parse("\u{feff}data: a\r\ndata:b\rdata\n\r\n");
+        assert_eq!(events, [Event::new().data("a\nb\n")]);
+    }
+
+    #[test]
+    fn fields() {
+        let (events, stream) = parse(
+            ": comment\nevent: greeting\nid: 1\ndata: a\n\nid: 2\n\ndata: b\nunknown: c\n\ndata: d",
+        );
+        assert_eq!(
+            events,
+            [
+                Event::new().event("greeting").id("1").data("a"),
+                Event::new().id("2").data("b"),
+            ]
+        );
+        assert_eq!(stream.last_event_id(), Some("2"));
+    }
+
+    #[test]
+    fn event_size_is_limited() {
+        // A line without a line break.
+        let mut stream = EventStream::new(Body::from("data: ".repeat(100))).max_event_size(64);
+        let error = block_on(stream.try_next()).unwrap_err();
+        assert!(matches!(error, BodyError::TooLarge));
+        assert!(matches!(
+            block_on(stream.try_next()),
+            Err(BodyError::TooLarge)
+        ));
+
+        // An event without a blank line.
+        let mut stream =
+            EventStream::new(Body::from("data: 0123456789\n".repeat(10))).max_event_size(64);
+        let error = block_on(stream.try_next()).unwrap_err();
+        assert!(matches!(error, BodyError::TooLarge));
+
+        let stream =
+            EventStream::new(Body::from("data: 0123456789\n\n".repeat(10))).max_event_size(64);
+        let events = block_on(stream.try_collect::<_, _, Vec<_>>()).unwrap();
+        assert_eq!(events.len(), 10);
+    }
+
+    #[test]
+    fn keep_alive() {
+        let interval = Duration::from_millis(50);
+        let events = stream::once(Event::new().data("a")).chain(stream::pending());
+        let mut body = Sse::new(events).keep_alive(interval).into_body();
+
+        assert_eq!(block_on(body.next()).unwrap().unwrap(), "data: a\n\n");
+        // The interval restarts after an event, so the comment can not come earlier.
+        let start = Instant::now();
+        assert_eq!(block_on(body.next()).unwrap().unwrap(), ":\n\n");
+        assert!(start.elapsed() >= interval);
+        assert_eq!(block_on(body.next()).unwrap().unwrap(), ":\n\n");
+    }
+
+    #[test]
+    fn round_trip() {
+        let event = Event::new()
+            .event("greeting")
+            .id("1")
+            .retry(Duration::from_millis(500))
+            .data("Hello,\nworld");
+        let encoded = event.encode();
+        let mut stream = EventStream::new(Body::from(encoded));
+        let parsed = block_on(stream.try_next()).unwrap().unwrap();
+        assert_eq!(parsed, event);
+    }
+}
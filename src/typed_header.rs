@@ -0,0 +1,1305 @@
+//! Typed access to common HTTP headers.
+//!
+//! A [`TypedHeader`] decodes the values of a header into a Rust type and encodes it back.
+//! `Request::typed_header` fails with `400 Bad Request` if the header is malformed.
+//!
+//! # Example
+//! ```rust
+//! use http_kit::{
+//!     header,
+//!     typed_header::{CacheControl, EntityTag, IfNoneMatch},
+//!     Request, Response,
+//! };
+//! use std::time::Duration;
+//!
+//! let request = Request::get("/").header(header::IF_NONE_MATCH, "W/\"1\", \"2\"");
+//! let if_none_match = request.typed_header::<IfNoneMatch>().unwrap().unwrap();
+//! assert!(if_none_match.matches(&EntityTag::strong("1")));
+//!
+//! let mut response = Response::empty();
+//! response.set_typed_header(CacheControl::new().public().max_age(Duration::from_secs(60)));
+//! assert_eq!(
+//!     response.get_header(header::CACHE_CONTROL).unwrap(),
+//!     "public, max-age=60"
+//! );
+//! ```
+
+use std::{
+    borrow::Cow,
+    fmt::{Debug, Display},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use http::{
+    header::{self, HeaderName},
+    HeaderMap, HeaderValue, Method, StatusCode,
+};
+
+use crate::{
+    content_type::is_token,
+    date::{format_http_date, parse_http_date},
+    ResultExt,
+};
+
+/// A header which can be decoded from and encoded to header values.
+pub trait TypedHeader: Sized {
+    /// Return the name of the header.
+    fn name() -> HeaderName;
+
+    /// Decode the header from all of its values, in order.
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader>;
+
+    /// Encode the header as a value.
+    fn encode(&self) -> HeaderValue;
+}
+
+/// The error type of malformed headers.
+#[derive(Debug)]
+pub struct InvalidHeader {
+    name: HeaderName,
+}
+
+impl InvalidHeader {
+    /// Create an error for the malformed header `H`.
+    pub fn new<H: TypedHeader>() -> Self {
+        Self { name: H::name() }
+    }
+
+    /// Return the name of the malformed header.
+    pub fn name(&self) -> &HeaderName {
+        &self.name
+    }
+}
+
+impl Display for InvalidHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Header `{}` is malformed", self.name)
+    }
+}
+
+impl std::error::Error for InvalidHeader {}
+
+// Decode `H` from `headers`, return `None` if it is missing.
+pub(crate) fn decode<H: TypedHeader>(headers: &HeaderMap) -> Result<Option<H>, crate::Error> {
+    let mut values = headers.get_all(H::name()).iter().peekable();
+    if values.peek().is_none() {
+        return Ok(None);
+    }
+    H::decode(values).map(Some).status(StatusCode::BAD_REQUEST)
+}
+
+// Return the value of a header which can not be repeated.
+fn single<'a>(mut values: impl Iterator<Item = &'a HeaderValue>) -> Option<&'a str> {
+    match (values.next(), values.next()) {
+        (Some(value), None) => value.to_str().ok().map(str::trim),
+        _ => None,
+    }
+}
+
+// Split the values of a list header into non-empty elements, commas in quoted strings are kept.
+fn list<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Option<Vec<&'a str>> {
+    let mut elements = Vec::new();
+    for value in values {
+        let value = value.to_str().ok()?;
+        let (mut start, mut quoted, mut escaped) = (0, false, false);
+        for (index, byte) in value.bytes().enumerate() {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' if quoted => escaped = true,
+                b'"' => quoted = !quoted,
+                b',' if !quoted => {
+                    elements.push(value[start..index].trim());
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+        if quoted {
+            return None;
+        }
+        elements.push(value[start..].trim());
+    }
+    elements.retain(|element| !element.is_empty());
+    Some(elements)
+}
+
+// Parse a non-negative decimal integer, signs are rejected unlike `str::parse`.
+fn digits(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// The `Content-Type` header, the media type of the body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType(Cow<'static, str>);
+
+impl ContentType {
+    /// Create the header from a media type, parameters included.
+    ///
+    /// # Panics
+    /// Panics if `media_type` is not a valid media type.
+    pub fn new(media_type: impl Into<Cow<'static, str>>) -> Self {
+        let media_type = media_type.into();
+        assert!(
+            crate::content_type::essence(&media_type).is_some()
+                && HeaderValue::from_str(&media_type).is_ok(),
+            "Invalid media type `{media_type}`"
+        );
+        Self(media_type)
+    }
+
+    /// `application/json`.
+    pub fn json() -> Self {
+        Self(Cow::Borrowed("application/json"))
+    }
+
+    /// `application/x-www-form-urlencoded`.
+    pub fn form() -> Self {
+        Self(Cow::Borrowed("application/x-www-form-urlencoded"))
+    }
+
+    /// `text/html; charset=utf-8`.
+    pub fn html() -> Self {
+        Self(Cow::Borrowed("text/html; charset=utf-8"))
+    }
+
+    /// `text/plain; charset=utf-8`.
+    pub fn text() -> Self {
+        Self(Cow::Borrowed("text/plain; charset=utf-8"))
+    }
+
+    /// `application/octet-stream`.
+    pub fn octet_stream() -> Self {
+        Self(Cow::Borrowed("application/octet-stream"))
+    }
+
+    /// Return the media type without parameters, such as `text/html`.
+    pub fn essence(&self) -> &str {
+        self.0.split(';').next().unwrap_or_default().trim()
+    }
+
+    /// Return the value of the parameter `name`, without quotes.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.0.split(';').skip(1).find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            key.trim()
+                .eq_ignore_ascii_case(name)
+                .then(|| unquote(value.trim()))
+        })
+    }
+
+    /// Return the media type with parameters.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TypedHeader for ContentType {
+    fn name() -> HeaderName {
+        header::CONTENT_TYPE
+    }
+
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader> {
+        single(values)
+            .filter(|value| crate::content_type::essence(value).is_some())
+            .map(|value| Self(Cow::Owned(value.to_owned())))
+            .ok_or_else(InvalidHeader::new::<Self>)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from_str(&self.0).unwrap()
+    }
+}
+
+/// The `Content-Length` header, the size of the body in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentLength(pub u64);
+
+impl TypedHeader for ContentLength {
+    fn name() -> HeaderName {
+        header::CONTENT_LENGTH
+    }
+
+    // Repeated values are accepted if they are identical.
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader> {
+        let lengths = list(values)
+            .unwrap_or_default()
+            .into_iter()
+            .map(digits)
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        match lengths[..] {
+            [length, ref rest @ ..] if rest.iter().all(|other| *other == length) => {
+                Ok(Self(length))
+            }
+            _ => Err(InvalidHeader::new::<Self>()),
+        }
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from(self.0)
+    }
+}
+
+/// The `Authorization` header, the credentials of the client.
+///
+/// Credentials are redacted from the `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Authorization {
+    scheme: String,
+    credentials: String,
+}
+
+impl Debug for Authorization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Authorization")
+            .field("scheme", &self.scheme)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Authorization {
+    /// Create the header from an authentication scheme and its credentials.
+    ///
+    /// # Panics
+    /// Panics if `scheme` is not a token or `credentials` contains characters not allowed in a header.
+    pub fn new(scheme: impl Into<String>, credentials: impl Into<String>) -> Self {
+        let (scheme, credentials) = (scheme.into(), credentials.into());
+        assert!(
+            is_token(&scheme),
+            "Invalid authentication scheme `{scheme}`"
+        );
+        assert!(
+            HeaderValue::from_str(&credentials).is_ok(),
+            "Invalid credentials"
+        );
+        Self {
+            scheme,
+            credentials: credentials.trim().to_owned(),
+        }
+    }
+
+    /// Create `Basic` credentials.
+    ///
+    /// # Panics
+    /// Panics if `user_id` contains a colon.
+    pub fn basic(user_id: &str, password: &str) -> Self {
+        assert!(!user_id.contains(':'), "User-id contains a colon");
+        Self {
+            scheme: "Basic".to_owned(),
+            credentials: base64_encode(format!("{user_id}:{password}").as_bytes()),
+        }
+    }
+
+    /// Create `Bearer` credentials.
+    ///
+    /// # Panics
+    /// Panics if `token` contains characters not allowed in a header.
+    pub fn bearer(token: impl Into<String>) -> Self {
+        Self::new("Bearer", token)
+    }
+
+    /// Return the authentication scheme.
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Return the credentials as sent.
+    pub fn credentials(&self) -> &str {
+        &self.credentials
+    }
+
+    /// Return the user-id and the password of `Basic` credentials.
+    pub fn as_basic(&self) -> Option<(String, String)> {
+        if !self.scheme.eq_ignore_ascii_case("basic") {
+            return None;
+        }
+        let decoded = String::from_utf8(base64_decode(&self.credentials)?).ok()?;
+        let (user_id, password) = decoded.split_once(':')?;
+        Some((user_id.to_owned(), password.to_owned()))
+    }
+
+    /// Return the token of `Bearer` credentials.
+    pub fn as_bearer(&self) -> Option<&str> {
+        (self.scheme.eq_ignore_ascii_case("bearer") && !self.credentials.is_empty())
+            .then_some(self.credentials.as_str())
+    }
+}
+
+impl TypedHeader for Authorization {
+    fn name() -> HeaderName {
+        header::AUTHORIZATION
+    }
+
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader> {
+        let value = single(values).ok_or_else(InvalidHeader::new::<Self>)?;
+        let (scheme, credentials) = value.split_once(' ').unwrap_or((value, ""));
+        if !is_token(scheme) {
+            return Err(InvalidHeader::new::<Self>());
+        }
+        Ok(Self {
+            scheme: scheme.to_owned(),
+            credentials: credentials.trim().to_owned(),
+        })
+    }
+
+    fn encode(&self) -> HeaderValue {
+        let value = if self.credentials.is_empty() {
+            self.scheme.clone()
+        } else {
+            format!("{} {}", self.scheme, self.credentials)
+        };
+        let mut value = HeaderValue::from_str(&value).unwrap();
+        value.set_sensitive(true);
+        value
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(input: &[u8]) -> String {
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let mut bytes = [0; 4];
+        bytes[1..=chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes(bytes);
+        for index in 0..4 {
+            if index <= chunk.len() {
+                output.push(BASE64[(bits >> (18 - 6 * index)) as usize & 63] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+// Decode canonical padded base64, unused bits of the last digit must be zero.
+fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    if input.len() % 4 != 0 {
+        return None;
+    }
+    let digits = input.trim_end_matches('=');
+    let padding = input.len() - digits.len();
+    if padding > 2 {
+        return None;
+    }
+    let mut output = Vec::with_capacity(input.len() / 4 * 3);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for byte in digits.bytes() {
+        let value = BASE64.iter().position(|&digit| digit == byte)? as u32;
+        buffer = (buffer << 6 | value) & 0xFFFF;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    (buffer & ((1 << bits) - 1) == 0).then_some(output)
+}
+
+/// The `Cache-Control` header, the directives for caches.
+///
+/// Unknown directives are ignored, and the field names of `no-cache` and `private` are not kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    no_cache: bool,
+    no_store: bool,
+    no_transform: bool,
+    must_revalidate: bool,
+    proxy_revalidate: bool,
+    must_understand: bool,
+    public: bool,
+    private: bool,
+    immutable: bool,
+    only_if_cached: bool,
+    max_age: Option<Duration>,
+    s_maxage: Option<Duration>,
+    max_stale: Option<Duration>,
+    min_fresh: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+    stale_if_error: Option<Duration>,
+}
+
+macro_rules! cache_directives {
+    (
+        flags { $($flag:ident, $is_flag:ident, $flag_name:literal;)* }
+        durations { $($duration:ident, $get_duration:ident, $duration_name:literal;)* }
+    ) => {
+        impl CacheControl {
+            /// Create the header without directives.
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            $(
+                #[doc = concat!("Set the `", $flag_name, "` directive.")]
+                pub fn $flag(mut self) -> Self {
+                    self.$flag = true;
+                    self
+                }
+
+                #[doc = concat!("Return `true` if the `", $flag_name, "` directive is set.")]
+                pub fn $is_flag(&self) -> bool {
+                    self.$flag
+                }
+            )*
+
+            $(
+                #[doc = concat!("Set the `", $duration_name, "` directive, in whole seconds.")]
+                pub fn $duration(mut self, duration: Duration) -> Self {
+                    self.$duration = Some(duration);
+                    self
+                }
+
+                #[doc = concat!("Return the `", $duration_name, "` directive.")]
+                pub fn $get_duration(&self) -> Option<Duration> {
+                    self.$duration
+                }
+            )*
+
+            fn set(&mut self, name: &str, value: Option<&str>) -> Option<()> {
+                $(
+                    if name.eq_ignore_ascii_case($flag_name) {
+                        self.$flag = true;
+                        return Some(());
+                    }
+                )*
+                $(
+                    if name.eq_ignore_ascii_case($duration_name) {
+                        self.$duration = Some(match value {
+                            Some(value) => delta_seconds(value)?,
+                            // A stale response of any age is accepted.
+                            None if name.eq_ignore_ascii_case("max-stale") => Duration::MAX,
+                            None => return None,
+                        });
+                    }
+                )*
+                Some(())
+            }
+
+            fn directives(&self) -> Vec<String> {
+                let mut directives = Vec::new();
+                $(
+                    if self.$flag {
+                        directives.push($flag_name.to_owned());
+                    }
+                )*
+                $(
+                    match self.$duration {
+                        Some(Duration::MAX) if $duration_name == "max-stale" => {
+                            directives.push($duration_name.to_owned())
+                        }
+                        Some(duration) => {
+                            directives.push(format!("{}={}", $duration_name, duration.as_secs()))
+                        }
+                        None => {}
+                    }
+                )*
+                directives
+            }
+        }
+    };
+}
+
+cache_directives! {
+    flags {
+        no_cache, is_no_cache, "no-cache";
+        no_store, is_no_store, "no-store";
+        no_transform, is_no_transform, "no-transform";
+        must_revalidate, is_must_revalidate, "must-revalidate";
+        proxy_revalidate, is_proxy_revalidate, "proxy-revalidate";
+        must_understand, is_must_understand, "must-understand";
+        public, is_public, "public";
+        private, is_private, "private";
+        immutable, is_immutable, "immutable";
+        only_if_cached, is_only_if_cached, "only-if-cached";
+    }
+    durations {
+        max_age, get_max_age, "max-age";
+        s_maxage, get_s_maxage, "s-maxage";
+        max_stale, get_max_stale, "max-stale";
+        min_fresh, get_min_fresh, "min-fresh";
+        stale_while_revalidate, get_stale_while_revalidate, "stale-while-revalidate";
+        stale_if_error, get_stale_if_error, "stale-if-error";
+    }
+}
+
+// Parse delta-seconds, which may be quoted, too large values are saturated.
+fn delta_seconds(value: &str) -> Option<Duration> {
+    let value = unquote(value);
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    Some(Duration::from_secs(value.parse().unwrap_or(u64::MAX)))
+}
+
+impl TypedHeader for CacheControl {
+    fn name() -> HeaderName {
+        header::CACHE_CONTROL
+    }
+
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader> {
+        let mut cache_control = Self::new();
+        for directive in list(values).ok_or_else(InvalidHeader::new::<Self>)? {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim())),
+                None => (directive, None),
+            };
+            cache_control
+                .set(name, value)
+                .ok_or_else(InvalidHeader::new::<Self>)?;
+        }
+        Ok(cache_control)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from_str(&self.directives().join(", ")).unwrap()
+    }
+}
+
+/// A range of bytes requested by the `Range` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// The bytes from the first position to the last position, inclusive.
+    FromTo(u64, u64),
+    /// The bytes from the position to the end.
+    From(u64),
+    /// The last bytes of the given length.
+    Last(u64),
+}
+
+impl ByteRange {
+    /// Resolve the range against a representation of `len` bytes into inclusive positions,
+    /// return `None` if it is unsatisfiable.
+    pub fn resolve(&self, len: u64) -> Option<(u64, u64)> {
+        match *self {
+            Self::FromTo(start, end) => (start < len).then(|| (start, end.min(len - 1))),
+            Self::From(start) => (start < len).then(|| (start, len - 1)),
+            Self::Last(suffix) => {
+                (suffix > 0 && len > 0).then(|| (len.saturating_sub(suffix), len - 1))
+            }
+        }
+    }
+}
+
+impl Display for ByteRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FromTo(start, end) => write!(f, "{start}-{end}"),
+            Self::From(start) => write!(f, "{start}-"),
+            Self::Last(suffix) => write!(f, "-{suffix}"),
+        }
+    }
+}
+
+/// The `Range` header, the byte ranges requested.
+///
+/// Only the `bytes` unit is supported, other units are reported as malformed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range {
+    ranges: Vec<ByteRange>,
+}
+
+impl Range {
+    /// Create the header from byte ranges.
+    ///
+    /// # Panics
+    /// Panics if there is no range or a range ends before it starts.
+    pub fn bytes(ranges: impl IntoIterator<Item = ByteRange>) -> Self {
+        let ranges: Vec<ByteRange> = ranges.into_iter().collect();
+        assert!(!ranges.is_empty(), "No byte range");
+        assert!(
+            ranges
+                .iter()
+                .all(|range| !matches!(range, ByteRange::FromTo(start, end) if end < start)),
+            "Byte range ends before it starts"
+        );
+        Self { ranges }
+    }
+
+    /// Return the requested ranges.
+    pub fn ranges(&self) -> &[ByteRange] {
+        &self.ranges
+    }
+
+    /// Resolve the satisfiable ranges against a representation of `len` bytes into inclusive positions.
+    pub fn satisfiable(&self, len: u64) -> Vec<(u64, u64)> {
+        self.ranges
+            .iter()
+            .filter_map(|range| range.resolve(len))
+            .collect()
+    }
+}
+
+impl TypedHeader for Range {
+    fn name() -> HeaderName {
+        header::RANGE
+    }
+
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader> {
+        let parse = |value: &str| {
+            let (unit, specs) = value.split_once('=')?;
+            if !unit.trim().eq_ignore_ascii_case("bytes") {
+                return None;
+            }
+            let mut ranges = Vec::new();
+            for spec in specs
+                .split(',')
+                .map(str::trim)
+                .filter(|spec| !spec.is_empty())
+            {
+                let (start, end) = spec.split_once('-')?;
+                let (start, end) = (start.trim(), end.trim());
+                ranges.push(match (start.is_empty(), end.is_empty()) {
+                    (true, _) => ByteRange::Last(digits(end)?),
+                    (false, true) => ByteRange::From(digits(start)?),
+                    (false, false) => {
+                        let (start, end) = (digits(start)?, digits(end)?);
+                        if end < start {
+                            return None;
+                        }
+                        ByteRange::FromTo(start, end)
+                    }
+                });
+            }
+            (!ranges.is_empty()).then_some(Self { ranges })
+        };
+        single(values)
+            .and_then(parse)
+            .ok_or_else(InvalidHeader::new::<Self>)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        let ranges: Vec<String> = self.ranges.iter().map(ToString::to_string).collect();
+        HeaderValue::from_str(&format!("bytes={}", ranges.join(","))).unwrap()
+    }
+}
+
+/// The `Content-Range` header, the range of bytes enclosed in a partial response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentRange {
+    range: Option<(u64, u64)>,
+    complete_length: Option<u64>,
+}
+
+impl ContentRange {
+    /// Create the header for the inclusive range from `start` to `end` of a representation of
+    /// `complete_length` bytes, or of an unknown length.
+    ///
+    /// # Panics
+    /// Panics if the range ends before it starts or after the representation ends.
+    pub fn bytes(start: u64, end: u64, complete_length: Option<u64>) -> Self {
+        assert!(start <= end, "Byte range ends before it starts");
+        assert!(
+            complete_length.is_none_or(|length| end < length),
+            "Byte range ends after the representation"
+        );
+        Self {
+            range: Some((start, end)),
+            complete_length,
+        }
+    }
+
+    /// Create the header of a `416 Range Not Satisfiable` response.
+    pub fn unsatisfied(complete_length: u64) -> Self {
+        Self {
+            range: None,
+            complete_length: Some(complete_length),
+        }
+    }
+
+    /// Return the enclosed range, `None` if the requested ranges were unsatisfiable.
+    pub fn range(&self) -> Option<(u64, u64)> {
+        self.range
+    }
+
+    /// Return the length of the whole representation, `None` if it is unknown.
+    pub fn complete_length(&self) -> Option<u64> {
+        self.complete_length
+    }
+}
+
+impl TypedHeader for ContentRange {
+    fn name() -> HeaderName {
+        header::CONTENT_RANGE
+    }
+
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader> {
+        let parse = |value: &str| {
+            let (unit, rest) = value.split_once(' ')?;
+            if !unit.eq_ignore_ascii_case("bytes") {
+                return None;
+            }
+            let (range, length) = rest.trim().split_once('/')?;
+            let complete_length = match length {
+                "*" => None,
+                length => Some(digits(length)?),
+            };
+            if range == "*" {
+                return complete_length.map(Self::unsatisfied);
+            }
+            let (start, end) = range.split_once('-')?;
+            let (start, end) = (digits(start)?, digits(end)?);
+            (start <= end && complete_length.is_none_or(|length| end < length)).then_some(Self {
+                range: Some((start, end)),
+                complete_length,
+            })
+        };
+        single(values)
+            .and_then(parse)
+            .ok_or_else(InvalidHeader::new::<Self>)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        let range = match self.range {
+            Some((start, end)) => format!("{start}-{end}"),
+            None => "*".to_owned(),
+        };
+        let length = match self.complete_length {
+            Some(length) => length.to_string(),
+            None => "*".to_owned(),
+        };
+        HeaderValue::from_str(&format!("bytes {range}/{length}")).unwrap()
+    }
+}
+
+/// An entity tag, the opaque validator of a representation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntityTag {
+    weak: bool,
+    tag: String,
+}
+
+impl EntityTag {
+    /// Create a strong entity tag, `tag` is given without quotes.
+    ///
+    /// # Panics
+    /// Panics if `tag` contains a quote or characters not allowed in an entity tag.
+    pub fn strong(tag: impl Into<String>) -> Self {
+        Self::new(false, tag.into())
+    }
+
+    /// Create a weak entity tag, `tag` is given without quotes.
+    ///
+    /// # Panics
+    /// Panics if `tag` contains a quote or characters not allowed in an entity tag.
+    pub fn weak(tag: impl Into<String>) -> Self {
+        Self::new(true, tag.into())
+    }
+
+    fn new(weak: bool, tag: String) -> Self {
+        assert!(is_opaque_tag(&tag), "Invalid entity tag `{tag}`");
+        Self { weak, tag }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let (weak, value) = match value.strip_prefix("W/") {
+            Some(value) => (true, value),
+            None => (false, value),
+        };
+        let tag = value.strip_prefix('"')?.strip_suffix('"')?;
+        is_opaque_tag(tag).then(|| Self {
+            weak,
+            tag: tag.to_owned(),
+        })
+    }
+
+    /// Return the opaque tag without quotes.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Return `true` if the entity tag is weak.
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// Compare with the strong comparison, both entity tags must be strong and identical.
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Compare with the weak comparison, the opaque tags must be identical.
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+fn is_opaque_tag(tag: &str) -> bool {
+    tag.bytes()
+        .all(|byte| byte == 0x21 || (0x23..=0x7E).contains(&byte))
+}
+
+impl Display for EntityTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+/// The `ETag` header, the entity tag of the representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ETag(pub EntityTag);
+
+impl TypedHeader for ETag {
+    fn name() -> HeaderName {
+        header::ETAG
+    }
+
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader> {
+        single(values)
+            .and_then(EntityTag::parse)
+            .map(Self)
+            .ok_or_else(InvalidHeader::new::<Self>)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from_str(&self.0.to_string()).unwrap()
+    }
+}
+
+// Decode `*` or a list of entity tags.
+fn entity_tags<'a>(
+    values: impl Iterator<Item = &'a HeaderValue>,
+) -> Option<Option<Vec<EntityTag>>> {
+    match list(values)?[..] {
+        [] => None,
+        ["*"] => Some(None),
+        ref tags => tags
+            .iter()
+            .map(|tag| EntityTag::parse(tag))
+            .collect::<Option<_>>()
+            .map(Some),
+    }
+}
+
+fn encode_entity_tags(tags: Option<&[EntityTag]>) -> HeaderValue {
+    let value = match tags {
+        None => "*".to_owned(),
+        Some(tags) => {
+            let tags: Vec<String> = tags.iter().map(ToString::to_string).collect();
+            tags.join(", ")
+        }
+    };
+    HeaderValue::from_str(&value).unwrap()
+}
+
+/// The `If-Match` header, a precondition on the current entity tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfMatch {
+    /// `*`, matching any current representation.
+    Any,
+    /// A list of entity tags.
+    Tags(Vec<EntityTag>),
+}
+
+impl IfMatch {
+    /// Return `true` if the current entity tag passes the precondition, by the strong comparison.
+    pub fn matches(&self, etag: &EntityTag) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Tags(tags) => tags.iter().any(|tag| tag.strong_eq(etag)),
+        }
+    }
+}
+
+impl TypedHeader for IfMatch {
+    fn name() -> HeaderName {
+        header::IF_MATCH
+    }
+
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader> {
+        entity_tags(values)
+            .map(|tags| tags.map_or(Self::Any, Self::Tags))
+            .ok_or_else(InvalidHeader::new::<Self>)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        match self {
+            Self::Any => encode_entity_tags(None),
+            Self::Tags(tags) => encode_entity_tags(Some(tags)),
+        }
+    }
+}
+
+/// The `If-None-Match` header, a precondition on the current entity tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfNoneMatch {
+    /// `*`, matching any current representation.
+    Any,
+    /// A list of entity tags.
+    Tags(Vec<EntityTag>),
+}
+
+impl IfNoneMatch {
+    /// Return `true` if the current entity tag is matched by the weak comparison,
+    /// in which case the precondition fails.
+    pub fn matches(&self, etag: &EntityTag) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Tags(tags) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        }
+    }
+}
+
+impl TypedHeader for IfNoneMatch {
+    fn name() -> HeaderName {
+        header::IF_NONE_MATCH
+    }
+
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader> {
+        entity_tags(values)
+            .map(|tags| tags.map_or(Self::Any, Self::Tags))
+            .ok_or_else(InvalidHeader::new::<Self>)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        match self {
+            Self::Any => encode_entity_tags(None),
+            Self::Tags(tags) => encode_entity_tags(Some(tags)),
+        }
+    }
+}
+
+fn secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn decode_date<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Option<SystemTime> {
+    parse_date(single(values)?)
+}
+
+fn parse_date(value: &str) -> Option<SystemTime> {
+    UNIX_EPOCH.checked_add(Duration::from_secs(parse_http_date(value)?))
+}
+
+// Sub-second precision is lost and times before the epoch are clamped to it.
+fn encode_date(time: SystemTime) -> HeaderValue {
+    HeaderValue::from_str(&format_http_date(secs(time))).unwrap()
+}
+
+/// The `If-Range` header, the validator a range request is conditional on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfRange {
+    /// An entity tag.
+    ETag(EntityTag),
+    /// A last modification time.
+    Date(SystemTime),
+}
+
+impl IfRange {
+    /// Return `true` if the ranges should be served for the current validators,
+    /// by the strong comparison of entity tags or an exact match of dates.
+    pub fn matches(&self, etag: Option<&EntityTag>, modified: Option<SystemTime>) -> bool {
+        match self {
+            Self::ETag(tag) => etag.is_some_and(|etag| tag.strong_eq(etag)),
+            Self::Date(date) => modified.is_some_and(|modified| secs(modified) == secs(*date)),
+        }
+    }
+}
+
+impl TypedHeader for IfRange {
+    fn name() -> HeaderName {
+        header::IF_RANGE
+    }
+
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader> {
+        let value = single(values).ok_or_else(InvalidHeader::new::<Self>)?;
+        EntityTag::parse(value)
+            .map(Self::ETag)
+            .or_else(|| parse_date(value).map(Self::Date))
+            .ok_or_else(InvalidHeader::new::<Self>)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        match self {
+            Self::ETag(tag) => HeaderValue::from_str(&tag.to_string()).unwrap(),
+            Self::Date(date) => encode_date(*date),
+        }
+    }
+}
+
+macro_rules! date_header {
+    ($(#[$doc:meta])* $ty:ident, $name:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $ty(pub SystemTime);
+
+        impl TypedHeader for $ty {
+            fn name() -> HeaderName {
+                header::$name
+            }
+
+            fn decode<'a>(
+                values: impl Iterator<Item = &'a HeaderValue>,
+            ) -> Result<Self, InvalidHeader> {
+                decode_date(values)
+                    .map(Self)
+                    .ok_or_else(InvalidHeader::new::<Self>)
+            }
+
+            fn encode(&self) -> HeaderValue {
+                encode_date(self.0)
+            }
+        }
+    };
+}
+
+date_header!(
+    /// The `Date` header, the time the message was originated.
+    Date,
+    DATE
+);
+
+date_header!(
+    /// The `Expires` header, the time after which the response is stale.
+    Expires,
+    EXPIRES
+);
+
+date_header!(
+    /// The `Last-Modified` header, the time the representation was last modified.
+    LastModified,
+    LAST_MODIFIED
+);
+
+date_header!(
+    /// The `If-Modified-Since` header, a precondition on the last modification time.
+    IfModifiedSince,
+    IF_MODIFIED_SINCE
+);
+
+date_header!(
+    /// The `If-Unmodified-Since` header, a precondition on the last modification time.
+    IfUnmodifiedSince,
+    IF_UNMODIFIED_SINCE
+);
+
+/// The `Allow` header, the methods supported by the target resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allow(pub Vec<Method>);
+
+impl TypedHeader for Allow {
+    fn name() -> HeaderName {
+        header::ALLOW
+    }
+
+    fn decode<'a>(values: impl Iterator<Item = &'a HeaderValue>) -> Result<Self, InvalidHeader> {
+        list(values)
+            .and_then(|methods| {
+                methods
+                    .into_iter()
+                    .map(|method| Method::from_bytes(method.as_bytes()).ok())
+                    .collect()
+            })
+            .map(Self)
+            .ok_or_else(InvalidHeader::new::<Self>)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        let methods: Vec<&str> = self.0.iter().map(Method::as_str).collect();
+        HeaderValue::from_str(&methods.join(", ")).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use http::{header, HeaderValue, Method, StatusCode};
+
+    use super::{
+        base64_decode, base64_encode, Allow, Authorization, ByteRange, CacheControl, ContentRange,
+        ETag, EntityTag, IfMatch, IfModifiedSince, IfNoneMatch, IfRange, LastModified, Range,
+        TypedHeader,
+    };
+    use crate::Request;
+
+    fn round_trip<H: TypedHeader + PartialEq + std::fmt::Debug>(header: H, value: &str) {
+        let encoded = header.encode();
+        assert_eq!(encoded, value);
+        assert_eq!(H::decode(std::iter::once(&encoded)).unwrap(), header);
+    }
+
+    fn decode<H: TypedHeader>(values: &[&'static str]) -> Option<H> {
+        let values: Vec<HeaderValue> = values
+            .iter()
+            .map(|value| HeaderValue::from_static(value))
+            .collect();
+        H::decode(values.iter()).ok()
+    }
+
+    #[test]
+    fn base64_padding() {
+        for (input, output) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64_encode(input.as_bytes()), output);
+            assert_eq!(base64_decode(output).unwrap(), input.as_bytes());
+        }
+    }
+
+    #[test]
+    fn base64_invalid() {
+        for input in [
+            "Zg", "Zm8", "Zg=", "Z===", "Zg===", "Zm8==", "Zh==", "Zm9=", "Zg==Zg==", "=Zg=",
+            "Zm9v!", "Zm 9", "Zm-_",
+        ] {
+            assert_eq!(base64_decode(input), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn authorization() {
+        let basic = Authorization::basic("user", "pass:word é");
+        assert_eq!(basic.credentials(), "dXNlcjpwYXNzOndvcmQgw6k=");
+        assert_eq!(
+            basic.as_basic(),
+            Some(("user".to_owned(), "pass:word é".to_owned()))
+        );
+        let encoded = basic.encode();
+        assert!(encoded.is_sensitive());
+        assert_eq!(
+            Authorization::decode(std::iter::once(&encoded)).unwrap(),
+            basic
+        );
+
+        let unpadded = decode::<Authorization>(&["Basic dXNlcjpwYQ"]).unwrap();
+        assert_eq!(unpadded.as_basic(), None);
+        let bearer = decode::<Authorization>(&["bearer abc"]).unwrap();
+        assert_eq!(bearer.as_bearer(), Some("abc"));
+        assert!(decode::<Authorization>(&["B@sic abc"]).is_none());
+    }
+
+    #[test]
+    fn entity_tags() {
+        round_trip(ETag(EntityTag::strong("abc")), "\"abc\"");
+        round_trip(ETag(EntityTag::weak("abc")), "W/\"abc\"");
+        assert!(decode::<ETag>(&["abc"]).is_none());
+        assert!(decode::<ETag>(&["\"a\"", "\"b\""]).is_none());
+
+        round_trip(IfNoneMatch::Any, "*");
+        round_trip(
+            IfNoneMatch::Tags(vec![EntityTag::weak("a"), EntityTag::strong("b,c")]),
+            "W/\"a\", \"b,c\"",
+        );
+        let if_none_match = decode::<IfNoneMatch>(&["W/\"a\"", "\"b\""]).unwrap();
+        assert!(if_none_match.matches(&EntityTag::strong("a")));
+        assert!(!if_none_match.matches(&EntityTag::strong("c")));
+        assert!(decode::<IfNoneMatch>(&["*, \"a\""]).is_none());
+
+        let if_match = decode::<IfMatch>(&["W/\"a\", \"b\""]).unwrap();
+        assert!(!if_match.matches(&EntityTag::strong("a")));
+        assert!(if_match.matches(&EntityTag::strong("b")));
+    }
+
+    #[test]
+    fn if_range() {
+        let modified = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        round_trip(IfRange::ETag(EntityTag::strong("a")), "\"a\"");
+        round_trip(IfRange::Date(modified), "Sun, 06 Nov 1994 08:49:37 GMT");
+
+        let if_range = decode::<IfRange>(&["\"a\""]).unwrap();
+        assert!(if_range.matches(Some(&EntityTag::strong("a")), None));
+        assert!(!if_range.matches(Some(&EntityTag::weak("a")), None));
+        let if_range = decode::<IfRange>(&["W/\"a\""]).unwrap();
+        assert!(!if_range.matches(Some(&EntityTag::strong("a")), None));
+        let if_range = IfRange::Date(modified);
+        assert!(if_range.matches(None, Some(modified + Duration::from_millis(500))));
+        assert!(!if_range.matches(None, Some(modified + Duration::from_secs(1))));
+        assert!(decode::<IfRange>(&["tomorrow"]).is_none());
+    }
+
+    #[test]
+    fn dates() {
+        let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        round_trip(LastModified(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(
+            decode::<IfModifiedSince>(&["Sunday, 06-Nov-94 08:49:37 GMT"]),
+            Some(IfModifiedSince(time))
+        );
+        assert_eq!(
+            LastModified(time + Duration::from_millis(999)).encode(),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert!(decode::<LastModified>(&["yesterday"]).is_none());
+    }
+
+    #[test]
+    fn invalid_dates_are_bad_requests() {
+        for value in [
+            "Sun, 06 Nov 500000000000 08:49:37 GMT",
+            "Sat, 31 Feb 2024 08:49:37 GMT",
+        ] {
+            let request = Request::get("/").header(header::IF_MODIFIED_SINCE, value);
+            let error = request.typed_header::<IfModifiedSince>().unwrap_err();
+            assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+            let request = Request::get("/").header(header::IF_RANGE, value);
+            assert!(request.typed_header::<IfRange>().is_err());
+        }
+    }
+
+    #[test]
+    fn cache_control() {
+        round_trip(
+            CacheControl::new()
+                .no_cache()
+                .public()
+                .max_age(Duration::from_secs(60)),
+            "no-cache, public, max-age=60",
+        );
+        let cache_control =
+            decode::<CacheControl>(&["private=\"a, b\", max-stale", "s-maxage=\"10\""]).unwrap();
+        assert!(cache_control.is_private());
+        assert_eq!(cache_control.get_max_stale(), Some(Duration::MAX));
+        assert_eq!(cache_control.get_s_maxage(), Some(Duration::from_secs(10)));
+        assert!(decode::<CacheControl>(&["max-age=-1"]).is_none());
+    }
+
+    #[test]
+    fn ranges() {
+        round_trip(
+            Range::bytes([
+                ByteRange::FromTo(0, 9),
+                ByteRange::From(10),
+                ByteRange::Last(5),
+            ]),
+            "bytes=0-9,10-,-5",
+        );
+        let range = decode::<Range>(&["bytes=5-2"]);
+        assert!(range.is_none());
+        assert!(decode::<Range>(&["items=0-1"]).is_none());
+        assert!(decode::<Range>(&["bytes=+1-2"]).is_none());
+
+        round_trip(ContentRange::bytes(0, 9, Some(10)), "bytes 0-9/10");
+        round_trip(ContentRange::bytes(0, 9, None), "bytes 0-9/*");
+        round_trip(ContentRange::unsatisfied(10), "bytes */10");
+        assert!(decode::<ContentRange>(&["bytes 0-10/10"]).is_none());
+    }
+
+    #[test]
+    fn allow() {
+        round_trip(Allow(vec![Method::GET, Method::HEAD]), "GET, HEAD");
+        assert_eq!(decode::<Allow>(&[""]), Some(Allow(Vec::new())));
+    }
+}